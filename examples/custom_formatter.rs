@@ -1,4 +1,4 @@
-use loggix::{Entry, Formatter, Level, Logger};
+use loggix::{Entry, Formatter, Level, Logger, LoggixError};
 use serde_json::Value;
 use std::collections::HashMap;
 use std::error::Error;
@@ -37,7 +37,7 @@ impl Default for CSVFormatter {
 }
 
 impl Formatter for CSVFormatter {
-    fn format(&self, entry: &Entry) -> Result<Vec<u8>, Box<dyn Error>> {
+    fn format(&self, entry: &Entry) -> Result<Vec<u8>, LoggixError> {
         let mut output = Vec::new();
 
         // Add headers if enabled
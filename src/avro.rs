@@ -0,0 +1,169 @@
+//! Avro encoding of log entries and a minimal Confluent Schema Registry
+//! client.
+//!
+//! Entries are written with a fixed record schema ([`ENTRY_SCHEMA`]) in
+//! the schema-registry wire format: a zero magic byte, the four-byte
+//! big-endian schema ID, then the Avro binary body. The schema is
+//! registered under the configured subject the first time it is needed and
+//! the returned ID is cached.
+
+use crate::backoff::{send_http, Backoff};
+use crate::batch::{block_on_thread, BoxError};
+use crate::{Entry, Level};
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::sync::OnceCell;
+
+/// Avro schema used for log entries
+///
+/// Field values are stored as strings: strings as they are, anything else
+/// as JSON text.
+pub const ENTRY_SCHEMA: &str = r#"{"type":"record","name":"LogEntry","namespace":"loggix","fields":[{"name":"timestamp","type":{"type":"long","logicalType":"timestamp-micros"}},{"name":"level","type":{"type":"enum","name":"Level","symbols":["TRACE","DEBUG","INFO","WARN","ERROR","FATAL","PANIC"]}},{"name":"message","type":"string"},{"name":"fields","type":{"type":"map","values":"string"}}]}"#;
+
+/// Connection to a schema registry, used by Avro-encoded Kafka payloads
+pub struct SchemaRegistry {
+    url: String,
+    subject: Option<String>,
+    basic_auth: Option<(String, String)>,
+    retry: Backoff,
+    timeout: Duration,
+    schema_id: OnceCell<u32>,
+}
+
+#[derive(Deserialize)]
+struct RegisterResponse {
+    id: u32,
+}
+
+impl SchemaRegistry {
+    /// Registry at `url`, e.g. `http://localhost:8081`
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            subject: None,
+            basic_auth: None,
+            retry: Backoff::default(),
+            timeout: Duration::from_secs(10),
+            schema_id: OnceCell::new(),
+        }
+    }
+
+    /// Subject to register the schema under (`<topic>-value` by default)
+    pub fn subject(mut self, subject: &str) -> Self {
+        self.subject = Some(subject.to_string());
+        self
+    }
+
+    /// Authenticate with HTTP basic auth
+    pub fn basic_auth(mut self, username: &str, password: &str) -> Self {
+        self.basic_auth = Some((username.to_string(), password.to_string()));
+        self
+    }
+
+    /// Retry policy for registry requests
+    pub fn retry(mut self, backoff: Backoff) -> Self {
+        self.retry = backoff;
+        self
+    }
+
+    /// Timeout for each registry request (10 seconds by default)
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Use `<topic>-value` as the subject unless one was set
+    pub(crate) fn default_subject(&mut self, topic: &str) {
+        if self.subject.is_none() {
+            self.subject = Some(format!("{}-value", topic));
+        }
+    }
+
+    /// The ID of [`ENTRY_SCHEMA`], registering it on first use
+    pub(crate) async fn schema_id(&self) -> Result<u32, BoxError> {
+        self.schema_id
+            .get_or_try_init(|| self.register())
+            .await
+            .copied()
+    }
+
+    /// Like [`schema_id`](Self::schema_id), for callers without a runtime
+    pub(crate) fn schema_id_blocking(&self) -> Result<u32, BoxError> {
+        if let Some(id) = self.schema_id.get() {
+            return Ok(*id);
+        }
+        block_on_thread(self.schema_id())?
+    }
+
+    async fn register(&self) -> Result<u32, BoxError> {
+        let subject = self.subject.as_deref().unwrap_or("loggix-value");
+        let url = format!("{}/subjects/{}/versions", self.url, subject);
+        let body = serde_json::json!({ "schema": ENTRY_SCHEMA }).to_string();
+        let client = reqwest::Client::builder().timeout(self.timeout).build()?;
+        let response = send_http(&self.retry, || {
+            let request = client
+                .post(&url)
+                .header("Content-Type", "application/vnd.schemaregistry.v1+json")
+                .body(body.clone());
+            match &self.basic_auth {
+                Some((username, password)) => request.basic_auth(username, Some(password)),
+                None => request,
+            }
+        })
+        .await
+        .map_err(|err| format!("schema registration for {} failed: {}", subject, err))?;
+        let registered: RegisterResponse = serde_json::from_slice(&response.bytes().await?)?;
+        Ok(registered.id)
+    }
+}
+
+/// Encode `entry` with [`ENTRY_SCHEMA`] in the wire format for `schema_id`
+pub(crate) fn encode(entry: &Entry, schema_id: u32) -> Vec<u8> {
+    let mut out = Vec::with_capacity(64 + entry.message.len());
+    out.push(0);
+    out.extend_from_slice(&schema_id.to_be_bytes());
+
+    write_long(&mut out, entry.timestamp.timestamp_micros());
+    let symbol = match entry.level {
+        Level::Trace => 0,
+        Level::Debug => 1,
+        Level::Info => 2,
+        Level::Warn => 3,
+        Level::Error => 4,
+        Level::Fatal => 5,
+        Level::Panic => 6,
+    };
+    write_long(&mut out, symbol);
+    write_string(&mut out, &entry.message);
+
+    // A map is written as one block of entries followed by an empty block
+    let mut fields: Vec<_> = entry.fields.iter().collect();
+    fields.sort_by(|a, b| a.0.cmp(b.0));
+    if !fields.is_empty() {
+        write_long(&mut out, fields.len() as i64);
+        for (key, value) in fields {
+            write_string(&mut out, key);
+            match value {
+                serde_json::Value::String(s) => write_string(&mut out, s),
+                other => write_string(&mut out, &other.to_string()),
+            }
+        }
+    }
+    write_long(&mut out, 0);
+    out
+}
+
+/// Zigzag variable-length encoding used for Avro `int` and `long`
+fn write_long(out: &mut Vec<u8>, value: i64) {
+    let mut n = ((value << 1) ^ (value >> 63)) as u64;
+    while n >= 0x80 {
+        out.push((n as u8 & 0x7f) | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+fn write_string(out: &mut Vec<u8>, value: &str) {
+    write_long(out, value.len() as i64);
+    out.extend_from_slice(value.as_bytes());
+}
@@ -0,0 +1,226 @@
+//! Exponential backoff with jitter, and the retrying HTTP send used by the
+//! network hooks.
+
+#[cfg(any(
+    feature = "kafka",
+    feature = "loki",
+    feature = "elasticsearch",
+    feature = "http",
+    feature = "splunk",
+    feature = "fluent"
+))]
+use crate::batch::BoxError;
+use std::collections::hash_map::RandomState;
+#[cfg(any(
+    feature = "kafka",
+    feature = "loki",
+    feature = "elasticsearch",
+    feature = "http",
+    feature = "splunk",
+    feature = "fluent"
+))]
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// Exponential backoff policy for retrying failed deliveries
+///
+/// The delay before retry `n` (starting at 0) is
+/// `initial * multiplier^n`, capped at `max`. With jitter enabled the delay
+/// is drawn uniformly from `[delay / 2, delay]` so that many clients do not
+/// retry in lockstep.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    multiplier: f64,
+    max_retries: u32,
+    jitter: bool,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_millis(100),
+            max: Duration::from_secs(10),
+            multiplier: 2.0,
+            max_retries: 5,
+            jitter: true,
+        }
+    }
+}
+
+impl Backoff {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A policy that never retries
+    pub fn none() -> Self {
+        Self::default().max_retries(0)
+    }
+
+    /// Delay before the first retry
+    pub fn initial(mut self, delay: Duration) -> Self {
+        self.initial = delay;
+        self
+    }
+
+    /// Upper bound for any single delay
+    pub fn max(mut self, delay: Duration) -> Self {
+        self.max = delay;
+        self
+    }
+
+    /// Factor the delay grows by after each retry
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Number of retries after the first attempt
+    pub fn max_retries(mut self, retries: u32) -> Self {
+        self.max_retries = retries;
+        self
+    }
+
+    /// Randomize delays to spread out retries
+    pub fn jitter(mut self, enabled: bool) -> Self {
+        self.jitter = enabled;
+        self
+    }
+
+    /// Number of retries after the first attempt
+    pub fn retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    /// Delay to wait before retry number `retry` (starting at 0)
+    pub fn delay(&self, retry: u32) -> Duration {
+        let exponent = self.multiplier.max(1.0).powi(retry.min(64) as i32);
+        let delay = (self.initial.as_secs_f64() * exponent).min(self.max.as_secs_f64());
+        let delay = if self.jitter {
+            delay * (0.5 + 0.5 * random_fraction())
+        } else {
+            delay
+        };
+        Duration::from_secs_f64(delay.max(0.0))
+    }
+}
+
+/// A uniformly distributed number in `[0, 1)` without pulling in an RNG crate
+fn random_fraction() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos(),
+    );
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Outcome of a single delivery attempt
+#[cfg(any(
+    feature = "kafka",
+    feature = "loki",
+    feature = "elasticsearch",
+    feature = "http",
+    feature = "splunk",
+    feature = "fluent"
+))]
+pub(crate) enum Attempt<T> {
+    Done(T),
+    /// Worth retrying: timeouts, throttling, server errors
+    Transient(BoxError),
+    /// Retrying cannot help: bad request, authentication, payload errors
+    #[cfg_attr(
+        not(any(
+            feature = "kafka",
+            feature = "loki",
+            feature = "elasticsearch",
+            feature = "http",
+            feature = "splunk"
+        )),
+        allow(dead_code)
+    )]
+    Permanent(BoxError),
+}
+
+/// Run `attempt` until it succeeds, fails permanently or retries run out
+#[cfg(any(
+    feature = "kafka",
+    feature = "loki",
+    feature = "elasticsearch",
+    feature = "http",
+    feature = "splunk",
+    feature = "fluent"
+))]
+pub(crate) async fn retry<T, F, Fut>(backoff: &Backoff, mut attempt: F) -> Result<T, BoxError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Attempt<T>>,
+{
+    let mut retry = 0;
+    loop {
+        match attempt().await {
+            Attempt::Done(value) => return Ok(value),
+            Attempt::Permanent(err) => return Err(err),
+            Attempt::Transient(err) if retry >= backoff.retries() => {
+                return Err(format!("{} (gave up after {} retries)", err, retry).into())
+            }
+            Attempt::Transient(_) => {
+                tokio::time::sleep(backoff.delay(retry)).await;
+                retry += 1;
+            }
+        }
+    }
+}
+
+/// Whether an HTTP status is worth retrying
+#[cfg(any(
+    feature = "kafka",
+    feature = "loki",
+    feature = "elasticsearch",
+    feature = "http",
+    feature = "splunk"
+))]
+pub(crate) fn is_retryable_status(status: reqwest::StatusCode) -> bool {
+    status.is_server_error()
+        || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+        || status == reqwest::StatusCode::REQUEST_TIMEOUT
+}
+
+/// Send a request built by `request`, retrying transport errors and
+/// retryable statuses; any other non-2xx status is a permanent failure
+#[cfg(any(
+    feature = "kafka",
+    feature = "loki",
+    feature = "elasticsearch",
+    feature = "http"
+))]
+pub(crate) async fn send_http<F>(
+    backoff: &Backoff,
+    request: F,
+) -> Result<reqwest::Response, BoxError>
+where
+    F: Fn() -> reqwest::RequestBuilder,
+{
+    retry(backoff, || async {
+        match request().send().await {
+            Ok(response) if response.status().is_success() => Attempt::Done(response),
+            Ok(response) => {
+                let status = response.status();
+                let body = response.text().await.unwrap_or_default();
+                let err = format!("server responded with {}: {}", status, body.trim()).into();
+                if is_retryable_status(status) {
+                    Attempt::Transient(err)
+                } else {
+                    Attempt::Permanent(err)
+                }
+            }
+            Err(err) => Attempt::Transient(err.into()),
+        }
+    })
+    .await
+}
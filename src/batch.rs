@@ -0,0 +1,358 @@
+//! Background batching shared by the network hooks.
+//!
+//! A hook hands items to a [`BatchWorker`], which owns a thread running a
+//! single-threaded tokio runtime. Items are collected until the batch reaches
+//! its item or byte limit, or until the oldest item has waited for the
+//! maximum latency, and are then passed to the hook's [`Export`]
+//! implementation. Logging threads only pay for a channel send.
+
+use crate::LoggixError;
+use lazy_static::lazy_static;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::mpsc as std_mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tokio::runtime::{Handle, Runtime, RuntimeFlavor};
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::Instant;
+
+/// Error type used on the background side of network hooks
+pub(crate) type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Run `future` to completion on a fresh thread with its own runtime
+///
+/// For one-off async work, such as registering a schema, from sync code
+/// that may or may not already be running inside a runtime.
+pub(crate) fn block_on_thread<F>(future: F) -> Result<F::Output, BoxError>
+where
+    F: Future + Send,
+    F::Output: Send,
+{
+    std::thread::scope(|scope| {
+        scope
+            .spawn(|| {
+                let runtime = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()?;
+                Ok(runtime.block_on(future))
+            })
+            .join()
+            .map_err(|_| BoxError::from("background task panicked"))?
+    })
+}
+
+lazy_static! {
+    /// Runtime for hooks driven from sync code, started on first use
+    static ref SHARED_RUNTIME: Result<Runtime, String> = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .thread_name("loggix-hooks")
+        .enable_all()
+        .build()
+        .map_err(|e| e.to_string());
+}
+
+/// Run `future` to completion on a runtime shared by all callers
+///
+/// For async work on a hot path, such as firing an async-only hook from
+/// [`Logger::log`](crate::Logger::log). Outside a runtime the calling thread
+/// drives the future; inside a multi-threaded runtime it blocks in place, and
+/// only a current-thread runtime needs a helper thread.
+pub(crate) fn block_on_shared<F>(future: F) -> Result<F::Output, BoxError>
+where
+    F: Future + Send,
+    F::Output: Send,
+{
+    let runtime = SHARED_RUNTIME
+        .as_ref()
+        .map_err(|e| BoxError::from(e.clone()))?;
+    match Handle::try_current() {
+        Err(_) => Ok(runtime.block_on(future)),
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+            Ok(tokio::task::block_in_place(|| runtime.block_on(future)))
+        }
+        Ok(_) => std::thread::scope(|scope| {
+            scope
+                .spawn(|| runtime.block_on(future))
+                .join()
+                .map_err(|_| BoxError::from("background task panicked"))
+        }),
+    }
+}
+
+/// Future returned by [`Export::export`]
+pub(crate) type ExportFuture<'a> = Pin<Box<dyn Future<Output = Result<(), BoxError>> + Send + 'a>>;
+
+/// Delivers a batch of items to a destination
+pub(crate) trait Export<T>: Send + Sync + 'static {
+    fn export(&self, batch: Vec<T>) -> ExportFuture<'_>;
+}
+
+/// Limits that decide when a batch is handed to the exporter
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BatchConfig {
+    /// Maximum number of items per batch
+    pub(crate) max_items: usize,
+    /// Maximum encoded size of a batch in bytes, `0` for no limit
+    pub(crate) max_bytes: usize,
+    /// Maximum time the oldest item waits before the batch is sent
+    pub(crate) max_latency: Duration,
+    /// Number of items that may be queued before new ones are rejected
+    pub(crate) queue_capacity: usize,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            max_items: 512,
+            max_bytes: 0,
+            max_latency: Duration::from_secs(1),
+            queue_capacity: 8192,
+        }
+    }
+}
+
+enum Command<T> {
+    Item(T, usize),
+    Flush(Ack),
+}
+
+/// Tells a flushing caller that the queue ahead of it has been sent
+enum Ack {
+    Blocking(std_mpsc::Sender<()>),
+    Async(oneshot::Sender<()>),
+}
+
+impl Ack {
+    /// Wake the caller, unless it has given up waiting
+    fn send(self) {
+        match self {
+            Ack::Blocking(ack) => {
+                let _ = ack.send(());
+            }
+            Ack::Async(ack) => {
+                let _ = ack.send(());
+            }
+        }
+    }
+}
+
+/// Handle to a background batching thread
+pub(crate) struct BatchWorker<T: Send + 'static> {
+    name: &'static str,
+    /// The last export failure, until it is reported
+    failure: Arc<Mutex<Option<String>>>,
+    sender: Option<mpsc::Sender<Command<T>>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl<T: Send + 'static> BatchWorker<T> {
+    /// Spawn the worker thread for a hook called `name`
+    pub(crate) fn spawn<E: Export<T>>(
+        name: &'static str,
+        config: BatchConfig,
+        exporter: E,
+    ) -> Result<Self, LoggixError> {
+        let (sender, receiver) = mpsc::channel(config.queue_capacity.max(1));
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let exporter = Arc::new(exporter);
+        let failure = Arc::new(Mutex::new(None));
+        let worker_failure = Arc::clone(&failure);
+        let thread = std::thread::Builder::new()
+            .name(format!("loggix-{}", name))
+            .spawn(move || {
+                runtime.block_on(run(name, config, exporter, worker_failure, receiver))
+            })?;
+
+        Ok(Self {
+            name,
+            failure,
+            sender: Some(sender),
+            thread: Some(thread),
+        })
+    }
+
+    /// Queue an item without blocking; `size` counts towards the byte limit
+    pub(crate) fn push(&self, item: T, size: usize) -> Result<(), LoggixError> {
+        let sender = self.sender()?;
+        sender
+            .try_send(Command::Item(item, size))
+            .map_err(|err| match err {
+                mpsc::error::TrySendError::Full(_) => {
+                    LoggixError::hook(self.name, "queue is full, dropping entry")
+                }
+                mpsc::error::TrySendError::Closed(_) => {
+                    LoggixError::hook(self.name, "worker has stopped")
+                }
+            })
+    }
+
+    /// Send everything queued so far and wait up to `timeout` for it
+    ///
+    /// Fails if a batch could not be delivered since the last report.
+    pub(crate) fn flush(&self, timeout: Duration) -> Result<(), LoggixError> {
+        let sender = self.sender()?;
+        let (ack, done) = std_mpsc::channel();
+        let deadline = std::time::Instant::now() + timeout;
+        // The queue may be full, so keep retrying until the deadline
+        let mut command = Command::Flush(Ack::Blocking(ack));
+        loop {
+            match sender.try_send(command) {
+                Ok(()) => break,
+                Err(mpsc::error::TrySendError::Full(returned)) => {
+                    if std::time::Instant::now() >= deadline {
+                        return Err(LoggixError::hook(self.name, "flush timed out"));
+                    }
+                    command = returned;
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+                Err(mpsc::error::TrySendError::Closed(_)) => {
+                    return Err(LoggixError::hook(self.name, "worker has stopped"));
+                }
+            }
+        }
+        let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+        done.recv_timeout(remaining)
+            .map_err(|_| LoggixError::hook(self.name, "flush timed out"))?;
+        self.take_failure()
+    }
+
+    /// Like [`flush`](Self::flush), without blocking the calling thread
+    pub(crate) async fn flush_async(&self, timeout: Duration) -> Result<(), LoggixError> {
+        let sender = self.sender()?;
+        let (ack, done) = oneshot::channel();
+        let flushed = async {
+            sender
+                .send(Command::Flush(Ack::Async(ack)))
+                .await
+                .map_err(|_| LoggixError::hook(self.name, "worker has stopped"))?;
+            done.await
+                .map_err(|_| LoggixError::hook(self.name, "worker has stopped"))
+        };
+        tokio::time::timeout(timeout, flushed)
+            .await
+            .map_err(|_| LoggixError::hook(self.name, "flush timed out"))??;
+        self.take_failure()
+    }
+
+    /// Report a batch that could not be delivered, once
+    pub(crate) fn take_failure(&self) -> Result<(), LoggixError> {
+        let failure = self
+            .failure
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .take();
+        match failure {
+            Some(err) => Err(LoggixError::hook(
+                self.name,
+                format!("delivery failed: {}", err),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    fn sender(&self) -> Result<&mpsc::Sender<Command<T>>, LoggixError> {
+        self.sender
+            .as_ref()
+            .ok_or_else(|| LoggixError::hook(self.name, "worker has shut down"))
+    }
+}
+
+impl<T: Send + 'static> Drop for BatchWorker<T> {
+    fn drop(&mut self) {
+        // Closing the channel makes the worker send what it has and exit
+        self.sender.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+async fn run<T, E: Export<T>>(
+    name: &'static str,
+    config: BatchConfig,
+    exporter: Arc<E>,
+    failure: Arc<Mutex<Option<String>>>,
+    mut receiver: mpsc::Receiver<Command<T>>,
+) {
+    let failure = &*failure;
+    let mut batch = Vec::new();
+    let mut batch_bytes = 0;
+    let mut deadline: Option<Instant> = None;
+
+    loop {
+        let command = match deadline {
+            Some(at) => match tokio::time::timeout_at(at, receiver.recv()).await {
+                Ok(command) => command,
+                Err(_) => {
+                    send(name, &*exporter, failure, &mut batch, &mut batch_bytes).await;
+                    update_deadline(&mut deadline, &batch, config);
+                    continue;
+                }
+            },
+            None => receiver.recv().await,
+        };
+
+        match command {
+            Some(Command::Item(item, size)) => {
+                if config.max_bytes > 0
+                    && !batch.is_empty()
+                    && batch_bytes + size > config.max_bytes
+                {
+                    send(name, &*exporter, failure, &mut batch, &mut batch_bytes).await;
+                    deadline = None;
+                }
+                batch.push(item);
+                batch_bytes += size;
+                if batch.len() >= config.max_items.max(1)
+                    || (config.max_bytes > 0 && batch_bytes >= config.max_bytes)
+                {
+                    send(name, &*exporter, failure, &mut batch, &mut batch_bytes).await;
+                }
+                update_deadline(&mut deadline, &batch, config);
+            }
+            Some(Command::Flush(ack)) => {
+                send(name, &*exporter, failure, &mut batch, &mut batch_bytes).await;
+                deadline = None;
+                ack.send();
+            }
+            None => {
+                send(name, &*exporter, failure, &mut batch, &mut batch_bytes).await;
+                break;
+            }
+        }
+    }
+}
+
+/// Start the latency timer for a new batch, or clear it once the batch is sent
+fn update_deadline<T>(deadline: &mut Option<Instant>, batch: &[T], config: BatchConfig) {
+    if batch.is_empty() {
+        *deadline = None;
+    } else if deadline.is_none() {
+        *deadline = Some(Instant::now() + config.max_latency);
+    }
+}
+
+async fn send<T, E: Export<T>>(
+    name: &'static str,
+    exporter: &E,
+    failure: &Mutex<Option<String>>,
+    batch: &mut Vec<T>,
+    batch_bytes: &mut usize,
+) {
+    if batch.is_empty() {
+        return;
+    }
+    *batch_bytes = 0;
+    let count = batch.len();
+    if let Err(e) = exporter.export(std::mem::take(batch)).await {
+        let lost = format!("{} entries lost: {}", count, e);
+        *failure
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(lost.clone());
+        crate::handle_error(LoggixError::hook(name, lost));
+    }
+}
@@ -0,0 +1,159 @@
+//! Batching adapter for any hook.
+//!
+//! [`BatchingHook`] queues entries as [`Record`]s and hands them to the
+//! wrapped hook's [`fire_batch_async`](Hook::fire_batch_async) from a
+//! background worker, once a batch reaches its entry count or byte size or
+//! its oldest entry has waited long enough. Hooks that do not implement
+//! batching get the entries one by one through the trait's default.
+
+use crate::batch::{BatchConfig, BatchWorker, BoxError, Export, ExportFuture};
+use crate::{Entry, Hook, Level, LoggixError, Record};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Builder for [`BatchingHook`]
+pub struct BatchingHookBuilder {
+    hook: Box<dyn Hook>,
+    batch: BatchConfig,
+}
+
+impl BatchingHookBuilder {
+    /// Maximum number of entries per batch (512 by default)
+    pub fn batch_size(mut self, size: usize) -> Self {
+        self.batch.max_items = size;
+        self
+    }
+
+    /// Maximum size of a batch, counting entries as JSON (no limit by
+    /// default)
+    pub fn batch_bytes(mut self, bytes: usize) -> Self {
+        self.batch.max_bytes = bytes;
+        self
+    }
+
+    /// Maximum time an entry waits before its batch is sent (1s by default)
+    pub fn batch_timeout(mut self, timeout: Duration) -> Self {
+        self.batch.max_latency = timeout;
+        self
+    }
+
+    /// Number of entries that may wait for delivery before new ones are
+    /// rejected (8192 by default)
+    pub fn queue_capacity(mut self, capacity: usize) -> Self {
+        self.batch.queue_capacity = capacity;
+        self
+    }
+
+    /// Start the delivery worker and create the hook
+    pub fn build(self) -> Result<BatchingHook, LoggixError> {
+        let levels = self.hook.levels();
+        let hook: Arc<dyn Hook> = Arc::from(self.hook);
+        let exporter = BatchExporter {
+            hook: Arc::clone(&hook),
+        };
+        let worker = BatchWorker::spawn("BatchingHook", self.batch, exporter)?;
+        Ok(BatchingHook {
+            levels,
+            hook,
+            worker,
+        })
+    }
+}
+
+/// A hook that delivers entries to another hook in batches
+///
+/// `fire` only queues the entry. If a batch could not be delivered, the
+/// error is returned from the next `fire` or `flush`. Dropping the hook
+/// delivers what is still queued.
+pub struct BatchingHook {
+    levels: Vec<Level>,
+    hook: Arc<dyn Hook>,
+    worker: BatchWorker<Record>,
+}
+
+impl BatchingHook {
+    /// Start building a hook that batches entries for `hook`
+    pub fn builder<H: Hook + 'static>(hook: H) -> BatchingHookBuilder {
+        BatchingHookBuilder {
+            hook: Box::new(hook),
+            batch: BatchConfig::default(),
+        }
+    }
+
+    /// Deliver everything queued so far, then flush the wrapped hook,
+    /// waiting up to `timeout`
+    pub fn flush(&self, timeout: Duration) -> Result<(), LoggixError> {
+        let deadline = Instant::now() + timeout;
+        self.worker.flush(timeout)?;
+        self.hook
+            .flush(deadline.saturating_duration_since(Instant::now()))
+    }
+
+    fn queue(&self, record: Record) -> Result<(), LoggixError> {
+        let size = serde_json::to_vec(&record)?.len();
+        self.worker.push(record, size)
+    }
+}
+
+impl Hook for BatchingHook {
+    fn levels(&self) -> Vec<Level> {
+        self.levels.clone()
+    }
+
+    fn requires_async(&self) -> bool {
+        self.hook.requires_async()
+    }
+
+    fn fire(&self, entry: &Entry) -> Result<(), LoggixError> {
+        self.queue(Record::from(entry))?;
+        self.worker.take_failure()
+    }
+
+    fn fire_batch(&self, records: &[Record]) -> Result<(), LoggixError> {
+        for record in records {
+            self.queue(record.clone())?;
+        }
+        self.worker.take_failure()
+    }
+
+    fn fire_batch_async<'a>(
+        &'a self,
+        records: &'a [Record],
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), LoggixError>> + Send + 'a>>
+    {
+        Box::pin(async move { self.fire_batch(records) })
+    }
+
+    fn flush(&self, timeout: Duration) -> Result<(), LoggixError> {
+        BatchingHook::flush(self, timeout)
+    }
+
+    fn flush_async(
+        &self,
+        timeout: Duration,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), LoggixError>> + Send + '_>>
+    {
+        Box::pin(async move {
+            let deadline = Instant::now() + timeout;
+            self.worker.flush_async(timeout).await?;
+            self.hook
+                .flush_async(deadline.saturating_duration_since(Instant::now()))
+                .await
+        })
+    }
+}
+
+struct BatchExporter {
+    hook: Arc<dyn Hook>,
+}
+
+impl Export<Record> for BatchExporter {
+    fn export(&self, batch: Vec<Record>) -> ExportFuture<'_> {
+        Box::pin(async move {
+            self.hook
+                .fire_batch_async(&batch)
+                .await
+                .map_err(BoxError::from)
+        })
+    }
+}
@@ -0,0 +1,226 @@
+//! Circuit breaker wrapper for any hook.
+//!
+//! [`CircuitBreakerHook`] counts consecutive failures of the wrapped hook.
+//! After too many, the circuit opens: entries are rejected at once with
+//! [`CircuitOpen`] instead of waiting on a dead destination. When the
+//! cool-down has passed, single probe entries are let through; enough
+//! successful probes close the circuit again, a failed one reopens it.
+
+use crate::retry::is_permanent;
+use crate::{Entry, Hook, Level, LoggixError};
+use std::error::Error;
+use std::fmt;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// State of a [`CircuitBreakerHook`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Entries go to the wrapped hook
+    Closed,
+    /// Entries are rejected until the cool-down has passed
+    Open,
+    /// Probe entries test whether the wrapped hook has recovered
+    HalfOpen,
+}
+
+/// The error returned for entries rejected by an open circuit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CircuitOpen {
+    /// Time until the next probe is let through
+    pub retry_in: Duration,
+}
+
+impl fmt::Display for CircuitOpen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "circuit open, next probe in {}ms",
+            self.retry_in.as_millis()
+        )
+    }
+}
+
+impl Error for CircuitOpen {}
+
+enum Breaker {
+    Closed {
+        failures: u32,
+    },
+    Open {
+        until: Instant,
+    },
+    HalfOpen {
+        probe: Option<Instant>,
+        successes: u32,
+    },
+}
+
+/// A hook that stops calling the wrapped hook while it keeps failing
+///
+/// Failures marked with [`PermanentError`](crate::PermanentError) mean the
+/// destination answered, so they do not count towards opening the circuit.
+pub struct CircuitBreakerHook<H: Hook> {
+    hook: H,
+    failure_threshold: u32,
+    success_threshold: u32,
+    cool_down: Duration,
+    breaker: Mutex<Breaker>,
+}
+
+impl<H: Hook> CircuitBreakerHook<H> {
+    /// Guard `hook`, opening after 5 consecutive failures for 30 seconds
+    pub fn new(hook: H) -> Self {
+        Self {
+            hook,
+            failure_threshold: 5,
+            success_threshold: 1,
+            cool_down: Duration::from_secs(30),
+            breaker: Mutex::new(Breaker::Closed { failures: 0 }),
+        }
+    }
+
+    /// Consecutive failures that open the circuit
+    pub fn failure_threshold(mut self, failures: u32) -> Self {
+        self.failure_threshold = failures.max(1);
+        self
+    }
+
+    /// Successful probes in a row that close the circuit again
+    pub fn success_threshold(mut self, successes: u32) -> Self {
+        self.success_threshold = successes.max(1);
+        self
+    }
+
+    /// How long the circuit stays open before probing
+    ///
+    /// Also how long a probe may take before another one is let through.
+    pub fn cool_down(mut self, cool_down: Duration) -> Self {
+        self.cool_down = cool_down;
+        self
+    }
+
+    /// The wrapped hook
+    pub fn inner(&self) -> &H {
+        &self.hook
+    }
+
+    /// Current state of the circuit
+    pub fn state(&self) -> CircuitState {
+        match *self.lock() {
+            Breaker::Closed { .. } => CircuitState::Closed,
+            Breaker::Open { until } if Instant::now() < until => CircuitState::Open,
+            Breaker::Open { .. } | Breaker::HalfOpen { .. } => CircuitState::HalfOpen,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Breaker> {
+        self.breaker
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Let an entry through, or reject it while the circuit is open
+    fn admit(&self) -> Result<(), CircuitOpen> {
+        let now = Instant::now();
+        let mut breaker = self.lock();
+        match *breaker {
+            Breaker::Closed { .. } => Ok(()),
+            Breaker::Open { until } if now < until => Err(CircuitOpen {
+                retry_in: until - now,
+            }),
+            Breaker::Open { .. } => {
+                *breaker = Breaker::HalfOpen {
+                    probe: Some(now),
+                    successes: 0,
+                };
+                Ok(())
+            }
+            Breaker::HalfOpen { ref mut probe, .. } => match *probe {
+                // One probe at a time, unless it is taking too long
+                Some(started) if now < started + self.cool_down => Err(CircuitOpen {
+                    retry_in: started + self.cool_down - now,
+                }),
+                _ => {
+                    *probe = Some(now);
+                    Ok(())
+                }
+            },
+        }
+    }
+
+    fn record(&self, result: &Result<(), LoggixError>) {
+        let failed = match result {
+            Ok(()) => false,
+            Err(error) => !is_permanent(error),
+        };
+        let mut breaker = self.lock();
+        *breaker = match *breaker {
+            Breaker::Closed { .. } if !failed => Breaker::Closed { failures: 0 },
+            Breaker::Closed { failures } if failures + 1 < self.failure_threshold => {
+                Breaker::Closed {
+                    failures: failures + 1,
+                }
+            }
+            Breaker::HalfOpen { successes, .. } if !failed => {
+                if successes + 1 >= self.success_threshold {
+                    Breaker::Closed { failures: 0 }
+                } else {
+                    Breaker::HalfOpen {
+                        probe: None,
+                        successes: successes + 1,
+                    }
+                }
+            }
+            // An entry let through before the circuit opened
+            Breaker::Open { until } => Breaker::Open { until },
+            Breaker::Closed { .. } | Breaker::HalfOpen { .. } => Breaker::Open {
+                until: Instant::now() + self.cool_down,
+            },
+        };
+    }
+}
+
+impl<H: Hook> Hook for CircuitBreakerHook<H> {
+    fn levels(&self) -> Vec<Level> {
+        self.hook.levels()
+    }
+
+    fn fire(&self, entry: &Entry) -> Result<(), LoggixError> {
+        self.admit()
+            .map_err(|open| LoggixError::hook("CircuitBreakerHook", open))?;
+        let result = self.hook.fire(entry);
+        self.record(&result);
+        result
+    }
+
+    fn fire_async<'a>(
+        &'a self,
+        entry: &'a Entry,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), LoggixError>> + Send + 'a>>
+    {
+        Box::pin(async move {
+            self.admit()
+                .map_err(|open| LoggixError::hook("CircuitBreakerHook", open))?;
+            let result = self.hook.fire_async(entry).await;
+            self.record(&result);
+            result
+        })
+    }
+
+    fn requires_async(&self) -> bool {
+        self.hook.requires_async()
+    }
+
+    fn flush(&self, timeout: Duration) -> Result<(), LoggixError> {
+        self.hook.flush(timeout)
+    }
+
+    fn flush_async(
+        &self,
+        timeout: Duration,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), LoggixError>> + Send + '_>>
+    {
+        self.hook.flush_async(timeout)
+    }
+}
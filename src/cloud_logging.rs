@@ -0,0 +1,183 @@
+//! Google Cloud Logging structured JSON formatter for GKE, Cloud Run and
+//! other environments where the logging agent parses stdout.
+
+use crate::ordered::OrderedObject;
+use crate::{Entry, Formatter, Level, LoggixError};
+use serde_json::{Map, Value};
+
+const SOURCE_LOCATION_KEY: &str = "logging.googleapis.com/sourceLocation";
+const TRACE_KEY: &str = "logging.googleapis.com/trace";
+const SPAN_ID_KEY: &str = "logging.googleapis.com/spanId";
+const TRACE_SAMPLED_KEY: &str = "logging.googleapis.com/trace_sampled";
+
+/// Map a level to a Cloud Logging `LogSeverity` name
+pub fn cloud_logging_severity(level: Level) -> &'static str {
+    match level {
+        Level::Trace | Level::Debug => "DEBUG",
+        Level::Info => "INFO",
+        Level::Warn => "WARNING",
+        Level::Error => "ERROR",
+        Level::Fatal => "CRITICAL",
+        Level::Panic => "EMERGENCY",
+    }
+}
+
+/// Google Cloud Logging formatter
+///
+/// Writes one JSON object per line using the special keys Cloud Logging
+/// understands: `severity`, `message`, `time`,
+/// `logging.googleapis.com/sourceLocation` (built from the `file`, `line` and
+/// `function` fields when present), `logging.googleapis.com/trace`,
+/// `logging.googleapis.com/spanId` and `httpRequest`. All other fields are
+/// written at the top level and end up in `jsonPayload`.
+#[derive(Debug, Clone)]
+pub struct CloudLoggingFormatter {
+    project_id: Option<String>,
+    trace_field: String,
+    span_field: String,
+    trace_sampled_field: String,
+    http_request_field: String,
+}
+
+impl Default for CloudLoggingFormatter {
+    fn default() -> Self {
+        Self {
+            project_id: None,
+            trace_field: "trace_id".to_string(),
+            span_field: "span_id".to_string(),
+            trace_sampled_field: "trace_sampled".to_string(),
+            http_request_field: "httpRequest".to_string(),
+        }
+    }
+}
+
+impl CloudLoggingFormatter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the project used to build `projects/<id>/traces/<trace>` names
+    ///
+    /// Without a project ID the trace field is passed through unchanged.
+    pub fn project_id(mut self, project_id: &str) -> Self {
+        self.project_id = Some(project_id.to_string());
+        self
+    }
+
+    /// Set the field holding the trace ID (`trace_id` by default)
+    pub fn trace_field(mut self, field: &str) -> Self {
+        self.trace_field = field.to_string();
+        self
+    }
+
+    /// Set the field holding the span ID (`span_id` by default)
+    pub fn span_field(mut self, field: &str) -> Self {
+        self.span_field = field.to_string();
+        self
+    }
+
+    /// Set the field holding the sampling decision (`trace_sampled` by default)
+    pub fn trace_sampled_field(mut self, field: &str) -> Self {
+        self.trace_sampled_field = field.to_string();
+        self
+    }
+
+    /// Set the field passed through as `httpRequest` (`httpRequest` by default)
+    pub fn http_request_field(mut self, field: &str) -> Self {
+        self.http_request_field = field.to_string();
+        self
+    }
+
+    fn trace_name(&self, trace: &str) -> String {
+        match &self.project_id {
+            Some(project) if !trace.starts_with("projects/") => {
+                format!("projects/{}/traces/{}", project, trace)
+            }
+            _ => trace.to_string(),
+        }
+    }
+}
+
+impl Formatter for CloudLoggingFormatter {
+    fn format(&self, entry: &Entry) -> Result<Vec<u8>, LoggixError> {
+        let mut document = OrderedObject::new();
+        document.insert(
+            "severity",
+            Value::String(cloud_logging_severity(entry.level).to_string()),
+        );
+        document.insert("message", Value::String(entry.message.clone()));
+        document.insert(
+            "time",
+            Value::String(
+                entry
+                    .timestamp
+                    .to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true),
+            ),
+        );
+
+        let mut source_location = Map::new();
+        for key in ["file", "line", "function"] {
+            if let Some(value) = entry.fields.get(key) {
+                // LogEntrySourceLocation.line is an int64, which proto3 JSON encodes as a string
+                source_location.insert(key.to_string(), Value::String(as_text(value)));
+            }
+        }
+        if !source_location.is_empty() {
+            document.insert(SOURCE_LOCATION_KEY, Value::Object(source_location));
+        }
+
+        if let Some(trace) = entry.fields.get(&self.trace_field) {
+            document.insert(TRACE_KEY, Value::String(self.trace_name(&as_text(trace))));
+        }
+        if let Some(span) = entry.fields.get(&self.span_field) {
+            document.insert(SPAN_ID_KEY, Value::String(as_text(span)));
+        }
+        if let Some(sampled) = entry.fields.get(&self.trace_sampled_field) {
+            let sampled = match sampled {
+                Value::Bool(b) => *b,
+                Value::String(s) => s == "true" || s == "1",
+                Value::Number(n) => n.as_i64() == Some(1),
+                _ => false,
+            };
+            document.insert(TRACE_SAMPLED_KEY, Value::Bool(sampled));
+        }
+        if let Some(http_request) = entry.fields.get(&self.http_request_field) {
+            document.insert("httpRequest", http_request.clone());
+        }
+
+        let consumed = [
+            "file",
+            "line",
+            "function",
+            self.trace_field.as_str(),
+            self.span_field.as_str(),
+            self.trace_sampled_field.as_str(),
+            self.http_request_field.as_str(),
+        ];
+        let mut fields: Vec<(&String, &Value)> = entry
+            .fields
+            .iter()
+            .filter(|(key, _)| !consumed.contains(&key.as_str()))
+            .collect();
+        fields.sort_by(|a, b| a.0.cmp(b.0));
+        for (key, value) in fields {
+            if document.contains_key(key) {
+                document.insert(format!("fields.{}", key), value.clone());
+            } else {
+                document.insert(key.as_str(), value.clone());
+            }
+        }
+
+        let mut output = serde_json::to_vec(&document)?;
+        output.push(b'\n');
+        Ok(output)
+    }
+}
+
+/// Render a field value as plain text, without JSON quotes for strings
+fn as_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
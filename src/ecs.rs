@@ -0,0 +1,151 @@
+//! Elastic Common Schema (ECS) JSON formatter for Elasticsearch and Kibana.
+
+use crate::ordered::OrderedObject;
+use crate::{Entry, Formatter, LoggixError};
+use serde_json::Value;
+
+/// ECS version advertised in the `ecs.version` key
+pub const ECS_VERSION: &str = "8.11.0";
+
+/// Field names that are moved to their ECS location by default
+const DEFAULT_MAPPINGS: &[(&str, &str)] = &[
+    ("error", "error.message"),
+    ("trace_id", "trace.id"),
+    ("span_id", "span.id"),
+    ("transaction_id", "transaction.id"),
+    ("service.name", "service.name"),
+    ("service_name", "service.name"),
+    ("service.version", "service.version"),
+    ("service.environment", "service.environment"),
+    ("host.name", "host.name"),
+    ("hostname", "host.name"),
+    ("user.id", "user.id"),
+    ("user_id", "user.id"),
+    ("event.dataset", "event.dataset"),
+];
+
+/// Elastic Common Schema formatter
+///
+/// Writes one JSON document per line with `@timestamp`, `log.level`,
+/// `message` and `ecs.version` first. Well-known fields such as `error`
+/// (set by `EntryBuilder::with_error`), `trace_id` and `service.name` are
+/// moved to their ECS locations; every other field is nested under a
+/// configurable namespace (`fields` by default).
+#[derive(Debug, Clone)]
+pub struct ECSFormatter {
+    namespace: String,
+    mappings: Vec<(String, String)>,
+}
+
+impl Default for ECSFormatter {
+    fn default() -> Self {
+        Self {
+            namespace: "fields".to_string(),
+            mappings: DEFAULT_MAPPINGS
+                .iter()
+                .map(|(from, to)| (from.to_string(), to.to_string()))
+                .collect(),
+        }
+    }
+}
+
+impl ECSFormatter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the object that unmapped fields are nested under
+    ///
+    /// An empty namespace writes unmapped fields at the top level, except
+    /// fields that clash with an ECS key such as `message` or `log`, which
+    /// go under `fields`.
+    pub fn namespace(mut self, namespace: &str) -> Self {
+        self.namespace = namespace.to_string();
+        self
+    }
+
+    /// Map a field name to an ECS location such as `http.request.method`
+    ///
+    /// Mappings added here take precedence over the built-in ones.
+    pub fn map_field(mut self, field: &str, ecs_path: &str) -> Self {
+        self.mappings.retain(|(from, _)| from != field);
+        self.mappings
+            .insert(0, (field.to_string(), ecs_path.to_string()));
+        self
+    }
+
+    fn mapping_for(&self, field: &str) -> Option<&str> {
+        self.mappings
+            .iter()
+            .find(|(from, _)| from == field)
+            .map(|(_, to)| to.as_str())
+    }
+}
+
+impl Formatter for ECSFormatter {
+    fn format(&self, entry: &Entry) -> Result<Vec<u8>, LoggixError> {
+        let mut document = OrderedObject::new();
+        document.insert(
+            "@timestamp",
+            Value::String(
+                entry
+                    .timestamp
+                    .to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            ),
+        );
+        document.insert(
+            "log.level",
+            Value::String(entry.level.to_string().to_lowercase()),
+        );
+        document.insert("message", Value::String(entry.message.clone()));
+        document.insert("ecs.version", Value::String(ECS_VERSION.to_string()));
+
+        let mut fields: Vec<(&String, &Value)> = entry.fields.iter().collect();
+        fields.sort_by(|a, b| a.0.cmp(b.0));
+
+        let mut unmapped = Vec::new();
+        for (key, value) in fields {
+            match self.mapping_for(key) {
+                // A structured error already has ECS sub-fields of its own
+                Some("error.message") if value.is_object() => {
+                    document.insert_path("error", value.clone())
+                }
+                Some(path) => document.insert_path(path, value.clone()),
+                None => unmapped.push((key, value)),
+            }
+        }
+
+        for (key, value) in unmapped {
+            if self.namespace.is_empty() {
+                if clashes(&document, key) {
+                    document.insert_path(&format!("fields.{}", key), value.clone());
+                } else {
+                    document.insert(key.clone(), value.clone());
+                }
+            } else {
+                let mut path = self.namespace.clone();
+                path.push('.');
+                path.push_str(key);
+                document.insert_path(&path, value.clone());
+            }
+        }
+
+        let mut output = serde_json::to_vec(&document)?;
+        output.push(b'\n');
+        Ok(output)
+    }
+}
+
+/// Whether a top-level field `key` would overwrite or collide with a key
+/// already written, e.g. `log` with `log.level`
+fn clashes(document: &OrderedObject, key: &str) -> bool {
+    document.keys().any(|existing| {
+        existing == key
+            || existing
+                .strip_prefix(key)
+                .is_some_and(|rest| rest.starts_with('.'))
+            || key
+                .strip_prefix(existing)
+                .is_some_and(|rest| rest.starts_with('.'))
+    })
+}
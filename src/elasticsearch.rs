@@ -0,0 +1,383 @@
+//! Elasticsearch and OpenSearch bulk indexing hook.
+//!
+//! Entries are formatted into JSON documents when they are fired, buffered,
+//! and indexed with the `_bulk` API into an index named after the entry's
+//! date (`logs-%Y.%m.%d` by default). Bulk responses are checked item by
+//! item: documents rejected with a retryable status (`429` or `5xx`) are
+//! sent again after a backoff, everything else is reported and dropped.
+
+use crate::backoff::{send_http, Backoff};
+use crate::batch::{BatchConfig, BatchWorker, BoxError, Export, ExportFuture};
+use crate::{Entry, Formatter, Hook, JSONFormatter, Level, LoggixError};
+use chrono::format::{Item, StrftimeItems};
+use serde_json::{json, Value};
+use std::fmt::Write;
+use std::time::Duration;
+
+const BULK_PATH: &str = "/_bulk";
+
+/// Builder for [`ElasticsearchHook`]
+pub struct ElasticsearchHookBuilder {
+    url: String,
+    index: String,
+    formatter: Box<dyn Formatter>,
+    pipeline: Option<String>,
+    basic_auth: Option<(String, String)>,
+    api_key: Option<String>,
+    headers: Vec<(String, String)>,
+    backoff: Backoff,
+    batch: BatchConfig,
+    timeout: Duration,
+}
+
+impl ElasticsearchHookBuilder {
+    /// Index name, with `strftime` placeholders filled from the entry
+    /// timestamp (`logs-%Y.%m.%d` by default)
+    pub fn index(mut self, pattern: &str) -> Self {
+        self.index = pattern.to_string();
+        self
+    }
+
+    /// Formatter used for the document body (`JSONFormatter` by default)
+    ///
+    /// Output that is not a JSON object is indexed as
+    /// `{"@timestamp": ..., "message": <output>}`.
+    pub fn formatter<F: Formatter + 'static>(mut self, formatter: F) -> Self {
+        self.formatter = Box::new(formatter);
+        self
+    }
+
+    /// Run documents through an ingest pipeline
+    pub fn pipeline(mut self, pipeline: &str) -> Self {
+        self.pipeline = Some(pipeline.to_string());
+        self
+    }
+
+    /// Authenticate with a username and password
+    pub fn basic_auth(mut self, username: &str, password: &str) -> Self {
+        self.basic_auth = Some((username.to_string(), password.to_string()));
+        self
+    }
+
+    /// Authenticate with an encoded API key (`Authorization: ApiKey ...`)
+    pub fn api_key(mut self, key: &str) -> Self {
+        self.api_key = Some(key.to_string());
+        self
+    }
+
+    /// Add an HTTP header
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Retry policy for failed requests and rejected documents
+    pub fn retry(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Maximum number of documents per bulk request (512 by default)
+    pub fn batch_size(mut self, size: usize) -> Self {
+        self.batch.max_items = size;
+        self
+    }
+
+    /// Maximum size of a bulk request body in bytes (5 MiB by default)
+    pub fn batch_bytes(mut self, bytes: usize) -> Self {
+        self.batch.max_bytes = bytes;
+        self
+    }
+
+    /// Maximum time a document waits before its batch is sent (1s by default)
+    pub fn batch_timeout(mut self, timeout: Duration) -> Self {
+        self.batch.max_latency = timeout;
+        self
+    }
+
+    /// Timeout for a single bulk request (30s by default)
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Start the indexing worker and create the hook
+    pub fn build(self) -> Result<ElasticsearchHook, LoggixError> {
+        if StrftimeItems::new(&self.index).any(|item| matches!(item, Item::Error)) {
+            return Err(LoggixError::config(format!(
+                "invalid index pattern: {}",
+                self.index
+            )));
+        }
+
+        let client = reqwest::Client::builder()
+            .timeout(self.timeout)
+            .build()
+            .map_err(LoggixError::config)?;
+        let mut headers = reqwest::header::HeaderMap::new();
+        for (name, value) in &self.headers {
+            headers.insert(
+                reqwest::header::HeaderName::from_bytes(name.as_bytes())
+                    .map_err(LoggixError::config)?,
+                reqwest::header::HeaderValue::from_str(value).map_err(LoggixError::config)?,
+            );
+        }
+        if let Some(key) = &self.api_key {
+            headers.insert(
+                reqwest::header::AUTHORIZATION,
+                reqwest::header::HeaderValue::from_str(&format!("ApiKey {}", key))
+                    .map_err(LoggixError::config)?,
+            );
+        }
+
+        let mut url = reqwest::Url::parse(&self.url).map_err(LoggixError::config)?;
+        if !url.path().ends_with(BULK_PATH) {
+            let path = format!("{}{}", url.path().trim_end_matches('/'), BULK_PATH);
+            url.set_path(&path);
+        }
+        if let Some(pipeline) = &self.pipeline {
+            url.query_pairs_mut().append_pair("pipeline", pipeline);
+        }
+        let indexer = BulkIndexer {
+            client,
+            url,
+            headers,
+            basic_auth: self.basic_auth,
+            backoff: self.backoff,
+        };
+        let worker = BatchWorker::spawn("ElasticsearchHook", self.batch, indexer)?;
+
+        Ok(ElasticsearchHook {
+            index: self.index,
+            formatter: self.formatter,
+            worker,
+        })
+    }
+}
+
+/// A hook that indexes log entries into Elasticsearch or OpenSearch
+pub struct ElasticsearchHook {
+    index: String,
+    formatter: Box<dyn Formatter>,
+    worker: BatchWorker<BulkDocument>,
+}
+
+impl ElasticsearchHook {
+    /// Start building a hook for the cluster at `url`, e.g.
+    /// `http://localhost:9200`
+    pub fn builder(url: &str) -> ElasticsearchHookBuilder {
+        ElasticsearchHookBuilder {
+            url: url.to_string(),
+            index: "logs-%Y.%m.%d".to_string(),
+            formatter: Box::new(JSONFormatter::new()),
+            pipeline: None,
+            basic_auth: None,
+            api_key: None,
+            headers: Vec::new(),
+            backoff: Backoff::default(),
+            batch: BatchConfig {
+                max_bytes: 5 * 1024 * 1024,
+                ..BatchConfig::default()
+            },
+            timeout: Duration::from_secs(30),
+        }
+    }
+
+    /// Index everything queued so far, waiting up to `timeout`
+    pub fn flush(&self, timeout: Duration) -> Result<(), LoggixError> {
+        self.worker.flush(timeout)
+    }
+
+    /// Name of the index an entry is written to
+    pub fn index_name(&self, entry: &Entry) -> String {
+        let mut name = String::new();
+        // The pattern is validated in `build`, so formatting cannot fail
+        let _ = write!(name, "{}", entry.timestamp.format(&self.index));
+        name
+    }
+
+    /// Render an entry as a single-line JSON document
+    fn document(&self, entry: &Entry) -> Result<String, LoggixError> {
+        let output = self.formatter.format(entry)?;
+        let document = match serde_json::from_slice::<Value>(&output) {
+            Ok(value @ Value::Object(_)) => value,
+            _ => json!({
+                "@timestamp": entry.timestamp.to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+                "message": String::from_utf8_lossy(&output).trim_end(),
+            }),
+        };
+        Ok(serde_json::to_string(&document)?)
+    }
+}
+
+impl Hook for ElasticsearchHook {
+    fn levels(&self) -> Vec<Level> {
+        vec![
+            Level::Trace,
+            Level::Debug,
+            Level::Info,
+            Level::Warn,
+            Level::Error,
+            Level::Fatal,
+            Level::Panic,
+        ]
+    }
+
+    fn fire(&self, entry: &Entry) -> Result<(), LoggixError> {
+        let document = BulkDocument {
+            action: serde_json::to_string(&json!({
+                "create": { "_index": self.index_name(entry) }
+            }))?,
+            source: self.document(entry)?,
+        };
+        let size = document.action.len() + document.source.len() + 2;
+        self.worker.push(document, size)
+    }
+
+    fn flush(&self, timeout: Duration) -> Result<(), LoggixError> {
+        ElasticsearchHook::flush(self, timeout)
+    }
+
+    fn flush_async(
+        &self,
+        timeout: Duration,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), LoggixError>> + Send + '_>>
+    {
+        Box::pin(self.worker.flush_async(timeout))
+    }
+}
+
+/// A bulk action line and its document source
+struct BulkDocument {
+    action: String,
+    source: String,
+}
+
+struct BulkIndexer {
+    client: reqwest::Client,
+    url: reqwest::Url,
+    headers: reqwest::header::HeaderMap,
+    basic_auth: Option<(String, String)>,
+    backoff: Backoff,
+}
+
+impl BulkIndexer {
+    /// Send one bulk request and return the documents worth retrying, along
+    /// with the number of documents dropped and the first rejection reason
+    async fn index(
+        &self,
+        documents: Vec<BulkDocument>,
+    ) -> Result<(Vec<BulkDocument>, usize, Option<String>), BoxError> {
+        let mut body = String::new();
+        for document in &documents {
+            body.push_str(&document.action);
+            body.push('\n');
+            body.push_str(&document.source);
+            body.push('\n');
+        }
+
+        let response = send_http(&self.backoff, || {
+            let request = self
+                .client
+                .post(self.url.clone())
+                .headers(self.headers.clone())
+                .header(reqwest::header::CONTENT_TYPE, "application/x-ndjson")
+                .body(body.clone());
+            match &self.basic_auth {
+                Some((username, password)) => request.basic_auth(username, Some(password)),
+                None => request,
+            }
+        })
+        .await?;
+        let response: Value = serde_json::from_slice(&response.bytes().await?)?;
+        let items = response["items"].as_array().cloned().unwrap_or_default();
+        if items.len() > documents.len() {
+            return Err(format!(
+                "bulk response has {} items for {} documents",
+                items.len(),
+                documents.len()
+            )
+            .into());
+        }
+        if response["errors"] != Value::Bool(true) && items.len() == documents.len() {
+            return Ok((Vec::new(), 0, None));
+        }
+
+        let mut retryable = Vec::new();
+        let mut dropped = 0;
+        let mut reason = None;
+        let mut items = items.into_iter();
+        for document in documents {
+            // Items come back in request order; a document without one was
+            // never reported as indexed, so send it again
+            let Some(item) = items.next() else {
+                retryable.push(document);
+                continue;
+            };
+            // Each item is keyed by its action, e.g. `{"create": {...}}`
+            let result = item.as_object().and_then(|item| item.values().next());
+            let status = result
+                .and_then(|result| result["status"].as_u64())
+                .unwrap_or(0);
+            if (200..300).contains(&status) {
+                continue;
+            }
+            let retry = reqwest::StatusCode::from_u16(status as u16)
+                .map(crate::backoff::is_retryable_status)
+                .unwrap_or(false);
+            if retry {
+                retryable.push(document);
+            } else {
+                dropped += 1;
+                if reason.is_none() {
+                    reason = result.map(|result| describe_error(status, &result["error"]));
+                }
+            }
+        }
+        Ok((retryable, dropped, reason))
+    }
+}
+
+impl Export<BulkDocument> for BulkIndexer {
+    fn export(&self, batch: Vec<BulkDocument>) -> ExportFuture<'_> {
+        Box::pin(async move {
+            let mut pending = batch;
+            let mut dropped = 0;
+            let mut reason = None;
+            let mut retry = 0;
+            while !pending.is_empty() {
+                let (retryable, rejected, first) = self.index(pending).await?;
+                dropped += rejected;
+                reason = reason.or(first);
+                if retryable.is_empty() {
+                    break;
+                }
+                if retry >= self.backoff.retries() {
+                    dropped += retryable.len();
+                    reason = reason.or(Some("still rejected after retries".to_string()));
+                    break;
+                }
+                tokio::time::sleep(self.backoff.delay(retry)).await;
+                retry += 1;
+                pending = retryable;
+            }
+
+            match reason {
+                Some(reason) if dropped > 0 => {
+                    Err(format!("{} documents rejected: {}", dropped, reason).into())
+                }
+                _ => Ok(()),
+            }
+        })
+    }
+}
+
+/// Summarize a bulk item error such as
+/// `{"type": "mapper_parsing_exception", "reason": "..."}`
+fn describe_error(status: u64, error: &Value) -> String {
+    match (error["type"].as_str(), error["reason"].as_str()) {
+        (Some(kind), Some(reason)) => format!("{} {}: {}", status, kind, reason),
+        (Some(kind), None) => format!("{} {}", status, kind),
+        _ => format!("status {}", status),
+    }
+}
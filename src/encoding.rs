@@ -0,0 +1,52 @@
+//! Small encoders shared by hooks that need them, kept here rather than
+//! pulling in a crate for a few lines.
+
+#[cfg(any(feature = "fluent", feature = "kafka"))]
+use rmpv::Value as MsgValue;
+#[cfg(any(feature = "fluent", feature = "kafka"))]
+use serde_json::Value;
+
+#[cfg(any(feature = "otlp", feature = "fluent"))]
+/// Standard, padded base64 as used in JSON payloads
+pub(crate) fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(any(feature = "fluent", feature = "kafka"))]
+/// Converts a JSON value into its MessagePack equivalent
+pub(crate) fn to_msgpack(value: &Value) -> MsgValue {
+    match value {
+        Value::Null => MsgValue::Nil,
+        Value::Bool(b) => MsgValue::Boolean(*b),
+        Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                MsgValue::from(i)
+            } else if let Some(u) = n.as_u64() {
+                MsgValue::from(u)
+            } else {
+                MsgValue::from(n.as_f64().unwrap_or_default())
+            }
+        }
+        Value::String(s) => MsgValue::from(s.as_str()),
+        Value::Array(items) => MsgValue::Array(items.iter().map(to_msgpack).collect()),
+        Value::Object(map) => MsgValue::Map(
+            map.iter()
+                .map(|(k, v)| (MsgValue::from(k.as_str()), to_msgpack(v)))
+                .collect(),
+        ),
+    }
+}
@@ -0,0 +1,129 @@
+//! The error type returned across the crate.
+//!
+//! [`LoggixError`] is `Send + Sync`, so it can cross threads and be held
+//! across `.await` in async hooks, and its variants can be matched on to tell
+//! a broken output from a failing hook or a bad configuration.
+
+#[cfg(feature = "kafka")]
+use crate::kafka_config::KafkaConfigError;
+use crate::shutdown::ShutdownError;
+#[cfg(feature = "kafka")]
+use rdkafka::error::KafkaError;
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// Errors returned by loggers, formatters, hooks and their builders
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum LoggixError {
+    /// Writing to the output, a file or a socket failed
+    Io(io::Error),
+    /// An entry could not be encoded
+    Format(Box<dyn Error + Send + Sync>),
+    /// A hook could not deliver an entry, `name` saying which
+    Hook {
+        name: String,
+        source: Box<dyn Error + Send + Sync>,
+    },
+    /// A builder or configuration value is unusable
+    Config(Box<dyn Error + Send + Sync>),
+    /// The Kafka client reported an error
+    #[cfg(feature = "kafka")]
+    Kafka(KafkaError),
+    /// A flush or shutdown could not deliver everything in time
+    Shutdown(ShutdownError),
+    /// The entry was deliberately not logged, e.g. because the logger has
+    /// been shut down
+    Filtered(String),
+}
+
+impl LoggixError {
+    /// A delivery failure of the hook called `name`
+    pub fn hook<E>(name: impl Into<String>, source: E) -> Self
+    where
+        E: Into<Box<dyn Error + Send + Sync>>,
+    {
+        LoggixError::Hook {
+            name: name.into(),
+            source: source.into(),
+        }
+    }
+
+    /// An unusable setting
+    pub fn config<E: Into<Box<dyn Error + Send + Sync>>>(source: E) -> Self {
+        LoggixError::Config(source.into())
+    }
+
+    /// An entry that could not be encoded
+    pub fn format<E: Into<Box<dyn Error + Send + Sync>>>(source: E) -> Self {
+        LoggixError::Format(source.into())
+    }
+}
+
+impl fmt::Display for LoggixError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoggixError::Io(err) => write!(f, "{}", err),
+            LoggixError::Format(err) => write!(f, "cannot format entry: {}", err),
+            LoggixError::Hook { name, source } => write!(f, "{}: {}", name, source),
+            LoggixError::Config(err) => write!(f, "{}", err),
+            #[cfg(feature = "kafka")]
+            LoggixError::Kafka(err) => write!(f, "Kafka error: {}", err),
+            LoggixError::Shutdown(err) => write!(f, "{}", err),
+            LoggixError::Filtered(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl Error for LoggixError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LoggixError::Io(err) => Some(err),
+            LoggixError::Format(err) | LoggixError::Config(err) => Some(err.as_ref()),
+            LoggixError::Hook { source, .. } => Some(source.as_ref()),
+            #[cfg(feature = "kafka")]
+            LoggixError::Kafka(err) => Some(err),
+            LoggixError::Shutdown(err) => Some(err),
+            LoggixError::Filtered(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for LoggixError {
+    fn from(err: io::Error) -> Self {
+        LoggixError::Io(err)
+    }
+}
+
+impl From<ShutdownError> for LoggixError {
+    fn from(err: ShutdownError) -> Self {
+        LoggixError::Shutdown(err)
+    }
+}
+
+impl From<serde_json::Error> for LoggixError {
+    fn from(err: serde_json::Error) -> Self {
+        LoggixError::Format(err.into())
+    }
+}
+
+impl From<fmt::Error> for LoggixError {
+    fn from(err: fmt::Error) -> Self {
+        LoggixError::Format(err.into())
+    }
+}
+
+#[cfg(feature = "kafka")]
+impl From<KafkaError> for LoggixError {
+    fn from(err: KafkaError) -> Self {
+        LoggixError::Kafka(err)
+    }
+}
+
+#[cfg(feature = "kafka")]
+impl From<KafkaConfigError> for LoggixError {
+    fn from(err: KafkaConfigError) -> Self {
+        LoggixError::Config(err.into())
+    }
+}
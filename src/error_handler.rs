@@ -0,0 +1,86 @@
+//! Process-wide handling of errors the logging macros cannot return.
+//!
+//! `info!`, `error!` and the other macros never panic. When logging fails,
+//! e.g. because stdout is a closed pipe or the disk is full, the error goes
+//! to [`handle_error`], which does what [`set_error_handler`] configured:
+//! print it to stderr (the default), only count it, or abort the process.
+//! Use the `try_` macros, such as `try_info!`, to get the `Result` instead.
+//!
+//! Hook failures are never returned from [`Logger::log`](crate::Logger::log),
+//! so loggers pass them to [`handle_error`] as well.
+
+use crate::LoggixError;
+use lazy_static::lazy_static;
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+/// What [`handle_error`] does with a logging error
+pub enum ErrorHandler {
+    /// Print the error to stderr
+    Stderr,
+    /// Only count the error, see [`error_count`]
+    Count,
+    /// Print the error to stderr and abort the process
+    Abort,
+    /// Pass the error to a function
+    Custom(Box<dyn Fn(&LoggixError) + Send + Sync>),
+}
+
+impl ErrorHandler {
+    /// Handle errors with `handler`
+    pub fn custom<F: Fn(&LoggixError) + Send + Sync + 'static>(handler: F) -> Self {
+        ErrorHandler::Custom(Box::new(handler))
+    }
+}
+
+lazy_static! {
+    static ref ERROR_HANDLER: RwLock<Arc<ErrorHandler>> =
+        RwLock::new(Arc::new(ErrorHandler::Stderr));
+}
+
+static ERROR_COUNT: AtomicU64 = AtomicU64::new(0);
+
+/// Choose what happens to errors from the logging macros
+pub fn set_error_handler(handler: ErrorHandler) {
+    let mut current = ERROR_HANDLER
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    *current = Arc::new(handler);
+}
+
+/// Number of errors passed to [`handle_error`] so far, whatever the handler
+pub fn error_count() -> u64 {
+    ERROR_COUNT.load(Ordering::Relaxed)
+}
+
+/// Count `error` and pass it to the configured [`ErrorHandler`]
+///
+/// [`LoggixError::Filtered`] errors are ignored: the entry was left out on
+/// purpose, e.g. after [`Logger::shutdown`](crate::Logger::shutdown).
+pub fn handle_error(error: LoggixError) {
+    if let LoggixError::Filtered(_) = error {
+        return;
+    }
+    ERROR_COUNT.fetch_add(1, Ordering::Relaxed);
+    // Release the lock before calling out, so a handler may replace itself
+    let handler = Arc::clone(
+        &ERROR_HANDLER
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()),
+    );
+    match handler.as_ref() {
+        ErrorHandler::Stderr => report(&error),
+        ErrorHandler::Count => {}
+        ErrorHandler::Abort => {
+            report(&error);
+            std::process::abort();
+        }
+        ErrorHandler::Custom(handler) => handler(&error),
+    }
+}
+
+/// Print to stderr without panicking if stderr is gone too
+fn report(error: &LoggixError) {
+    let _ = writeln!(std::io::stderr(), "loggix: failed to log: {}", error);
+}
@@ -0,0 +1,403 @@
+//! Fluentd / Fluent Bit Forward protocol hook.
+//!
+//! Entries are encoded as `[time, record]` MessagePack events, with `time`
+//! as an EventTime so sub-second precision survives, and grouped by tag into
+//! PackedForward messages (`[tag, <event stream>, option]`) sent over TCP or
+//! a Unix socket. With acknowledgements enabled, every message carries a
+//! `chunk` ID and is sent again until the server answers with a matching
+//! `ack`, giving at-least-once delivery.
+
+use crate::backoff::{retry, Attempt, Backoff};
+use crate::batch::{BatchConfig, BatchWorker, BoxError, Export, ExportFuture};
+use crate::encoding::to_msgpack;
+use crate::template::{compile_template, TemplatePart};
+use crate::{Entry, Hook, Level, LoggixError};
+use rmpv::Value as MsgValue;
+use serde_json::Value;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Where a Fluentd or Fluent Bit instance accepts Forward connections
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FluentAddress {
+    /// `host:port`, usually port 24224
+    Tcp(String),
+    /// Path of a Unix domain socket
+    Unix(String),
+}
+
+impl FluentAddress {
+    /// Parse `tcp://host:port`, `unix:///path/to.sock` or plain `host:port`
+    pub fn parse(address: &str) -> Self {
+        if let Some(path) = address.strip_prefix("unix://") {
+            FluentAddress::Unix(path.to_string())
+        } else {
+            let address = address.strip_prefix("tcp://").unwrap_or(address);
+            FluentAddress::Tcp(address.to_string())
+        }
+    }
+}
+
+/// Builder for [`FluentHook`]
+pub struct FluentHookBuilder {
+    address: FluentAddress,
+    tag: String,
+    acknowledgements: bool,
+    ack_timeout: Duration,
+    connect_timeout: Duration,
+    backoff: Backoff,
+    batch: BatchConfig,
+}
+
+impl FluentHookBuilder {
+    /// Tag template (`loggix` by default)
+    ///
+    /// `{level}` is replaced with the lowercase level and `{name}` with the
+    /// value of field `name`, e.g. `app.{service}.{level}`. Missing fields
+    /// become `unknown`.
+    pub fn tag(mut self, template: &str) -> Self {
+        self.tag = template.to_string();
+        self
+    }
+
+    /// Request an `ack` for every message and resend until it arrives
+    pub fn acknowledgements(mut self, enabled: bool) -> Self {
+        self.acknowledgements = enabled;
+        self
+    }
+
+    /// How long to wait for an `ack` before resending (10s by default)
+    pub fn ack_timeout(mut self, timeout: Duration) -> Self {
+        self.ack_timeout = timeout;
+        self
+    }
+
+    /// Timeout for connecting and writing (5s by default)
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Retry policy for failed sends
+    pub fn retry(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Maximum number of events per batch (512 by default)
+    pub fn batch_size(mut self, size: usize) -> Self {
+        self.batch.max_items = size;
+        self
+    }
+
+    /// Maximum encoded size of a batch in bytes (1 MiB by default)
+    pub fn batch_bytes(mut self, bytes: usize) -> Self {
+        self.batch.max_bytes = bytes;
+        self
+    }
+
+    /// Maximum time an event waits before its batch is sent (1s by default)
+    pub fn batch_timeout(mut self, timeout: Duration) -> Self {
+        self.batch.max_latency = timeout;
+        self
+    }
+
+    /// Start the forwarder and create the hook
+    pub fn build(self) -> Result<FluentHook, LoggixError> {
+        let tag = compile_template(&self.tag, "tag")?;
+        #[cfg(not(unix))]
+        if let FluentAddress::Unix(_) = self.address {
+            return Err(LoggixError::config(
+                "Unix sockets are not supported on this platform",
+            ));
+        }
+
+        let forwarder = Forwarder {
+            address: self.address,
+            acknowledgements: self.acknowledgements,
+            ack_timeout: self.ack_timeout,
+            connect_timeout: self.connect_timeout,
+            backoff: self.backoff,
+            connection: tokio::sync::Mutex::new(None),
+        };
+        let worker = BatchWorker::spawn("FluentHook", self.batch, forwarder)?;
+        Ok(FluentHook { tag, worker })
+    }
+}
+
+/// A hook that forwards log entries to Fluentd or Fluent Bit
+pub struct FluentHook {
+    tag: Vec<TemplatePart>,
+    worker: BatchWorker<FluentEvent>,
+}
+
+impl FluentHook {
+    /// Start building a hook for `address`, e.g. `127.0.0.1:24224` or
+    /// `unix:///var/run/fluent.sock`
+    pub fn builder(address: &str) -> FluentHookBuilder {
+        FluentHookBuilder {
+            address: FluentAddress::parse(address),
+            tag: "loggix".to_string(),
+            acknowledgements: false,
+            ack_timeout: Duration::from_secs(10),
+            connect_timeout: Duration::from_secs(5),
+            backoff: Backoff::default(),
+            batch: BatchConfig {
+                max_bytes: 1024 * 1024,
+                ..BatchConfig::default()
+            },
+        }
+    }
+
+    /// Send everything queued so far, waiting up to `timeout`
+    pub fn flush(&self, timeout: Duration) -> Result<(), LoggixError> {
+        self.worker.flush(timeout)
+    }
+
+    /// Render the tag template for an entry
+    pub fn tag_for(&self, entry: &Entry) -> String {
+        let mut tag = String::new();
+        for part in &self.tag {
+            match part {
+                TemplatePart::Text(text) => tag.push_str(text),
+                TemplatePart::Level => tag.push_str(&entry.level.to_string().to_lowercase()),
+                TemplatePart::Field(name) => match entry.fields.get(name) {
+                    Some(Value::String(s)) => tag.push_str(s),
+                    Some(Value::Null) | None => tag.push_str("unknown"),
+                    Some(other) => tag.push_str(&other.to_string()),
+                },
+            }
+        }
+        tag
+    }
+}
+
+impl Hook for FluentHook {
+    fn levels(&self) -> Vec<Level> {
+        vec![
+            Level::Trace,
+            Level::Debug,
+            Level::Info,
+            Level::Warn,
+            Level::Error,
+            Level::Fatal,
+            Level::Panic,
+        ]
+    }
+
+    fn fire(&self, entry: &Entry) -> Result<(), LoggixError> {
+        let mut event = Vec::new();
+        rmpv::encode::write_value(
+            &mut event,
+            &MsgValue::Array(vec![event_time(entry), record(entry)]),
+        )
+        .map_err(LoggixError::format)?;
+        let size = event.len();
+        self.worker.push(
+            FluentEvent {
+                tag: self.tag_for(entry),
+                event,
+            },
+            size,
+        )
+    }
+
+    fn flush(&self, timeout: Duration) -> Result<(), LoggixError> {
+        FluentHook::flush(self, timeout)
+    }
+
+    fn flush_async(
+        &self,
+        timeout: Duration,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), LoggixError>> + Send + '_>>
+    {
+        Box::pin(self.worker.flush_async(timeout))
+    }
+}
+
+/// EventTime: extension type 0 holding big-endian seconds and nanoseconds
+fn event_time(entry: &Entry) -> MsgValue {
+    let mut time = Vec::with_capacity(8);
+    time.extend_from_slice(&(entry.timestamp.timestamp() as u32).to_be_bytes());
+    time.extend_from_slice(&entry.timestamp.timestamp_subsec_nanos().to_be_bytes());
+    MsgValue::Ext(0, time)
+}
+
+/// The event record: `message`, `level`, then the fields sorted by key
+fn record(entry: &Entry) -> MsgValue {
+    let mut record = vec![
+        (
+            MsgValue::from("message"),
+            MsgValue::from(entry.message.as_str()),
+        ),
+        (
+            MsgValue::from("level"),
+            MsgValue::from(entry.level.to_string().to_lowercase()),
+        ),
+    ];
+    let mut fields: Vec<_> = entry.fields.iter().collect();
+    fields.sort_by(|a, b| a.0.cmp(b.0));
+    for (key, value) in fields {
+        let key = if key == "message" || key == "level" {
+            format!("fields.{}", key)
+        } else {
+            key.clone()
+        };
+        record.push((MsgValue::from(key), to_msgpack(value)));
+    }
+    MsgValue::Map(record)
+}
+
+/// An encoded `[time, record]` event and its tag
+struct FluentEvent {
+    tag: String,
+    event: Vec<u8>,
+}
+
+trait Connection: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Connection for T {}
+
+struct Forwarder {
+    address: FluentAddress,
+    acknowledgements: bool,
+    ack_timeout: Duration,
+    connect_timeout: Duration,
+    backoff: Backoff,
+    connection: tokio::sync::Mutex<Option<Box<dyn Connection>>>,
+}
+
+impl Forwarder {
+    async fn connect(&self) -> Result<Box<dyn Connection>, BoxError> {
+        let connect = async {
+            let stream: Box<dyn Connection> = match &self.address {
+                FluentAddress::Tcp(address) => {
+                    let stream = tokio::net::TcpStream::connect(address).await?;
+                    stream.set_nodelay(true)?;
+                    Box::new(stream)
+                }
+                #[cfg(unix)]
+                FluentAddress::Unix(path) => Box::new(tokio::net::UnixStream::connect(path).await?),
+                #[cfg(not(unix))]
+                FluentAddress::Unix(_) => {
+                    return Err(std::io::Error::other("Unix sockets are not supported"))
+                }
+            };
+            Ok::<_, std::io::Error>(stream)
+        };
+        Ok(tokio::time::timeout(self.connect_timeout, connect)
+            .await
+            .map_err(|_| format!("connecting to {:?} timed out", self.address))??)
+    }
+
+    /// Write one message and, if acknowledgements are on, wait for its ack
+    async fn send(&self, message: &[u8], chunk: Option<&str>) -> Result<(), BoxError> {
+        let mut connection = self.connection.lock().await;
+        if connection.is_none() {
+            *connection = Some(self.connect().await?);
+        }
+        let stream = connection.as_mut().expect("connection was just opened");
+
+        let result = async {
+            tokio::time::timeout(self.connect_timeout, stream.write_all(message))
+                .await
+                .map_err(|_| "write timed out")??;
+            if let Some(chunk) = chunk {
+                let ack = tokio::time::timeout(self.ack_timeout, read_value(stream))
+                    .await
+                    .map_err(|_| format!("no ack for chunk {}", chunk))??;
+                let acked = match &ack {
+                    MsgValue::Map(pairs) => pairs
+                        .iter()
+                        .any(|(k, v)| k.as_str() == Some("ack") && v.as_str() == Some(chunk)),
+                    _ => false,
+                };
+                if !acked {
+                    return Err(format!("unexpected ack for chunk {}: {}", chunk, ack).into());
+                }
+            }
+            Ok::<(), BoxError>(())
+        }
+        .await;
+
+        // The stream may hold a partial write or a stale ack, so start over
+        if result.is_err() {
+            *connection = None;
+        }
+        result
+    }
+}
+
+/// Read one MessagePack value from the stream
+async fn read_value(stream: &mut Box<dyn Connection>) -> Result<MsgValue, BoxError> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 256];
+    loop {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Err("connection closed while waiting for ack".into());
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+        match rmpv::decode::read_value(&mut buffer.as_slice()) {
+            Ok(value) => return Ok(value),
+            // Anything but running out of input means the data is corrupt
+            Err(err) if !is_incomplete(&err) => return Err(err.into()),
+            Err(_) => continue,
+        }
+    }
+}
+
+fn is_incomplete(err: &rmpv::decode::Error) -> bool {
+    match err {
+        rmpv::decode::Error::InvalidMarkerRead(io) | rmpv::decode::Error::InvalidDataRead(io) => {
+            io.kind() == std::io::ErrorKind::UnexpectedEof
+        }
+        _ => false,
+    }
+}
+
+impl Export<FluentEvent> for Forwarder {
+    fn export(&self, batch: Vec<FluentEvent>) -> ExportFuture<'_> {
+        Box::pin(async move {
+            // One PackedForward message per tag, in order of first appearance
+            let mut streams: Vec<(String, Vec<u8>, usize)> = Vec::new();
+            for event in batch {
+                match streams.iter_mut().find(|(tag, _, _)| *tag == event.tag) {
+                    Some((_, stream, count)) => {
+                        stream.extend_from_slice(&event.event);
+                        *count += 1;
+                    }
+                    None => streams.push((event.tag, event.event, 1)),
+                }
+            }
+
+            for (tag, stream, count) in streams {
+                let chunk = self
+                    .acknowledgements
+                    .then(|| crate::encoding::base64_encode(uuid::Uuid::new_v4().as_bytes()));
+                let mut option = vec![(MsgValue::from("size"), MsgValue::from(count))];
+                if let Some(chunk) = &chunk {
+                    option.push((MsgValue::from("chunk"), MsgValue::from(chunk.as_str())));
+                }
+                let mut message = Vec::new();
+                rmpv::encode::write_value(
+                    &mut message,
+                    &MsgValue::Array(vec![
+                        MsgValue::from(tag),
+                        MsgValue::Binary(stream),
+                        MsgValue::Map(option),
+                    ]),
+                )?;
+
+                retry(&self.backoff, || async {
+                    match self.send(&message, chunk.as_deref()).await {
+                        Ok(()) => Attempt::Done(()),
+                        Err(err) => Attempt::Transient(err),
+                    }
+                })
+                .await?;
+            }
+            Ok(())
+        })
+    }
+}
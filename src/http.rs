@@ -0,0 +1,474 @@
+//! Generic batching HTTP webhook hook.
+//!
+//! Entries are queued as [`Record`]s and posted to a URL in batches, with
+//! the body rendered as a JSON array, NDJSON, or a user-supplied
+//! [`BodyTemplate`]. Delivery happens on a background worker; a failed
+//! delivery is returned from the next call to `fire` or `flush` so it shows
+//! up on the hook error path.
+
+use crate::backoff::{send_http, Backoff};
+use crate::batch::{BatchConfig, BatchWorker, BoxError, Export, ExportFuture};
+use crate::{Entry, Hook, Level, LoggixError, Record};
+use flate2::write::GzEncoder;
+use serde_json::Value;
+use std::io::Write;
+use std::time::Duration;
+
+/// How a batch of entries is turned into a request body
+#[derive(Debug, Clone, Default)]
+pub enum HttpBody {
+    /// `[{"timestamp": ..., "level": ..., "message": ..., "fields": {...}}, ...]`
+    #[default]
+    JsonArray,
+    /// One JSON object per line
+    Ndjson,
+    /// Entries rendered through a template
+    Template(BodyTemplate),
+}
+
+/// A text template for request bodies
+///
+/// Each entry is rendered through the entry template, the results are joined
+/// with the separator (a newline by default) and, if set, substituted into
+/// the envelope's `{{entries}}` placeholder.
+///
+/// Entry placeholders are `{{timestamp}}`, `{{level}}`, `{{message}}`,
+/// `{{fields}}` and `{{field.NAME}}`. Values are inserted as plain text;
+/// append `|json` (e.g. `{{message|json}}`) to insert a quoted and escaped
+/// JSON value instead. The envelope supports `{{entries}}` and `{{count}}`.
+///
+/// ```rust
+/// use loggix::BodyTemplate;
+///
+/// let template = BodyTemplate::new(r#"{"text": {{message|json}}}"#)
+///     .separator(",")
+///     .envelope(r#"{"alerts": [{{entries}}]}"#);
+/// ```
+#[derive(Debug, Clone)]
+pub struct BodyTemplate {
+    entry: String,
+    separator: String,
+    envelope: Option<String>,
+}
+
+impl BodyTemplate {
+    pub fn new(entry: &str) -> Self {
+        Self {
+            entry: entry.to_string(),
+            separator: "\n".to_string(),
+            envelope: None,
+        }
+    }
+
+    /// Text placed between rendered entries
+    pub fn separator(mut self, separator: &str) -> Self {
+        self.separator = separator.to_string();
+        self
+    }
+
+    /// Wrap the joined entries, e.g. `{"events": [{{entries}}]}`
+    pub fn envelope(mut self, envelope: &str) -> Self {
+        self.envelope = Some(envelope.to_string());
+        self
+    }
+}
+
+/// Builder for [`HttpHook`]
+pub struct HttpHookBuilder {
+    url: String,
+    method: String,
+    headers: Vec<(String, String)>,
+    bearer_token: Option<String>,
+    basic_auth: Option<(String, String)>,
+    body: HttpBody,
+    content_type: Option<String>,
+    gzip: bool,
+    levels: Vec<Level>,
+    backoff: Backoff,
+    batch: BatchConfig,
+    timeout: Duration,
+}
+
+impl HttpHookBuilder {
+    /// HTTP method (`POST` by default)
+    pub fn method(mut self, method: &str) -> Self {
+        self.method = method.to_string();
+        self
+    }
+
+    /// Add an HTTP header
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Send `Authorization: Bearer <token>`
+    pub fn bearer_token(mut self, token: &str) -> Self {
+        self.bearer_token = Some(token.to_string());
+        self
+    }
+
+    /// Authenticate with a username and password
+    pub fn basic_auth(mut self, username: &str, password: &str) -> Self {
+        self.basic_auth = Some((username.to_string(), password.to_string()));
+        self
+    }
+
+    /// Body layout (a JSON array by default)
+    pub fn body(mut self, body: HttpBody) -> Self {
+        self.body = body;
+        self
+    }
+
+    /// Override the `Content-Type` derived from the body layout
+    pub fn content_type(mut self, content_type: &str) -> Self {
+        self.content_type = Some(content_type.to_string());
+        self
+    }
+
+    /// Gzip request bodies
+    pub fn gzip(mut self, enabled: bool) -> Self {
+        self.gzip = enabled;
+        self
+    }
+
+    /// Only send entries at these levels (all levels by default)
+    pub fn levels(mut self, levels: Vec<Level>) -> Self {
+        self.levels = levels;
+        self
+    }
+
+    /// Retry policy for failed requests
+    pub fn retry(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Maximum number of entries per request (512 by default)
+    pub fn batch_size(mut self, size: usize) -> Self {
+        self.batch.max_items = size;
+        self
+    }
+
+    /// Maximum time an entry waits before its batch is sent (1s by default)
+    pub fn batch_timeout(mut self, timeout: Duration) -> Self {
+        self.batch.max_latency = timeout;
+        self
+    }
+
+    /// Number of entries that may wait for delivery before new ones are
+    /// rejected (8192 by default)
+    pub fn queue_capacity(mut self, capacity: usize) -> Self {
+        self.batch.queue_capacity = capacity;
+        self
+    }
+
+    /// Timeout for a single request (10s by default)
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Start the delivery worker and create the hook
+    pub fn build(self) -> Result<HttpHook, LoggixError> {
+        let method = reqwest::Method::from_bytes(self.method.to_uppercase().as_bytes())
+            .map_err(LoggixError::config)?;
+        let client = reqwest::Client::builder()
+            .timeout(self.timeout)
+            .build()
+            .map_err(LoggixError::config)?;
+
+        let mut headers = reqwest::header::HeaderMap::new();
+        for (name, value) in &self.headers {
+            headers.insert(
+                reqwest::header::HeaderName::from_bytes(name.as_bytes())
+                    .map_err(LoggixError::config)?,
+                reqwest::header::HeaderValue::from_str(value).map_err(LoggixError::config)?,
+            );
+        }
+        if let Some(token) = &self.bearer_token {
+            headers.insert(
+                reqwest::header::AUTHORIZATION,
+                reqwest::header::HeaderValue::from_str(&format!("Bearer {}", token))
+                    .map_err(LoggixError::config)?,
+            );
+        }
+        let content_type = match (&self.content_type, &self.body) {
+            (Some(content_type), _) => content_type.clone(),
+            (None, HttpBody::JsonArray) => "application/json".to_string(),
+            (None, HttpBody::Ndjson) => "application/x-ndjson".to_string(),
+            (None, HttpBody::Template(_)) => "text/plain; charset=utf-8".to_string(),
+        };
+        headers.insert(
+            reqwest::header::CONTENT_TYPE,
+            reqwest::header::HeaderValue::from_str(&content_type).map_err(LoggixError::config)?,
+        );
+        if self.gzip {
+            headers.insert(
+                reqwest::header::CONTENT_ENCODING,
+                reqwest::header::HeaderValue::from_static("gzip"),
+            );
+        }
+
+        let body = match self.body {
+            HttpBody::JsonArray => Body::JsonArray,
+            HttpBody::Ndjson => Body::Ndjson,
+            HttpBody::Template(template) => Body::Template {
+                entry: compile(&template.entry, false)?,
+                separator: template.separator,
+                envelope: template
+                    .envelope
+                    .as_deref()
+                    .map(|envelope| compile(envelope, true))
+                    .transpose()?,
+            },
+        };
+
+        let sender = HttpSender {
+            client,
+            url: self.url,
+            method,
+            headers,
+            basic_auth: self.basic_auth,
+            body,
+            gzip: self.gzip,
+            backoff: self.backoff,
+        };
+        let worker = BatchWorker::spawn("HttpHook", self.batch, sender)?;
+
+        Ok(HttpHook {
+            levels: self.levels,
+            worker,
+        })
+    }
+}
+
+/// A hook that posts batches of entries to an HTTP endpoint
+///
+/// `fire` and `fire_async` only queue the entry, so logging never waits on
+/// the network. If a batch could not be delivered, the error is returned
+/// from the next `fire` or `flush`.
+pub struct HttpHook {
+    levels: Vec<Level>,
+    worker: BatchWorker<Record>,
+}
+
+impl HttpHook {
+    /// Start building a hook that sends to `url`
+    pub fn builder(url: &str) -> HttpHookBuilder {
+        HttpHookBuilder {
+            url: url.to_string(),
+            method: "POST".to_string(),
+            headers: Vec::new(),
+            bearer_token: None,
+            basic_auth: None,
+            body: HttpBody::default(),
+            content_type: None,
+            gzip: false,
+            levels: vec![
+                Level::Trace,
+                Level::Debug,
+                Level::Info,
+                Level::Warn,
+                Level::Error,
+                Level::Fatal,
+                Level::Panic,
+            ],
+            backoff: Backoff::default(),
+            batch: BatchConfig::default(),
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// Send everything queued so far, waiting up to `timeout`
+    pub fn flush(&self, timeout: Duration) -> Result<(), LoggixError> {
+        self.worker.flush(timeout)
+    }
+}
+
+impl Hook for HttpHook {
+    fn levels(&self) -> Vec<Level> {
+        self.levels.clone()
+    }
+
+    fn fire(&self, entry: &Entry) -> Result<(), LoggixError> {
+        let record = Record::from(entry);
+        let size = record.message.len();
+        self.worker.push(record, size)?;
+        self.worker.take_failure()
+    }
+
+    fn flush(&self, timeout: Duration) -> Result<(), LoggixError> {
+        HttpHook::flush(self, timeout)
+    }
+
+    fn flush_async(
+        &self,
+        timeout: Duration,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), LoggixError>> + Send + '_>>
+    {
+        Box::pin(self.worker.flush_async(timeout))
+    }
+}
+
+/// A compiled [`BodyTemplate`] part
+enum Segment {
+    Text(String),
+    Value { name: String, json: bool },
+}
+
+enum Body {
+    JsonArray,
+    Ndjson,
+    Template {
+        entry: Vec<Segment>,
+        separator: String,
+        envelope: Option<Vec<Segment>>,
+    },
+}
+
+/// Split a template into text and placeholders, rejecting unknown names
+fn compile(template: &str, envelope: bool) -> Result<Vec<Segment>, LoggixError> {
+    let mut segments = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let end = rest[start..].find("}}").ok_or_else(|| {
+            LoggixError::config(format!("unclosed placeholder in template: {}", template))
+        })?;
+        if start > 0 {
+            segments.push(Segment::Text(rest[..start].to_string()));
+        }
+        let placeholder = rest[start + 2..start + end].trim();
+        let (name, json) = match placeholder.strip_suffix("|json") {
+            Some(name) => (name.trim(), true),
+            None => (placeholder, false),
+        };
+        let known = if envelope {
+            matches!(name, "entries" | "count")
+        } else {
+            matches!(name, "timestamp" | "level" | "message" | "fields")
+                || name.starts_with("field.")
+        };
+        if !known {
+            return Err(LoggixError::config(format!(
+                "unknown template placeholder: {{{{{}}}}}",
+                placeholder
+            )));
+        }
+        segments.push(Segment::Value {
+            name: name.to_string(),
+            json,
+        });
+        rest = &rest[start + end + 2..];
+    }
+    if !rest.is_empty() {
+        segments.push(Segment::Text(rest.to_string()));
+    }
+    Ok(segments)
+}
+
+/// Render compiled segments, looking placeholder values up with `value`
+fn render(segments: &[Segment], value: impl Fn(&str) -> Value) -> String {
+    let mut output = String::new();
+    for segment in segments {
+        match segment {
+            Segment::Text(text) => output.push_str(text),
+            Segment::Value { name, json } => match value(name) {
+                Value::String(s) if !json => output.push_str(&s),
+                other => output.push_str(&other.to_string()),
+            },
+        }
+    }
+    output
+}
+
+fn record_value(record: &Record, name: &str) -> Value {
+    match name {
+        "timestamp" => Value::String(record.timestamp.to_rfc3339()),
+        "level" => Value::String(record.level.to_string().to_lowercase()),
+        "message" => Value::String(record.message.clone()),
+        "fields" => serde_json::to_value(&record.fields).unwrap_or_default(),
+        _ => name
+            .strip_prefix("field.")
+            .and_then(|field| record.fields.get(field))
+            .cloned()
+            .unwrap_or_default(),
+    }
+}
+
+struct HttpSender {
+    client: reqwest::Client,
+    url: String,
+    method: reqwest::Method,
+    headers: reqwest::header::HeaderMap,
+    basic_auth: Option<(String, String)>,
+    body: Body,
+    gzip: bool,
+    backoff: Backoff,
+}
+
+impl HttpSender {
+    fn encode(&self, batch: &[Record]) -> Result<Vec<u8>, BoxError> {
+        let body = match &self.body {
+            Body::JsonArray => serde_json::to_vec(batch)?,
+            Body::Ndjson => {
+                let mut body = Vec::new();
+                for record in batch {
+                    serde_json::to_writer(&mut body, record)?;
+                    body.push(b'\n');
+                }
+                body
+            }
+            Body::Template {
+                entry,
+                separator,
+                envelope,
+            } => {
+                let entries: Vec<String> = batch
+                    .iter()
+                    .map(|record| render(entry, |name| record_value(record, name)))
+                    .collect();
+                let entries = entries.join(separator);
+                match envelope {
+                    Some(envelope) => render(envelope, |name| match name {
+                        "count" => Value::from(batch.len()),
+                        _ => Value::String(entries.clone()),
+                    })
+                    .into_bytes(),
+                    None => entries.into_bytes(),
+                }
+            }
+        };
+
+        if self.gzip {
+            let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(&body)?;
+            Ok(encoder.finish()?)
+        } else {
+            Ok(body)
+        }
+    }
+
+    async fn send(&self, batch: Vec<Record>) -> Result<(), BoxError> {
+        let body = self.encode(&batch)?;
+        send_http(&self.backoff, || {
+            let request = self
+                .client
+                .request(self.method.clone(), &self.url)
+                .headers(self.headers.clone())
+                .body(body.clone());
+            match &self.basic_auth {
+                Some((username, password)) => request.basic_auth(username, Some(password)),
+                None => request,
+            }
+        })
+        .await?;
+        Ok(())
+    }
+}
+
+impl Export<Record> for HttpSender {
+    fn export(&self, batch: Vec<Record>) -> ExportFuture<'_> {
+        Box::pin(self.send(batch))
+    }
+}
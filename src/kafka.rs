@@ -0,0 +1,1061 @@
+//! Apache Kafka hook.
+//!
+//! [`KafkaHook::builder`] passes arbitrary librdkafka properties through to
+//! the producer, so security (SASL, SSL), compression, batching and
+//! delivery guarantees are configured the same way as in any other
+//! librdkafka client. Properties are checked when the hook is built, so a
+//! typo or an inconsistent security setup fails at startup instead of on
+//! the first log entry.
+//!
+//! Messages always go through a `ThreadedProducer`. By default the hook
+//! awaits each message's delivery report, so it must be fired from async
+//! code; a sync [`Logger::log`](crate::Logger::log) runs that on a helper
+//! thread. [`KafkaHookBuilder::threaded`] makes `fire` just enqueue the
+//! message, leaving delivery to the producer's polling thread, so it works
+//! from plain threads.
+//!
+//! Each entry goes to the hook's topic unless a routing rule picks another
+//! one, e.g. by level or from a template over field values. Routed topic
+//! names are sanitized and their number is capped, so field values cannot
+//! create an unbounded set of topics.
+//!
+//! Message keys, record headers and the partitioning strategy can be derived
+//! from entry data as well, so consumers can route records without parsing
+//! the payload.
+//!
+//! [`KafkaHook::metrics`] counts enqueued, delivered, failed and retried
+//! messages, and [`KafkaHookBuilder::on_statistics`] passes librdkafka's
+//! statistics JSON to a callback. `flush` is also part of [`Hook`], so a
+//! dropped [`Logger`](crate::Logger) waits for in-flight messages instead of
+//! losing them.
+//!
+//! Payloads are JSON by default. [`KafkaEncoding`] switches to any
+//! [`Formatter`], MessagePack, or Avro registered with a schema registry.
+
+use crate::avro::{self, SchemaRegistry};
+use crate::template::{compile_template, TemplatePart};
+use crate::{Entry, Formatter, Hook, Level, LoggixError};
+use rdkafka::client::ClientContext;
+use rdkafka::config::ClientConfig;
+use rdkafka::error::{KafkaError, RDKafkaErrorCode};
+use rdkafka::message::{Header, Message, OwnedHeaders};
+use rdkafka::producer::{
+    BaseRecord, DeliveryResult, Partitioner, Producer, ProducerContext, ThreadedProducer,
+    PARTITION_UA,
+};
+use std::collections::HashSet;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+
+type HookFuture<'a> = Pin<Box<dyn Future<Output = Result<(), LoggixError>> + Send + 'a>>;
+
+/// Builder for [`KafkaHook`]
+pub struct KafkaHookBuilder {
+    topic: String,
+    properties: Vec<(String, String)>,
+    key: Option<KeySource>,
+    headers: Vec<(String, HeaderSource)>,
+    metadata_headers: bool,
+    partitioner: Option<KafkaPartitioner>,
+    encoding: KafkaEncoding,
+    content_type: Option<String>,
+    send_timeout: Duration,
+    threaded: bool,
+    on_delivery: Option<DeliveryCallback>,
+    on_statistics: Option<StatisticsCallback>,
+    routes: Vec<(RouteMatch, String)>,
+    max_topics: usize,
+}
+
+impl KafkaHookBuilder {
+    /// Set a librdkafka producer property, e.g. `compression.type` or
+    /// `linger.ms`; later values replace earlier ones
+    pub fn property(mut self, key: &str, value: &str) -> Self {
+        self.properties.retain(|(k, _)| k != key);
+        self.properties.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Set several librdkafka producer properties at once
+    pub fn properties<K, V, I>(mut self, properties: I) -> Self
+    where
+        K: AsRef<str>,
+        V: AsRef<str>,
+        I: IntoIterator<Item = (K, V)>,
+    {
+        for (key, value) in properties {
+            self = self.property(key.as_ref(), value.as_ref());
+        }
+        self
+    }
+
+    /// Authenticate with SASL, e.g. `sasl("SCRAM-SHA-512", "user", "pass")`
+    ///
+    /// Sets `security.protocol` to `SASL_SSL` unless it was already set.
+    pub fn sasl(self, mechanism: &str, username: &str, password: &str) -> Self {
+        let builder = if self.get("security.protocol").is_none() {
+            self.property("security.protocol", "SASL_SSL")
+        } else {
+            self
+        };
+        builder
+            .property("sasl.mechanism", mechanism)
+            .property("sasl.username", username)
+            .property("sasl.password", password)
+    }
+
+    /// Use the message field `key_field` as the Kafka message key
+    ///
+    /// Numbers and booleans are used in their text form; entries without
+    /// the field are sent without a key.
+    pub fn key_field(mut self, key_field: &str) -> Self {
+        self.key = Some(KeySource::Field(key_field.to_string()));
+        self
+    }
+
+    /// Build the message key from a template such as `{tenant}:{user_id}`
+    ///
+    /// Placeholders work as in [`route_template`](Self::route_template), but
+    /// values are used unchanged. Entries missing a field are sent without a
+    /// key.
+    pub fn key_template(mut self, template: &str) -> Self {
+        self.key = Some(KeySource::Template(template.to_string()));
+        self
+    }
+
+    /// Add a record header with a fixed value
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers
+            .push((name.to_string(), HeaderSource::Value(value.to_string())));
+        self
+    }
+
+    /// Copy the field `field` into a record header of the same name
+    ///
+    /// Entries without the field get no such header.
+    pub fn header_field(mut self, field: &str) -> Self {
+        self.headers
+            .push((field.to_string(), HeaderSource::Field(field.to_string())));
+        self
+    }
+
+    /// Add `level`, `hostname` and `content-type` headers to every record
+    pub fn metadata_headers(mut self, enabled: bool) -> Self {
+        self.metadata_headers = enabled;
+        self
+    }
+
+    /// How entries are encoded into the message payload (JSON by default)
+    pub fn encoding(mut self, encoding: KafkaEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Encode entries with `formatter`, e.g. `ECSFormatter` or
+    /// `LogfmtFormatter`
+    pub fn formatter<F: Formatter + 'static>(self, formatter: F) -> Self {
+        self.encoding(KafkaEncoding::Formatter(Box::new(formatter)))
+    }
+
+    /// Value of the `content-type` metadata header
+    ///
+    /// Defaults to the encoding's media type; formatter payloads have none
+    /// unless it is set here.
+    pub fn content_type(mut self, content_type: &str) -> Self {
+        self.content_type = Some(content_type.to_string());
+        self
+    }
+
+    /// Choose how records are assigned to partitions
+    ///
+    /// Without one, librdkafka's `partitioner` property decides.
+    pub fn partitioner(mut self, partitioner: KafkaPartitioner) -> Self {
+        self.partitioner = Some(partitioner);
+        self
+    }
+
+    /// How long a send may wait for space in the producer queue
+    ///
+    /// The default of zero fails the send immediately when the queue is full.
+    pub fn send_timeout(mut self, timeout: Duration) -> Self {
+        self.send_timeout = timeout;
+        self
+    }
+
+    /// Deliver through a `ThreadedProducer` so `fire` works without an
+    /// async runtime
+    pub fn threaded(mut self, enabled: bool) -> Self {
+        self.threaded = enabled;
+        self
+    }
+
+    /// Call `callback` with the outcome of every message
+    ///
+    /// In threaded mode the callback runs on the producer's polling thread.
+    pub fn on_delivery<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&KafkaDeliveryReport) + Send + Sync + 'static,
+    {
+        self.on_delivery = Some(Arc::new(callback));
+        self
+    }
+
+    /// Call `callback` with librdkafka's statistics JSON every `interval`
+    ///
+    /// The JSON is passed through as is; see librdkafka's STATISTICS.md
+    /// for its fields. The callback runs on the producer's polling thread.
+    pub fn on_statistics<F>(self, interval: Duration, callback: F) -> Self
+    where
+        F: Fn(&str) + Send + Sync + 'static,
+    {
+        let mut builder = self.property(
+            "statistics.interval.ms",
+            &interval.as_millis().max(1).to_string(),
+        );
+        builder.on_statistics = Some(Arc::new(callback));
+        builder
+    }
+
+    /// Send entries at `level` or above to `topic`
+    ///
+    /// Routes are tried in the order they were added; entries no route
+    /// matches go to the hook's topic.
+    pub fn route_level(mut self, level: Level, topic: &str) -> Self {
+        self.routes
+            .push((RouteMatch::Level(level), topic.to_string()));
+        self
+    }
+
+    /// Build the topic from a template such as `logs.{tenant}` or
+    /// `logs.{service}.{level}`
+    ///
+    /// `{level}` is the lowercase level and any other placeholder is a
+    /// field. The route only matches entries that have every field in the
+    /// template; field values are reduced to the characters Kafka allows in
+    /// topic names.
+    pub fn route_template(mut self, template: &str) -> Self {
+        self.routes
+            .push((RouteMatch::Template, template.to_string()));
+        self
+    }
+
+    /// Maximum number of distinct topics templates may produce (100 by
+    /// default); entries that would need another topic go to the hook's
+    /// topic instead
+    pub fn max_topics(mut self, max: usize) -> Self {
+        self.max_topics = max;
+        self
+    }
+
+    fn get(&self, key: &str) -> Option<&str> {
+        self.properties
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Check settings librdkafka would only reject later, or not at all
+    fn validate(&self) -> Result<(), LoggixError> {
+        if self.topic.is_empty() {
+            return Err(LoggixError::config("Kafka topic must not be empty"));
+        }
+        validate_topic(&self.topic)?;
+        for (route, topic) in &self.routes {
+            match route {
+                RouteMatch::Level(_) => validate_topic(topic)?,
+                RouteMatch::Template => {
+                    let literal: String = compile_template(topic, "topic")?
+                        .iter()
+                        .map(|part| match part {
+                            TemplatePart::Text(text) => text.as_str(),
+                            _ => "x",
+                        })
+                        .collect();
+                    validate_topic(&literal)?;
+                }
+            }
+        }
+        if let Some(KeySource::Template(template)) = &self.key {
+            compile_template(template, "key")?;
+        }
+        if let Some(KafkaPartitioner::Fixed(partition)) = self.partitioner {
+            if partition < 0 {
+                return Err(LoggixError::config(format!(
+                    "invalid Kafka partition {}",
+                    partition
+                )));
+            }
+        }
+        if self
+            .get("bootstrap.servers")
+            .unwrap_or("")
+            .trim()
+            .is_empty()
+        {
+            return Err(LoggixError::config("bootstrap.servers must not be empty"));
+        }
+
+        let protocol = self
+            .get("security.protocol")
+            .unwrap_or("plaintext")
+            .to_lowercase();
+        if !matches!(
+            protocol.as_str(),
+            "plaintext" | "ssl" | "sasl_plaintext" | "sasl_ssl"
+        ) {
+            return Err(LoggixError::config(format!(
+                "unknown security.protocol {:?}, expected PLAINTEXT, SSL, SASL_PLAINTEXT or SASL_SSL",
+                protocol
+            )));
+        }
+        if let Some(mechanism) = self.get("sasl.mechanism").or(self.get("sasl.mechanisms")) {
+            if !protocol.starts_with("sasl_") {
+                return Err(LoggixError::config(format!(
+                    "sasl.mechanism {} requires security.protocol SASL_PLAINTEXT or SASL_SSL, got {}",
+                    mechanism,
+                    protocol.to_uppercase()
+                )));
+            }
+            let needs_credentials = mechanism.eq_ignore_ascii_case("PLAIN")
+                || mechanism.to_uppercase().starts_with("SCRAM-");
+            if needs_credentials
+                && (self.get("sasl.username").is_none() || self.get("sasl.password").is_none())
+            {
+                return Err(LoggixError::config(format!(
+                    "sasl.mechanism {} requires sasl.username and sasl.password",
+                    mechanism
+                )));
+            }
+        }
+
+        if self.get("enable.idempotence") == Some("true") {
+            if let Some(acks) = self.get("acks").or(self.get("request.required.acks")) {
+                if acks != "all" && acks != "-1" {
+                    return Err(LoggixError::config(format!(
+                        "enable.idempotence requires acks=all, got acks={}",
+                        acks
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Validate the configuration and create the producer
+    pub fn build(mut self) -> Result<KafkaHook, LoggixError> {
+        self.validate()?;
+
+        let mut config = ClientConfig::new();
+        for (key, value) in &self.properties {
+            config.set(key, value);
+        }
+        if self.partitioner.is_some() {
+            // librdkafka bypasses the partitioner for keyless records otherwise
+            if self.get("sticky.partitioning.linger.ms").is_none() {
+                config.set("sticky.partitioning.linger.ms", "0");
+            }
+            // The partitioner is installed on the default topic config, which
+            // only exists once a topic-level property is set; the custom
+            // partitioner takes precedence over this value
+            if self.get("partitioner").is_none() {
+                config.set("partitioner", "consistent_random");
+            }
+        }
+        let counters = Arc::new(DeliveryCounters::default());
+        let context = DeliveryContext {
+            callback: self.on_delivery,
+            statistics: self.on_statistics,
+            counters: Arc::clone(&counters),
+            partitioner: self.partitioner.map(|strategy| HookPartitioner {
+                strategy,
+                next: AtomicUsize::new(0),
+            }),
+        };
+        let producer = config.create_with_context(context).map_err(|err| {
+            LoggixError::config(format!("invalid Kafka producer configuration: {}", err))
+        })?;
+
+        let routes = self
+            .routes
+            .into_iter()
+            .map(|(route, topic)| match route {
+                RouteMatch::Level(level) => Route::Level(level, topic),
+                RouteMatch::Template => {
+                    Route::Template(compile_template(&topic, "topic").expect("validated above"))
+                }
+            })
+            .collect();
+        let key = self.key.map(|key| match key {
+            KeySource::Field(name) => vec![TemplatePart::Field(name)],
+            KeySource::Template(template) => {
+                compile_template(&template, "key").expect("validated above")
+            }
+        });
+        if let KafkaEncoding::Avro(registry) = &mut self.encoding {
+            registry.default_subject(&self.topic);
+        }
+        let content_type = self.content_type.or_else(|| {
+            match self.encoding {
+                KafkaEncoding::Json => Some("application/json"),
+                KafkaEncoding::MessagePack => Some("application/msgpack"),
+                KafkaEncoding::Avro(_) => Some("application/avro"),
+                KafkaEncoding::Formatter(_) => None,
+            }
+            .map(str::to_string)
+        });
+        let hostname = if self.metadata_headers {
+            hostname::get()
+                .ok()
+                .map(|host| host.to_string_lossy().into_owned())
+        } else {
+            None
+        };
+
+        Ok(KafkaHook {
+            producer,
+            threaded: self.threaded,
+            topic: self.topic,
+            key,
+            headers: self.headers,
+            metadata_headers: self.metadata_headers,
+            hostname,
+            encoding: self.encoding,
+            content_type,
+            send_timeout: self.send_timeout,
+            counters,
+            routes,
+            max_topics: self.max_topics,
+            routed_topics: Mutex::new(HashSet::new()),
+        })
+    }
+}
+
+/// How entries are encoded into Kafka message payloads
+pub enum KafkaEncoding {
+    /// The entry serialized as JSON, the default
+    Json,
+    /// The output of a formatter, without its trailing newline
+    Formatter(Box<dyn Formatter>),
+    /// The entry as a MessagePack map with the same keys as the JSON form
+    MessagePack,
+    /// Avro in the schema-registry wire format, see [`SchemaRegistry`]
+    ///
+    /// The schema is registered under `<topic>-value` unless the registry
+    /// was given a subject.
+    Avro(SchemaRegistry),
+}
+
+enum KeySource {
+    Field(String),
+    Template(String),
+}
+
+enum HeaderSource {
+    Value(String),
+    Field(String),
+}
+
+/// What a routing rule matches on, before its template is compiled
+enum RouteMatch {
+    Level(Level),
+    Template,
+}
+
+enum Route {
+    Level(Level, String),
+    Template(Vec<TemplatePart>),
+}
+
+/// Text of a field value for keys, headers and topics; `None` for null
+fn field_text(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(s) => Some(s.clone()),
+        serde_json::Value::Null => None,
+        other => Some(other.to_string()),
+    }
+}
+
+/// Render a template, or `None` if the entry lacks one of its fields
+fn render_template(parts: &[TemplatePart], entry: &Entry) -> Option<String> {
+    let mut rendered = String::new();
+    for part in parts {
+        match part {
+            TemplatePart::Text(text) => rendered.push_str(text),
+            TemplatePart::Level => rendered.push_str(&entry.level.to_string().to_lowercase()),
+            TemplatePart::Field(name) => rendered.push_str(&field_text(entry.fields.get(name)?)?),
+        }
+    }
+    Some(rendered)
+}
+
+/// Maximum topic name length accepted by Kafka
+const MAX_TOPIC_LENGTH: usize = 249;
+
+fn is_topic_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '.' || c == '_' || c == '-'
+}
+
+fn validate_topic(topic: &str) -> Result<(), LoggixError> {
+    if topic.is_empty() || topic.len() > MAX_TOPIC_LENGTH || topic == "." || topic == ".." {
+        return Err(LoggixError::config(format!(
+            "invalid Kafka topic name {:?}",
+            topic
+        )));
+    }
+    if let Some(c) = topic.chars().find(|c| !is_topic_char(*c)) {
+        return Err(LoggixError::config(format!(
+            "invalid character {:?} in Kafka topic name {:?}",
+            c, topic
+        )));
+    }
+    Ok(())
+}
+
+/// Outcome of delivering one message, passed to
+/// [`KafkaHookBuilder::on_delivery`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KafkaDeliveryReport {
+    pub topic: String,
+    pub partition: i32,
+    /// Offset of the delivered message, `None` if delivery failed
+    pub offset: Option<i64>,
+    /// Why delivery failed
+    pub error: Option<String>,
+}
+
+impl KafkaDeliveryReport {
+    /// Whether the message was delivered
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+type DeliveryCallback = Arc<dyn Fn(&KafkaDeliveryReport) + Send + Sync>;
+
+type StatisticsCallback = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// Message counts of a [`KafkaHook`], see [`KafkaHook::metrics`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KafkaMetrics {
+    /// Messages accepted into the producer queue
+    pub enqueued: u64,
+    /// Messages acknowledged by the broker
+    pub delivered: u64,
+    /// Messages rejected by the producer or that failed delivery
+    pub failed: u64,
+    /// Sends repeated because the producer queue was full
+    pub retried: u64,
+}
+
+impl KafkaMetrics {
+    /// Messages enqueued but not yet delivered or failed
+    pub fn in_flight(&self) -> u64 {
+        self.enqueued.saturating_sub(self.delivered + self.failed)
+    }
+}
+
+#[derive(Default)]
+struct DeliveryCounters {
+    enqueued: AtomicU64,
+    delivered: AtomicU64,
+    failed_delivery: AtomicU64,
+    rejected: AtomicU64,
+    retried: AtomicU64,
+}
+
+/// Where to send the report of a message `fire_async` is waiting for
+type DeliveryWaiter = Box<Option<oneshot::Sender<KafkaDeliveryReport>>>;
+
+/// Forwards delivery reports from the polling thread to the callback and
+/// to `fire_async`, and provides the configured partitioner
+struct DeliveryContext {
+    callback: Option<DeliveryCallback>,
+    statistics: Option<StatisticsCallback>,
+    counters: Arc<DeliveryCounters>,
+    partitioner: Option<HookPartitioner>,
+}
+
+impl ClientContext for DeliveryContext {
+    fn stats_raw(&self, statistics: &[u8]) {
+        if let Some(callback) = &self.statistics {
+            callback(&String::from_utf8_lossy(statistics));
+        }
+    }
+}
+
+impl ProducerContext<HookPartitioner> for DeliveryContext {
+    type DeliveryOpaque = DeliveryWaiter;
+
+    fn delivery(&self, result: &DeliveryResult<'_>, waiter: Self::DeliveryOpaque) {
+        let report = match result {
+            Ok(message) => KafkaDeliveryReport {
+                topic: message.topic().to_string(),
+                partition: message.partition(),
+                offset: Some(message.offset()),
+                error: None,
+            },
+            Err((err, message)) => KafkaDeliveryReport {
+                topic: message.topic().to_string(),
+                partition: message.partition(),
+                offset: None,
+                error: Some(err.to_string()),
+            },
+        };
+        let counter = if report.is_ok() {
+            &self.counters.delivered
+        } else {
+            &self.counters.failed_delivery
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        if let Some(callback) = &self.callback {
+            callback(&report);
+        }
+        if let Some(waiter) = *waiter {
+            let _ = waiter.send(report);
+        }
+    }
+
+    fn get_custom_partitioner(&self) -> Option<&HookPartitioner> {
+        self.partitioner.as_ref()
+    }
+}
+
+/// How records are assigned to partitions, see
+/// [`KafkaHookBuilder::partitioner`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KafkaPartitioner {
+    /// Hash the key with murmur2 like the Java client, so records with the
+    /// same key share a partition; keyless records go round-robin
+    KeyHash,
+    /// Use the partitions in turn, ignoring the key
+    RoundRobin,
+    /// Send every record to this partition
+    Fixed(i32),
+}
+
+struct HookPartitioner {
+    strategy: KafkaPartitioner,
+    next: AtomicUsize,
+}
+
+impl Partitioner for HookPartitioner {
+    fn partition(
+        &self,
+        _topic: &str,
+        key: Option<&[u8]>,
+        partition_cnt: i32,
+        is_partition_available: impl Fn(i32) -> bool,
+    ) -> i32 {
+        if partition_cnt <= 0 {
+            return PARTITION_UA;
+        }
+        match (self.strategy, key) {
+            (KafkaPartitioner::Fixed(partition), _) if partition < partition_cnt => partition,
+            (KafkaPartitioner::Fixed(_), _) => PARTITION_UA,
+            (KafkaPartitioner::KeyHash, Some(key)) => {
+                ((murmur2(key) & 0x7fff_ffff) % partition_cnt as u32) as i32
+            }
+            _ => {
+                // Skip partitions without a leader, unless none has one
+                let mut partition = 0;
+                for _ in 0..partition_cnt {
+                    partition =
+                        (self.next.fetch_add(1, Ordering::Relaxed) % partition_cnt as usize) as i32;
+                    if is_partition_available(partition) {
+                        break;
+                    }
+                }
+                partition
+            }
+        }
+    }
+}
+
+/// Kafka's murmur2 hash, as used by the Java client's default partitioner
+fn murmur2(data: &[u8]) -> u32 {
+    const SEED: u32 = 0x9747_b28c;
+    const M: u32 = 0x5bd1_e995;
+    const R: u32 = 24;
+
+    let mut h = SEED ^ data.len() as u32;
+    let mut chunks = data.chunks_exact(4);
+    for chunk in &mut chunks {
+        let mut k = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        h = h.wrapping_mul(M);
+        h ^= k;
+    }
+    let rest = chunks.remainder();
+    if !rest.is_empty() {
+        for (i, byte) in rest.iter().enumerate() {
+            h ^= (*byte as u32) << (8 * i);
+        }
+        h = h.wrapping_mul(M);
+    }
+    h ^= h >> 13;
+    h = h.wrapping_mul(M);
+    h ^= h >> 15;
+    h
+}
+
+/// A hook that sends log entries to Kafka
+pub struct KafkaHook {
+    producer: ThreadedProducer<DeliveryContext, HookPartitioner>,
+    threaded: bool,
+    topic: String,
+    key: Option<Vec<TemplatePart>>,
+    headers: Vec<(String, HeaderSource)>,
+    metadata_headers: bool,
+    hostname: Option<String>,
+    encoding: KafkaEncoding,
+    content_type: Option<String>,
+    send_timeout: Duration,
+    counters: Arc<DeliveryCounters>,
+    routes: Vec<Route>,
+    max_topics: usize,
+    routed_topics: Mutex<HashSet<String>>,
+}
+
+/// Everything a record borrows, prepared before sending
+struct OutgoingMessage {
+    topic: String,
+    key: Option<String>,
+    payload: Vec<u8>,
+    headers: Vec<(String, String)>,
+}
+
+impl OutgoingMessage {
+    fn record(&self, waiter: DeliveryWaiter) -> BaseRecord<'_, str, [u8], DeliveryWaiter> {
+        let mut record =
+            BaseRecord::with_opaque_to(&self.topic, waiter).payload(self.payload.as_slice());
+        if let Some(key) = &self.key {
+            record = record.key(key.as_str());
+        }
+        if !self.headers.is_empty() {
+            let headers = self
+                .headers
+                .iter()
+                .fold(OwnedHeaders::new(), |headers, (key, value)| {
+                    headers.insert(Header {
+                        key,
+                        value: Some(value),
+                    })
+                });
+            record = record.headers(headers);
+        }
+        record
+    }
+}
+
+impl KafkaHook {
+    /// Create a new KafkaHook
+    pub fn new(bootstrap_servers: &str, topic: String) -> Result<Self, LoggixError> {
+        Self::builder(bootstrap_servers, &topic).build()
+    }
+
+    /// Start building a hook that produces to `topic`
+    pub fn builder(bootstrap_servers: &str, topic: &str) -> KafkaHookBuilder {
+        KafkaHookBuilder {
+            topic: topic.to_string(),
+            properties: vec![(
+                "bootstrap.servers".to_string(),
+                bootstrap_servers.to_string(),
+            )],
+            key: None,
+            headers: Vec::new(),
+            metadata_headers: false,
+            partitioner: None,
+            encoding: KafkaEncoding::Json,
+            content_type: None,
+            send_timeout: Duration::from_secs(0),
+            threaded: false,
+            on_delivery: None,
+            on_statistics: None,
+            routes: Vec::new(),
+            max_topics: 100,
+        }
+    }
+
+    /// The message key for an entry
+    pub fn key_for(&self, entry: &Entry) -> Option<String> {
+        render_template(self.key.as_ref()?, entry)
+    }
+
+    /// The record headers for an entry
+    pub fn headers_for(&self, entry: &Entry) -> Vec<(String, String)> {
+        let mut headers = Vec::new();
+        if self.metadata_headers {
+            headers.push(("level".to_string(), entry.level.to_string().to_lowercase()));
+            if let Some(hostname) = &self.hostname {
+                headers.push(("hostname".to_string(), hostname.clone()));
+            }
+            if let Some(content_type) = &self.content_type {
+                headers.push(("content-type".to_string(), content_type.clone()));
+            }
+        }
+        for (name, source) in &self.headers {
+            let value = match source {
+                HeaderSource::Value(value) => Some(value.clone()),
+                HeaderSource::Field(field) => entry.fields.get(field).and_then(field_text),
+            };
+            if let Some(value) = value {
+                headers.push((name.clone(), value));
+            }
+        }
+        headers
+    }
+
+    /// The topic an entry is sent to
+    pub fn topic_for(&self, entry: &Entry) -> String {
+        for route in &self.routes {
+            match route {
+                Route::Level(level, topic) if entry.level >= *level => return topic.clone(),
+                Route::Level(..) => {}
+                Route::Template(parts) => {
+                    if let Some(topic) = self.render_topic(parts, entry) {
+                        return topic;
+                    }
+                }
+            }
+        }
+        self.topic.clone()
+    }
+
+    /// Render a template route, or `None` if a field is missing or the
+    /// topic limit is reached
+    fn render_topic(&self, parts: &[TemplatePart], entry: &Entry) -> Option<String> {
+        // Literal parts were validated, so only field values are affected
+        let topic: String = render_template(parts, entry)?
+            .chars()
+            .map(|c| if is_topic_char(c) { c } else { '_' })
+            .collect();
+        if topic.len() > MAX_TOPIC_LENGTH || topic.chars().all(|c| c == '.') {
+            return None;
+        }
+
+        let mut routed = self
+            .routed_topics
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if routed.contains(&topic) {
+            return Some(topic);
+        }
+        if routed.len() < self.max_topics {
+            routed.insert(topic.clone());
+            return Some(topic);
+        }
+        None
+    }
+
+    /// Wait up to `timeout` for all queued messages to be delivered
+    pub fn flush(&self, timeout: Duration) -> Result<(), LoggixError> {
+        self.producer.flush(timeout)?;
+        Ok(())
+    }
+
+    /// Message counts since the hook was built
+    ///
+    /// Delivery outcomes are counted as librdkafka reports them, so after a
+    /// successful [`flush`](Self::flush) nothing is in flight.
+    pub fn metrics(&self) -> KafkaMetrics {
+        let counters = &self.counters;
+        KafkaMetrics {
+            enqueued: counters.enqueued.load(Ordering::Relaxed),
+            delivered: counters.delivered.load(Ordering::Relaxed),
+            failed: counters.failed_delivery.load(Ordering::Relaxed)
+                + counters.rejected.load(Ordering::Relaxed),
+            retried: counters.retried.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Set the field name to use as the Kafka message key
+    pub fn with_key_field(mut self, key_field: String) -> Self {
+        self.key = Some(vec![TemplatePart::Field(key_field)]);
+        self
+    }
+
+    /// The encoded message payload for an entry
+    ///
+    /// With Avro encoding the first call registers the schema, blocking
+    /// until the registry responds.
+    pub fn payload_for(&self, entry: &Entry) -> Result<Vec<u8>, LoggixError> {
+        let schema_id = match &self.encoding {
+            KafkaEncoding::Avro(registry) => Some(
+                registry
+                    .schema_id_blocking()
+                    .map_err(|e| LoggixError::hook("KafkaHook", e))?,
+            ),
+            _ => None,
+        };
+        self.encode(entry, schema_id)
+    }
+
+    fn encode(&self, entry: &Entry, schema_id: Option<u32>) -> Result<Vec<u8>, LoggixError> {
+        match &self.encoding {
+            KafkaEncoding::Json => Ok(serde_json::to_vec(entry)?),
+            KafkaEncoding::Formatter(formatter) => {
+                let mut output = formatter.format(entry)?;
+                while output.last() == Some(&b'\n') || output.last() == Some(&b'\r') {
+                    output.pop();
+                }
+                Ok(output)
+            }
+            KafkaEncoding::MessagePack => {
+                let value = crate::encoding::to_msgpack(&serde_json::to_value(entry)?);
+                let mut output = Vec::new();
+                rmpv::encode::write_value(&mut output, &value).map_err(LoggixError::format)?;
+                Ok(output)
+            }
+            KafkaEncoding::Avro(_) => Ok(avro::encode(
+                entry,
+                schema_id.expect("schema ID is resolved before encoding"),
+            )),
+        }
+    }
+
+    fn message(&self, entry: &Entry, payload: Vec<u8>) -> OutgoingMessage {
+        OutgoingMessage {
+            topic: self.topic_for(entry),
+            key: self.key_for(entry),
+            payload,
+            headers: self.headers_for(entry),
+        }
+    }
+}
+
+impl Hook for KafkaHook {
+    fn levels(&self) -> Vec<Level> {
+        vec![
+            Level::Trace,
+            Level::Debug,
+            Level::Info,
+            Level::Warn,
+            Level::Error,
+            Level::Fatal,
+            Level::Panic,
+        ]
+    }
+
+    fn requires_async(&self) -> bool {
+        !self.threaded
+    }
+
+    fn fire(&self, entry: &Entry) -> Result<(), LoggixError> {
+        if !self.threaded {
+            // Only reached when called directly: `requires_async` makes
+            // `Logger::log` use `fire_async` for this hook
+            return Err(LoggixError::hook(
+                "KafkaHook",
+                "fire needs a threaded producer; call fire_async or build the hook with threaded(true)",
+            ));
+        }
+
+        let message = self.message(entry, self.payload_for(entry)?);
+        let mut record = message.record(Box::new(None));
+
+        // Wait for queue space until the send timeout runs out
+        let deadline = Instant::now() + self.send_timeout;
+        loop {
+            match self.producer.send(record) {
+                Ok(()) => {
+                    self.counters.enqueued.fetch_add(1, Ordering::Relaxed);
+                    return Ok(());
+                }
+                Err((KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull), returned))
+                    if Instant::now() < deadline =>
+                {
+                    record = returned;
+                    self.counters.retried.fetch_add(1, Ordering::Relaxed);
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+                Err((err, _)) => {
+                    self.counters.rejected.fetch_add(1, Ordering::Relaxed);
+                    return Err(err.into());
+                }
+            }
+        }
+    }
+
+    fn fire_async<'a>(&'a self, entry: &'a Entry) -> HookFuture<'a> {
+        Box::pin(async move {
+            if self.threaded {
+                // Threaded delivery never blocks, so there is nothing to await
+                return self.fire(entry);
+            }
+
+            let schema_id = match &self.encoding {
+                KafkaEncoding::Avro(registry) => Some(
+                    registry
+                        .schema_id()
+                        .await
+                        .map_err(|e| LoggixError::hook("KafkaHook", e))?,
+                ),
+                _ => None,
+            };
+            let message = self.message(entry, self.encode(entry, schema_id)?);
+            let (waiter, report) = oneshot::channel();
+            let mut record = message.record(Box::new(Some(waiter)));
+
+            let deadline = Instant::now() + self.send_timeout;
+            loop {
+                match self.producer.send(record) {
+                    Ok(()) => {
+                        self.counters.enqueued.fetch_add(1, Ordering::Relaxed);
+                        break;
+                    }
+                    Err((KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull), returned))
+                        if Instant::now() < deadline =>
+                    {
+                        record = returned;
+                        self.counters.retried.fetch_add(1, Ordering::Relaxed);
+                        tokio::time::sleep(Duration::from_millis(10)).await;
+                    }
+                    Err((err, _)) => {
+                        self.counters.rejected.fetch_add(1, Ordering::Relaxed);
+                        return Err(err.into());
+                    }
+                }
+            }
+
+            let report = report.await.map_err(|_| {
+                LoggixError::hook(
+                    "KafkaHook",
+                    "producer shut down before delivering the message",
+                )
+            })?;
+            match report.error {
+                Some(error) => Err(LoggixError::hook("KafkaHook", error)),
+                None => Ok(()),
+            }
+        })
+    }
+
+    fn flush(&self, timeout: Duration) -> Result<(), LoggixError> {
+        KafkaHook::flush(self, timeout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::murmur2;
+
+    #[test]
+    fn test_murmur2_matches_java_client() {
+        // Values from the Java client's partitioner tests
+        assert_eq!(murmur2(b"21") as i32, -973932308);
+        assert_eq!(murmur2(b"foobar") as i32, -790332482);
+        assert_eq!(murmur2(b"a-little-bit-long-string") as i32, -985981536);
+        assert_eq!(murmur2(b"a-little-bit-longer-string") as i32, -1486304829);
+        assert_eq!(
+            murmur2(b"lkjh234lh9fiuh90y23oiuhsafujhadof229phr9h19h89h8") as i32,
+            -58897971
+        );
+        assert_eq!(murmur2(b"abc") as i32, 479470107);
+    }
+}
@@ -0,0 +1,282 @@
+//! Building a [`KafkaHook`] from the `kafka:` section of a YAML file.
+//!
+//! The section uses the same keys as the `config.yaml` shipped with the
+//! repository. Every key can be overridden with a `LOGGIX_KAFKA_<KEY>`
+//! environment variable, e.g. `LOGGIX_KAFKA_BOOTSTRAP_SERVERS`, so the same
+//! file works across environments. With `create_topic: true` the topic is
+//! created through the admin client, using the configured partitions and
+//! replication factor, before the hook is returned.
+
+use crate::batch::block_on_thread;
+use crate::{KafkaHook, LoggixError};
+use rdkafka::admin::{AdminClient, AdminOptions, NewTopic, TopicReplication};
+use rdkafka::client::DefaultClientContext;
+use rdkafka::config::ClientConfig;
+use rdkafka::types::RDKafkaErrorCode;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
+
+/// Prefix of the environment variables that override [`KafkaConfig`] keys
+pub const KAFKA_ENV_PREFIX: &str = "LOGGIX_KAFKA_";
+
+/// The `kafka:` section of a configuration file
+///
+/// `group_id`, `auto_offset_reset` and `session_timeout_ms` are consumer
+/// settings; they are accepted so the shipped file parses, but the hook
+/// does not use them.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct KafkaConfig {
+    pub bootstrap_servers: Option<String>,
+    pub group_id: Option<String>,
+    pub auto_offset_reset: Option<String>,
+    pub socket_timeout_ms: Option<u32>,
+    pub session_timeout_ms: Option<u32>,
+    /// Replication factor for created topics (1 by default)
+    pub replication_factor: Option<i32>,
+    /// Partition count for created topics (1 by default)
+    pub partitions: Option<i32>,
+    /// Create the topic when building the hook
+    pub create_topic: bool,
+    /// Additional librdkafka producer properties
+    pub properties: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct ConfigFile {
+    kafka: Option<KafkaConfig>,
+}
+
+/// Why a [`KafkaHook`] could not be built from configuration
+#[derive(Debug)]
+pub enum KafkaConfigError {
+    /// The configuration file could not be read
+    Io(std::io::Error),
+    /// The configuration is not valid YAML or has the wrong shape
+    Parse(serde_yaml::Error),
+    /// A required setting is missing
+    Missing(&'static str),
+    /// A setting has an unusable value
+    Invalid { setting: String, reason: String },
+    /// The producer rejected the configuration
+    Producer(String),
+    /// The topic could not be created
+    TopicCreation(String),
+}
+
+impl fmt::Display for KafkaConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KafkaConfigError::Io(err) => write!(f, "cannot read Kafka configuration: {}", err),
+            KafkaConfigError::Parse(err) => write!(f, "invalid Kafka configuration: {}", err),
+            KafkaConfigError::Missing(setting) => {
+                write!(f, "missing Kafka setting `{}`", setting)
+            }
+            KafkaConfigError::Invalid { setting, reason } => {
+                write!(f, "invalid Kafka setting `{}`: {}", setting, reason)
+            }
+            KafkaConfigError::Producer(err) => write!(f, "{}", err),
+            KafkaConfigError::TopicCreation(err) => {
+                write!(f, "cannot create Kafka topic: {}", err)
+            }
+        }
+    }
+}
+
+impl std::error::Error for KafkaConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            KafkaConfigError::Io(err) => Some(err),
+            KafkaConfigError::Parse(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+fn invalid(setting: &str, reason: impl fmt::Display) -> KafkaConfigError {
+    KafkaConfigError::Invalid {
+        setting: setting.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(setting: &str, value: &str) -> Result<T, KafkaConfigError>
+where
+    T::Err: fmt::Display,
+{
+    value.trim().parse().map_err(|err| invalid(setting, err))
+}
+
+impl KafkaConfig {
+    /// Parse the `kafka:` section of a YAML document
+    pub fn from_yaml_str(yaml: &str) -> Result<Self, KafkaConfigError> {
+        let file: ConfigFile = serde_yaml::from_str(yaml).map_err(KafkaConfigError::Parse)?;
+        file.kafka.ok_or(KafkaConfigError::Missing("kafka"))
+    }
+
+    /// Read the `kafka:` section of a YAML file
+    pub fn from_yaml_file<P: AsRef<Path>>(path: P) -> Result<Self, KafkaConfigError> {
+        let yaml = std::fs::read_to_string(path).map_err(KafkaConfigError::Io)?;
+        Self::from_yaml_str(&yaml)
+    }
+
+    /// Apply `LOGGIX_KAFKA_*` environment variable overrides
+    pub fn apply_env(&mut self) -> Result<(), KafkaConfigError> {
+        self.apply_overrides(|name| std::env::var(name).ok())
+    }
+
+    /// Apply overrides looked up by variable name, e.g.
+    /// `LOGGIX_KAFKA_PARTITIONS`, from a source other than the environment
+    pub fn apply_overrides<F>(&mut self, lookup: F) -> Result<(), KafkaConfigError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let var = |key: &str| lookup(&format!("{}{}", KAFKA_ENV_PREFIX, key.to_uppercase()));
+
+        if let Some(value) = var("bootstrap_servers") {
+            self.bootstrap_servers = Some(value);
+        }
+        if let Some(value) = var("group_id") {
+            self.group_id = Some(value);
+        }
+        if let Some(value) = var("auto_offset_reset") {
+            self.auto_offset_reset = Some(value);
+        }
+        if let Some(value) = var("socket_timeout_ms") {
+            self.socket_timeout_ms = Some(parse_number("socket_timeout_ms", &value)?);
+        }
+        if let Some(value) = var("session_timeout_ms") {
+            self.session_timeout_ms = Some(parse_number("session_timeout_ms", &value)?);
+        }
+        if let Some(value) = var("replication_factor") {
+            self.replication_factor = Some(parse_number("replication_factor", &value)?);
+        }
+        if let Some(value) = var("partitions") {
+            self.partitions = Some(parse_number("partitions", &value)?);
+        }
+        if let Some(value) = var("create_topic") {
+            self.create_topic = match value.trim().to_lowercase().as_str() {
+                "true" | "1" | "yes" => true,
+                "false" | "0" | "no" => false,
+                other => {
+                    return Err(invalid(
+                        "create_topic",
+                        format!("expected a boolean, got {:?}", other),
+                    ))
+                }
+            };
+        }
+        Ok(())
+    }
+
+    /// Check the settings, returning the bootstrap servers
+    fn validate(&self) -> Result<&str, KafkaConfigError> {
+        let bootstrap_servers = self
+            .bootstrap_servers
+            .as_deref()
+            .map(str::trim)
+            .filter(|servers| !servers.is_empty())
+            .ok_or(KafkaConfigError::Missing("bootstrap_servers"))?;
+        if self.socket_timeout_ms == Some(0) {
+            return Err(invalid("socket_timeout_ms", "must be positive"));
+        }
+        if let Some(partitions) = self.partitions {
+            if partitions < 1 {
+                return Err(invalid("partitions", "must be at least 1"));
+            }
+        }
+        if let Some(replication_factor) = self.replication_factor {
+            if replication_factor < 1 {
+                return Err(invalid("replication_factor", "must be at least 1"));
+            }
+        }
+        Ok(bootstrap_servers)
+    }
+
+    /// Producer properties derived from the settings
+    fn producer_properties(&self, bootstrap_servers: &str) -> Vec<(String, String)> {
+        let mut properties = vec![(
+            "bootstrap.servers".to_string(),
+            bootstrap_servers.to_string(),
+        )];
+        if let Some(timeout) = self.socket_timeout_ms {
+            properties.push(("socket.timeout.ms".to_string(), timeout.to_string()));
+        }
+        properties.extend(
+            self.properties
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone())),
+        );
+        properties
+    }
+
+    /// Create `topic` unless it already exists
+    fn create_topic(
+        &self,
+        properties: &[(String, String)],
+        topic: &str,
+    ) -> Result<(), KafkaConfigError> {
+        let mut config = ClientConfig::new();
+        for (key, value) in properties {
+            config.set(key, value);
+        }
+        let admin: AdminClient<DefaultClientContext> = config
+            .create()
+            .map_err(|err| KafkaConfigError::Producer(err.to_string()))?;
+        let new_topic = NewTopic::new(
+            topic,
+            self.partitions.unwrap_or(1),
+            TopicReplication::Fixed(self.replication_factor.unwrap_or(1)),
+        );
+        let timeout = Duration::from_millis(self.socket_timeout_ms.unwrap_or(30_000).into());
+        let options = AdminOptions::new()
+            .operation_timeout(Some(timeout))
+            .request_timeout(Some(timeout));
+
+        let results = block_on_thread(admin.create_topics([&new_topic], &options))
+            .map_err(|err| KafkaConfigError::TopicCreation(err.to_string()))?
+            .map_err(|err| KafkaConfigError::TopicCreation(err.to_string()))?;
+        for result in results {
+            match result {
+                Ok(_) | Err((_, RDKafkaErrorCode::TopicAlreadyExists)) => {}
+                Err((name, code)) => {
+                    return Err(KafkaConfigError::TopicCreation(format!(
+                        "{}: {}",
+                        name, code
+                    )))
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl KafkaHook {
+    /// Build a hook producing to `topic` from configuration
+    ///
+    /// Creates the topic first when `create_topic` is set. Problems are
+    /// returned as [`LoggixError::Config`] wrapping a [`KafkaConfigError`].
+    pub fn from_config(config: &KafkaConfig, topic: &str) -> Result<Self, LoggixError> {
+        let bootstrap_servers = config.validate()?;
+        let properties = config.producer_properties(bootstrap_servers);
+        let hook = KafkaHook::builder(bootstrap_servers, topic)
+            .properties(properties.iter().map(|(k, v)| (k.as_str(), v.as_str())))
+            .build()
+            .map_err(|err| KafkaConfigError::Producer(err.to_string()))?;
+        if config.create_topic {
+            config.create_topic(&properties, topic)?;
+        }
+        Ok(hook)
+    }
+
+    /// Build a hook from the `kafka:` section of a YAML file, with
+    /// `LOGGIX_KAFKA_*` environment variables taking precedence
+    pub fn from_yaml_file<P: AsRef<Path>>(path: P, topic: &str) -> Result<Self, LoggixError> {
+        let mut config = KafkaConfig::from_yaml_file(path)?;
+        config.apply_env()?;
+        Self::from_config(&config, topic)
+    }
+}
@@ -5,8 +5,17 @@
 //! - Seven log levels: Trace, Debug, Info, Warning, Error, Fatal, and Panic
 //! - Structured logging with fields
 //! - Beautiful terminal output with colors
-//! - JSON formatter for machine processing
+//! - JSON formatter for machine processing with configurable key names and layout
+//! - Logfmt formatter for Loki/Heroku-style tooling
+//! - Elastic Common Schema (ECS) formatter for Elasticsearch and Kibana
+//! - Google Cloud Logging formatter for GKE and Cloud Run
 //! - Extensible hook system
+//! - OpenTelemetry (OTLP/HTTP) log export (`otlp` feature)
+//! - Grafana Loki push hook with label extraction (`loki` feature)
+//! - Elasticsearch/OpenSearch bulk indexing hook (`elasticsearch` feature)
+//! - Batching HTTP webhook hook with JSON, NDJSON or templated bodies (`http` feature)
+//! - Splunk HTTP Event Collector hook with indexer acknowledgements (`splunk` feature)
+//! - Fluentd / Fluent Bit Forward protocol hook over TCP or Unix sockets (`fluent` feature)
 //! - Thread-safe by default
 //! - Global and local logger instances
 //!
@@ -25,6 +34,11 @@
 //!         "size".to_string() => 10.to_string()
 //!     )
 //!     .info("A group of walrus emerges");
+//!
+//!     // Format arguments and inline fields
+//!     let name = "Walter";
+//!     info!("{} the walrus appears", name);
+//!     info!(animal = %name, size = 10; "A walrus emerges");
 //! }
 //! ```
 //!
@@ -112,18 +126,92 @@
 use chrono::{DateTime, Utc};
 use colored::Colorize;
 use lazy_static::lazy_static;
-use rdkafka::config::ClientConfig;
-use rdkafka::producer::{FutureProducer, FutureRecord};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::{
     collections::HashMap,
     fmt,
     io::{self, Write},
+    sync::atomic::{AtomicBool, AtomicUsize},
     sync::{Arc, Mutex},
-    time::Duration,
 };
 
+#[cfg(feature = "kafka")]
+mod avro;
+mod backoff;
+mod batch;
+mod batching;
+mod circuit_breaker;
+mod cloud_logging;
+mod ecs;
+#[cfg(feature = "elasticsearch")]
+mod elasticsearch;
+#[cfg(any(feature = "otlp", feature = "fluent", feature = "kafka"))]
+mod encoding;
+mod error;
+mod error_handler;
+#[cfg(feature = "fluent")]
+mod fluent;
+#[cfg(feature = "http")]
+mod http;
+#[cfg(feature = "kafka")]
+mod kafka;
+#[cfg(feature = "kafka")]
+mod kafka_config;
+#[cfg(feature = "kafka")]
+mod kafka_mock;
+mod logfmt;
+#[cfg(feature = "loki")]
+mod loki;
+mod ordered;
+#[cfg(feature = "otlp")]
+mod otlp;
+mod retry;
+mod shutdown;
+#[cfg(feature = "splunk")]
+mod splunk;
+mod spool;
+#[cfg(any(feature = "fluent", feature = "kafka"))]
+mod template;
+
+#[cfg(feature = "kafka")]
+pub use avro::{SchemaRegistry, ENTRY_SCHEMA};
+pub use backoff::Backoff;
+pub use batching::{BatchingHook, BatchingHookBuilder};
+pub use circuit_breaker::{CircuitBreakerHook, CircuitOpen, CircuitState};
+pub use cloud_logging::{cloud_logging_severity, CloudLoggingFormatter};
+pub use ecs::{ECSFormatter, ECS_VERSION};
+#[cfg(feature = "elasticsearch")]
+pub use elasticsearch::{ElasticsearchHook, ElasticsearchHookBuilder};
+pub use error::LoggixError;
+pub use error_handler::{error_count, handle_error, set_error_handler, ErrorHandler};
+#[cfg(feature = "fluent")]
+pub use fluent::{FluentAddress, FluentHook, FluentHookBuilder};
+#[cfg(feature = "http")]
+pub use http::{BodyTemplate, HttpBody, HttpHook, HttpHookBuilder};
+#[cfg(feature = "kafka")]
+pub use kafka::{
+    KafkaDeliveryReport, KafkaEncoding, KafkaHook, KafkaHookBuilder, KafkaMetrics, KafkaPartitioner,
+};
+#[cfg(feature = "kafka")]
+pub use kafka_config::{KafkaConfig, KafkaConfigError, KAFKA_ENV_PREFIX};
+#[cfg(feature = "kafka")]
+pub use kafka_mock::{KafkaMockCluster, KafkaMockConsumer, KafkaMockMessage};
+pub use logfmt::LogfmtFormatter;
+#[cfg(feature = "loki")]
+pub use loki::{LokiEncoding, LokiHook, LokiHookBuilder};
+#[cfg(feature = "otlp")]
+pub use otlp::{
+    any_value, severity_number, AnyValue, ArrayValue, ExportLogsServiceRequest,
+    InstrumentationScope, KeyValue, KeyValueList, LogRecord, OtlpEncoding, OtlpHook,
+    OtlpHookBuilder, Resource, ResourceLogs, ScopeLogs,
+};
+pub use retry::{PermanentError, RetryHook};
+pub use shutdown::{ShutdownError, ShutdownGuard};
+#[cfg(feature = "splunk")]
+pub use splunk::{SplunkHook, SplunkHookBuilder};
+pub use spool::{SpoolHook, SpoolHookBuilder};
+
 // Re-exports
 pub use chrono;
 pub use colored;
@@ -187,29 +275,159 @@ pub struct Entry<'a> {
     pub logger: &'a Logger,
 }
 
+/// An owned copy of a log entry, for hooks that keep entries beyond `fire`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub timestamp: DateTime<Utc>,
+    pub level: Level,
+    pub message: String,
+    pub fields: Fields,
+}
+
+impl Record {
+    /// Borrow the record as an entry of `logger`, e.g. to run a formatter
+    pub fn to_entry<'a>(&self, logger: &'a Logger) -> Entry<'a> {
+        Entry {
+            timestamp: self.timestamp,
+            level: self.level,
+            message: self.message.clone(),
+            fields: self.fields.clone(),
+            logger,
+        }
+    }
+}
+
+lazy_static! {
+    /// Owner of entries rebuilt from records where no logger is at hand
+    pub(crate) static ref RECORD_OWNER: Logger = Logger::new();
+}
+
+impl From<&Entry<'_>> for Record {
+    fn from(entry: &Entry<'_>) -> Self {
+        Self {
+            timestamp: entry.timestamp,
+            level: entry.level,
+            message: entry.message.clone(),
+            fields: entry.fields.clone(),
+        }
+    }
+}
+
+/// Summarize per-entry failures of a batch delivered by `H`
+fn batch_result<H: ?Sized>(
+    failed: usize,
+    total: usize,
+    first_error: Option<LoggixError>,
+) -> Result<(), LoggixError> {
+    match first_error {
+        Some(e) => {
+            let (name, cause) = match e {
+                LoggixError::Hook { name, source } => (name, source.to_string()),
+                other => (std::any::type_name::<H>().to_string(), other.to_string()),
+            };
+            let message = format!("{} of {} entries failed: {}", failed, total, cause);
+            Err(LoggixError::hook(name, message))
+        }
+        None => Ok(()),
+    }
+}
+
 /// Hook trait for implementing custom hooks
 pub trait Hook: Send + Sync {
     /// Get the levels this hook should fire for
     fn levels(&self) -> Vec<Level>;
 
     /// Fire the hook for a log entry
-    fn fire(&self, entry: &Entry) -> Result<(), Box<dyn std::error::Error>>;
+    fn fire(&self, entry: &Entry) -> Result<(), LoggixError>;
 
     /// Fire the hook asynchronously for a log entry
     #[allow(unused_variables)]
     fn fire_async<'a>(
         &'a self,
         entry: &'a Entry,
-    ) -> std::pin::Pin<
-        Box<dyn std::future::Future<Output = Result<(), Box<dyn std::error::Error>>> + Send + 'a>,
-    > {
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), LoggixError>> + Send + 'a>>
+    {
         Box::pin(async move { self.fire(entry) })
     }
+
+    /// Whether [`fire`](Hook::fire) only works from async code
+    ///
+    /// [`Logger::log`] then runs [`fire_async`](Hook::fire_async) to
+    /// completion on a shared runtime instead. Most hooks deliver or enqueue
+    /// synchronously and keep the default of `false`.
+    fn requires_async(&self) -> bool {
+        false
+    }
+
+    /// Deliver several entries at once, e.g. in a single request
+    ///
+    /// Called by [`BatchingHook`]. The default fires each entry in turn,
+    /// carrying on past failures, and reports how many failed.
+    fn fire_batch(&self, records: &[Record]) -> Result<(), LoggixError> {
+        let mut failed = 0;
+        let mut first_error = None;
+        for record in records {
+            if let Err(e) = self.fire(&record.to_entry(&RECORD_OWNER)) {
+                failed += 1;
+                first_error.get_or_insert(e);
+            }
+        }
+        batch_result::<Self>(failed, records.len(), first_error)
+    }
+
+    /// Deliver several entries at once asynchronously
+    ///
+    /// The default calls [`fire_batch`](Hook::fire_batch), or awaits
+    /// [`fire_async`](Hook::fire_async) for each entry when
+    /// [`requires_async`](Hook::requires_async) is set.
+    fn fire_batch_async<'a>(
+        &'a self,
+        records: &'a [Record],
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), LoggixError>> + Send + 'a>>
+    {
+        Box::pin(async move {
+            if !self.requires_async() {
+                return self.fire_batch(records);
+            }
+            let mut failed = 0;
+            let mut first_error = None;
+            for record in records {
+                let entry = record.to_entry(&RECORD_OWNER);
+                if let Err(e) = self.fire_async(&entry).await {
+                    failed += 1;
+                    first_error.get_or_insert(e);
+                }
+            }
+            batch_result::<Self>(failed, records.len(), first_error)
+        })
+    }
+
+    /// Wait up to `timeout` for entries the hook has accepted but not yet
+    /// delivered
+    ///
+    /// Called by [`Logger::flush`] and when a logger is dropped. Hooks that
+    /// deliver inline have nothing to wait for.
+    #[allow(unused_variables)]
+    fn flush(&self, timeout: std::time::Duration) -> Result<(), LoggixError> {
+        Ok(())
+    }
+
+    /// Wait up to `timeout` for queued entries asynchronously
+    ///
+    /// Called by [`Logger::shutdown_async`]. The default calls
+    /// [`flush`](Hook::flush).
+    fn flush_async(
+        &self,
+        timeout: std::time::Duration,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), LoggixError>> + Send + '_>>
+    {
+        Box::pin(async move { self.flush(timeout) })
+    }
 }
 
 /// Formatter trait for implementing custom formatters
 pub trait Formatter: Send + Sync {
-    fn format(&self, entry: &Entry) -> Result<Vec<u8>, Box<dyn std::error::Error>>;
+    fn format(&self, entry: &Entry) -> Result<Vec<u8>, LoggixError>;
 }
 
 /// Text formatter with optional colors
@@ -256,7 +474,7 @@ impl TextFormatter {
 }
 
 impl Formatter for TextFormatter {
-    fn format(&self, entry: &Entry) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    fn format(&self, entry: &Entry) -> Result<Vec<u8>, LoggixError> {
         let mut output = Vec::new();
 
         // Format timestamp
@@ -297,118 +515,175 @@ impl Formatter for TextFormatter {
     }
 }
 
+/// How `JSONFormatter` encodes the entry timestamp
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampFormat {
+    /// RFC 3339 string with the given sub-second precision, always in UTC (`Z`)
+    Rfc3339(chrono::SecondsFormat),
+    /// Seconds since the Unix epoch as a number
+    UnixSeconds,
+    /// Milliseconds since the Unix epoch as a number
+    UnixMillis,
+    /// Nanoseconds since the Unix epoch as a number
+    UnixNanos,
+}
+
+impl Default for TimestampFormat {
+    fn default() -> Self {
+        TimestampFormat::Rfc3339(chrono::SecondsFormat::AutoSi)
+    }
+}
+
+impl TimestampFormat {
+    fn encode(&self, timestamp: &DateTime<Utc>) -> Result<Value, LoggixError> {
+        Ok(match self {
+            TimestampFormat::Rfc3339(precision) => {
+                Value::String(timestamp.to_rfc3339_opts(*precision, true))
+            }
+            TimestampFormat::UnixSeconds => Value::from(timestamp.timestamp()),
+            TimestampFormat::UnixMillis => Value::from(timestamp.timestamp_millis()),
+            TimestampFormat::UnixNanos => {
+                Value::from(timestamp.timestamp_nanos_opt().ok_or_else(|| {
+                    LoggixError::format("timestamp out of range for nanosecond precision")
+                })?)
+            }
+        })
+    }
+}
+
 /// JSON formatter for machine-readable output
+///
+/// By default an entry is written as
+/// `{"timestamp":...,"level":...,"message":...,"fields":{...}}`. The key
+/// names, the timestamp encoding and whether fields are nested under a data
+/// key or flattened to the top level can all be changed to suit the log
+/// backend. Flattened fields that clash with the timestamp, level or message
+/// keys are prefixed with `fields.`.
 #[derive(Debug, Clone)]
 pub struct JSONFormatter {
     pretty: bool,
+    timestamp_key: String,
+    level_key: String,
+    message_key: String,
+    data_key: String,
+    flatten_fields: bool,
+    timestamp_format: TimestampFormat,
+    disable_timestamp: bool,
 }
 
 impl JSONFormatter {
     pub fn new() -> Self {
-        Self { pretty: false }
+        Self::default()
     }
 
     pub fn pretty(mut self, enabled: bool) -> Self {
         self.pretty = enabled;
         self
     }
-}
 
-impl Default for JSONFormatter {
-    fn default() -> Self {
-        Self { pretty: false }
+    /// Rename the timestamp key (e.g. `@timestamp`)
+    pub fn timestamp_key(mut self, key: &str) -> Self {
+        self.timestamp_key = key.to_string();
+        self
     }
-}
 
-impl Formatter for JSONFormatter {
-    fn format(&self, entry: &Entry) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-        let mut output = Vec::new();
-        if self.pretty {
-            serde_json::to_writer_pretty(&mut output, &entry)?;
-        } else {
-            serde_json::to_writer(&mut output, &entry)?;
-        }
-        output.extend_from_slice(b"\n");
-        Ok(output)
+    /// Rename the level key (e.g. `severity`)
+    pub fn level_key(mut self, key: &str) -> Self {
+        self.level_key = key.to_string();
+        self
     }
-}
 
-/// A hook that sends log entries to Kafka
-pub struct KafkaHook {
-    producer: FutureProducer,
-    topic: String,
-    key_field: Option<String>,
-}
-
-impl KafkaHook {
-    /// Create a new KafkaHook
-    pub fn new(bootstrap_servers: &str, topic: String) -> Result<Self, Box<dyn std::error::Error>> {
-        let producer: FutureProducer = ClientConfig::new()
-            .set("bootstrap.servers", bootstrap_servers)
-            .create()?;
-
-        Ok(KafkaHook {
-            producer,
-            topic,
-            key_field: None,
-        })
+    /// Rename the message key (e.g. `msg`)
+    pub fn message_key(mut self, key: &str) -> Self {
+        self.message_key = key.to_string();
+        self
     }
 
-    /// Set the field name to use as the Kafka message key
-    pub fn with_key_field(mut self, key_field: String) -> Self {
-        self.key_field = Some(key_field);
+    /// Set the key fields are nested under
+    pub fn data_key(mut self, key: &str) -> Self {
+        self.data_key = key.to_string();
         self
     }
 
-    fn get_key_from_fields(&self, fields: &Fields) -> Option<String> {
-        self.key_field.as_ref().and_then(|key_field| {
-            fields
-                .get(key_field)
-                .and_then(|value| value.as_str().map(|s| s.to_string()))
-        })
+    /// Write fields at the top level instead of under the data key
+    pub fn flatten_fields(mut self, enabled: bool) -> Self {
+        self.flatten_fields = enabled;
+        self
     }
-}
 
-impl Hook for KafkaHook {
-    fn levels(&self) -> Vec<Level> {
-        vec![
-            Level::Trace,
-            Level::Debug,
-            Level::Info,
-            Level::Warn,
-            Level::Error,
-            Level::Fatal,
-            Level::Panic,
-        ]
+    /// Choose how the timestamp is encoded
+    pub fn timestamp_format(mut self, format: TimestampFormat) -> Self {
+        self.timestamp_format = format;
+        self
     }
 
-    fn fire(&self, _entry: &Entry) -> Result<(), Box<dyn std::error::Error>> {
-        // For sync contexts, we'll return an error suggesting to use fire_async
-        Err("KafkaHook requires an async runtime. Please use fire_async or ensure you're in an async context.".into())
+    /// Omit the timestamp entirely
+    pub fn disable_timestamp(mut self, disabled: bool) -> Self {
+        self.disable_timestamp = disabled;
+        self
     }
+}
 
-    fn fire_async<'a>(
-        &'a self,
-        entry: &'a Entry,
-    ) -> std::pin::Pin<
-        Box<dyn std::future::Future<Output = Result<(), Box<dyn std::error::Error>>> + Send + 'a>,
-    > {
-        Box::pin(async move {
-            let payload = serde_json::to_string(&entry)?;
-            let key = self.get_key_from_fields(&entry.fields);
+impl Default for JSONFormatter {
+    fn default() -> Self {
+        Self {
+            pretty: false,
+            timestamp_key: "timestamp".to_string(),
+            level_key: "level".to_string(),
+            message_key: "message".to_string(),
+            data_key: "fields".to_string(),
+            flatten_fields: false,
+            timestamp_format: TimestampFormat::default(),
+            disable_timestamp: false,
+        }
+    }
+}
 
-            let mut record = FutureRecord::to(&self.topic).payload(payload.as_bytes());
+impl Formatter for JSONFormatter {
+    fn format(&self, entry: &Entry) -> Result<Vec<u8>, LoggixError> {
+        let mut document = ordered::OrderedObject::new();
+        if !self.disable_timestamp {
+            document.insert(
+                self.timestamp_key.as_str(),
+                self.timestamp_format.encode(&entry.timestamp)?,
+            );
+        }
+        document.insert(self.level_key.as_str(), serde_json::to_value(entry.level)?);
+        document.insert(
+            self.message_key.as_str(),
+            Value::String(entry.message.clone()),
+        );
 
-            if let Some(ref key) = key {
-                record = record.key(key);
+        let mut fields: Vec<(&String, &Value)> = entry.fields.iter().collect();
+        fields.sort_by(|a, b| a.0.cmp(b.0));
+
+        if self.flatten_fields {
+            for (key, value) in fields {
+                let clashes = (!self.disable_timestamp && *key == self.timestamp_key)
+                    || *key == self.level_key
+                    || *key == self.message_key;
+                if clashes {
+                    document.insert(format!("fields.{}", key), value.clone());
+                } else {
+                    document.insert(key.as_str(), value.clone());
+                }
             }
+        } else {
+            let data: serde_json::Map<String, Value> = fields
+                .into_iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect();
+            document.insert(self.data_key.as_str(), Value::Object(data));
+        }
 
-            self.producer
-                .send(record, Duration::from_secs(0))
-                .await
-                .map_err(|(err, _)| err)?;
-            Ok(())
-        })
+        let mut output = Vec::new();
+        if self.pretty {
+            serde_json::to_writer_pretty(&mut output, &document)?;
+        } else {
+            serde_json::to_writer(&mut output, &document)?;
+        }
+        output.extend_from_slice(b"\n");
+        Ok(output)
     }
 }
 
@@ -418,6 +693,8 @@ pub struct Logger {
     formatter: Box<dyn Formatter>,
     hooks: Vec<Box<dyn Hook>>,
     output: Arc<Mutex<Box<dyn Write + Send>>>,
+    closed: AtomicBool,
+    in_flight: AtomicUsize,
 }
 
 impl fmt::Debug for Logger {
@@ -436,6 +713,22 @@ impl Clone for Logger {
             formatter: Box::new(TextFormatter::default()),
             hooks: Vec::new(),
             output: Arc::clone(&self.output),
+            closed: AtomicBool::new(false),
+            in_flight: AtomicUsize::new(0),
+        }
+    }
+}
+
+/// How long a dropped logger waits for its hooks to deliver queued entries
+const DROP_FLUSH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+impl Drop for Logger {
+    fn drop(&mut self) {
+        if self.hooks.is_empty() || self.is_shut_down() {
+            return;
+        }
+        if let Err(e) = self.shutdown(DROP_FLUSH_TIMEOUT) {
+            handle_error(e);
         }
     }
 }
@@ -447,6 +740,8 @@ impl Logger {
             formatter: Box::new(TextFormatter::default()),
             hooks: Vec::new(),
             output: Arc::new(Mutex::new(Box::new(io::stdout()))),
+            closed: AtomicBool::new(false),
+            in_flight: AtomicUsize::new(0),
         }
     }
 
@@ -474,16 +769,29 @@ impl Logger {
         Arc::new(self)
     }
 
+    /// Flush the output, then give every hook until `timeout` to deliver
+    /// what it has queued
+    ///
+    /// All hooks are flushed even if one fails. Unlike
+    /// [`shutdown`](Self::shutdown), the logger keeps accepting entries.
+    pub fn flush(&self, timeout: std::time::Duration) -> Result<(), LoggixError> {
+        let mut report = self.drain(std::time::Instant::now() + timeout);
+        // Entries logged concurrently are not waited for
+        report.in_flight = 0;
+        report.into_result()
+    }
+
     /// Log a message with the given level and fields
     pub async fn log_async(
         &self,
         level: Level,
         msg: &str,
         fields: Fields,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    ) -> Result<(), LoggixError> {
         if level < self.level {
             return Ok(());
         }
+        let _in_flight = self.admit()?;
 
         let entry = Entry {
             message: msg.to_string(),
@@ -496,16 +804,19 @@ impl Logger {
         // Format and write the log entry
         let formatted = self.formatter.format(&entry)?;
         {
-            let mut output = self.output.lock().unwrap();
+            let mut output = self
+                .output
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
             output.write_all(&formatted)?;
             output.flush()?;
         }
 
-        // Fire hooks
+        // Fire hooks; failures go to the error handler, not the caller
         for hook in &self.hooks {
             if hook.levels().contains(&level) {
                 if let Err(e) = hook.fire_async(&entry).await {
-                    eprintln!("Hook failed: {}", e);
+                    handle_error(e);
                 }
             }
         }
@@ -514,15 +825,11 @@ impl Logger {
     }
 
     /// Log a message with the given level and fields
-    pub fn log(
-        &self,
-        level: Level,
-        msg: &str,
-        fields: Fields,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn log(&self, level: Level, msg: &str, fields: Fields) -> Result<(), LoggixError> {
         if level < self.level {
             return Ok(());
         }
+        let _in_flight = self.admit()?;
 
         let entry = Entry {
             message: msg.to_string(),
@@ -535,23 +842,26 @@ impl Logger {
         // Format and write the log entry
         let formatted = self.formatter.format(&entry)?;
         {
-            let mut output = self.output.lock().unwrap();
+            let mut output = self
+                .output
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
             output.write_all(&formatted)?;
             output.flush()?;
         }
 
-        // Fire hooks
+        // Fire hooks; failures go to the error handler, not the caller
         for hook in &self.hooks {
             if hook.levels().contains(&level) {
-                // Try fire_async first, fall back to fire if it fails
-                if let Ok(rt) = tokio::runtime::Runtime::new() {
-                    rt.block_on(async {
-                        if let Err(e) = hook.fire_async(&entry).await {
-                            eprintln!("Hook failed: {}", e);
-                        }
-                    });
-                } else if let Err(e) = hook.fire(&entry) {
-                    eprintln!("Hook failed: {}", e);
+                let result = if hook.requires_async() {
+                    batch::block_on_shared(hook.fire_async(&entry))
+                        .map_err(|e| LoggixError::hook("Logger", e))
+                        .and_then(|result| result)
+                } else {
+                    hook.fire(&entry)
+                };
+                if let Err(e) = result {
+                    handle_error(e);
                 }
             }
         }
@@ -616,31 +926,31 @@ impl<'a> EntryBuilder<'a> {
         self
     }
 
-    pub fn trace<M: Into<String>>(self, msg: M) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn trace<M: Into<String>>(self, msg: M) -> Result<(), LoggixError> {
         self.logger.log(Level::Trace, &msg.into(), self.fields)
     }
 
-    pub fn debug<M: Into<String>>(self, msg: M) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn debug<M: Into<String>>(self, msg: M) -> Result<(), LoggixError> {
         self.logger.log(Level::Debug, &msg.into(), self.fields)
     }
 
-    pub fn info<M: Into<String>>(self, msg: M) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn info<M: Into<String>>(self, msg: M) -> Result<(), LoggixError> {
         self.logger.log(Level::Info, &msg.into(), self.fields)
     }
 
-    pub fn warn<M: Into<String>>(self, msg: M) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn warn<M: Into<String>>(self, msg: M) -> Result<(), LoggixError> {
         self.logger.log(Level::Warn, &msg.into(), self.fields)
     }
 
-    pub fn error<M: Into<String>>(self, msg: M) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn error<M: Into<String>>(self, msg: M) -> Result<(), LoggixError> {
         self.logger.log(Level::Error, &msg.into(), self.fields)
     }
 
-    pub fn fatal<M: Into<String>>(self, msg: M) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn fatal<M: Into<String>>(self, msg: M) -> Result<(), LoggixError> {
         self.logger.log(Level::Fatal, &msg.into(), self.fields)
     }
 
-    pub fn panic<M: Into<String>>(self, msg: M) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn panic<M: Into<String>>(self, msg: M) -> Result<(), LoggixError> {
         self.logger.log(Level::Panic, &msg.into(), self.fields)
     }
 }
@@ -686,66 +996,173 @@ macro_rules! with_fields {
     }};
 }
 
+// The level macros all accept
+//
+//     info!([logger: <logger>,] [target: <target>,] [<key> = <value>, ...;] <message>)
+//
+// where the message is a format string with arguments or any expression
+// convertible to a `String`, and a field value is serialized as is, or
+// captured with `Display` (`%value`) or `Debug` (`?value`). They pass
+// logging errors to `handle_error` instead of panicking; the `try_`
+// variants return them.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __log {
+    // Which logger
+    ($level:ident; logger: $logger:expr, $($rest:tt)+) => {
+        $crate::__log!(@target $level, ($logger).with_fields($crate::Fields::new()); $($rest)+)
+    };
+    ($level:ident; $($rest:tt)+) => {
+        $crate::__log!(@target $level, $crate::with_fields($crate::Fields::new()); $($rest)+)
+    };
+
+    (@target $level:ident, $builder:expr; target: $target:expr, $($rest:tt)+) => {
+        $crate::__log!(@fields $level, $builder.with_field("target", $target); $($rest)+)
+    };
+    (@target $level:ident, $builder:expr; $($rest:tt)+) => {
+        $crate::__log!(@fields $level, $builder; $($rest)+)
+    };
+
+    // Fields, up to the `;` before the message
+    (@fields $level:ident, $builder:expr; $($key:ident).+ = %$value:expr, $($rest:tt)+) => {
+        $crate::__log!(@fields $level, $builder.with_field(stringify!($($key).+), ::std::format!("{}", $value)); $($rest)+)
+    };
+    (@fields $level:ident, $builder:expr; $($key:ident).+ = %$value:expr; $($rest:tt)+) => {
+        $crate::__log!(@message $level, $builder.with_field(stringify!($($key).+), ::std::format!("{}", $value)); $($rest)+)
+    };
+    (@fields $level:ident, $builder:expr; $($key:ident).+ = ?$value:expr, $($rest:tt)+) => {
+        $crate::__log!(@fields $level, $builder.with_field(stringify!($($key).+), ::std::format!("{:?}", $value)); $($rest)+)
+    };
+    (@fields $level:ident, $builder:expr; $($key:ident).+ = ?$value:expr; $($rest:tt)+) => {
+        $crate::__log!(@message $level, $builder.with_field(stringify!($($key).+), ::std::format!("{:?}", $value)); $($rest)+)
+    };
+    (@fields $level:ident, $builder:expr; $($key:ident).+ = $value:expr, $($rest:tt)+) => {
+        $crate::__log!(@fields $level, $builder.with_field(stringify!($($key).+), &$value); $($rest)+)
+    };
+    (@fields $level:ident, $builder:expr; $($key:ident).+ = $value:expr; $($rest:tt)+) => {
+        $crate::__log!(@message $level, $builder.with_field(stringify!($($key).+), &$value); $($rest)+)
+    };
+    (@fields $level:ident, $builder:expr; $($rest:tt)+) => {
+        $crate::__log!(@message $level, $builder; $($rest)+)
+    };
+
+    (@message $level:ident, $builder:expr; $format:literal $(, $arg:expr)* $(,)?) => {
+        $builder.$level(::std::format!($format $(, $arg)*))
+    };
+    (@message $level:ident, $builder:expr; $message:expr $(,)?) => {
+        $builder.$level($message)
+    };
+}
+
 #[macro_export]
 macro_rules! trace {
-    ($msg:expr) => {
-        $crate::with_fields!()
-            .trace($msg)
-            .expect("Failed to log trace message")
+    ($($arg:tt)+) => {
+        if let Err(err) = $crate::__log!(trace; $($arg)+) {
+            $crate::handle_error(err)
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! try_trace {
+    ($($arg:tt)+) => {
+        $crate::__log!(trace; $($arg)+)
     };
 }
 
 #[macro_export]
 macro_rules! debug {
-    ($msg:expr) => {
-        $crate::with_fields!()
-            .debug($msg)
-            .expect("Failed to log debug message")
+    ($($arg:tt)+) => {
+        if let Err(err) = $crate::__log!(debug; $($arg)+) {
+            $crate::handle_error(err)
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! try_debug {
+    ($($arg:tt)+) => {
+        $crate::__log!(debug; $($arg)+)
     };
 }
 
 #[macro_export]
 macro_rules! info {
-    ($msg:expr) => {
-        $crate::with_fields!()
-            .info($msg)
-            .expect("Failed to log info message")
+    ($($arg:tt)+) => {
+        if let Err(err) = $crate::__log!(info; $($arg)+) {
+            $crate::handle_error(err)
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! try_info {
+    ($($arg:tt)+) => {
+        $crate::__log!(info; $($arg)+)
     };
 }
 
 #[macro_export]
 macro_rules! warn {
-    ($msg:expr) => {
-        $crate::with_fields!()
-            .warn($msg)
-            .expect("Failed to log warning message")
+    ($($arg:tt)+) => {
+        if let Err(err) = $crate::__log!(warn; $($arg)+) {
+            $crate::handle_error(err)
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! try_warn {
+    ($($arg:tt)+) => {
+        $crate::__log!(warn; $($arg)+)
     };
 }
 
 #[macro_export]
 macro_rules! error {
-    ($msg:expr) => {
-        $crate::with_fields!()
-            .error($msg)
-            .expect("Failed to log error message")
+    ($($arg:tt)+) => {
+        if let Err(err) = $crate::__log!(error; $($arg)+) {
+            $crate::handle_error(err)
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! try_error {
+    ($($arg:tt)+) => {
+        $crate::__log!(error; $($arg)+)
     };
 }
 
 #[macro_export]
 macro_rules! fatal {
-    ($msg:expr) => {
-        $crate::with_fields!()
-            .fatal($msg)
-            .expect("Failed to log fatal message")
+    ($($arg:tt)+) => {
+        if let Err(err) = $crate::__log!(fatal; $($arg)+) {
+            $crate::handle_error(err)
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! try_fatal {
+    ($($arg:tt)+) => {
+        $crate::__log!(fatal; $($arg)+)
     };
 }
 
 #[macro_export]
 macro_rules! panic {
-    ($msg:expr) => {
-        $crate::with_fields!()
-            .panic($msg)
-            .expect("Failed to log panic message")
+    ($($arg:tt)+) => {
+        if let Err(err) = $crate::__log!(panic; $($arg)+) {
+            $crate::handle_error(err)
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! try_panic {
+    ($($arg:tt)+) => {
+        $crate::__log!(panic; $($arg)+)
     };
 }
 
@@ -810,7 +1227,7 @@ mod test {
             vec![Level::Info]
         }
 
-        fn fire(&self, _entry: &Entry) -> Result<(), Box<dyn std::error::Error>> {
+        fn fire(&self, _entry: &Entry) -> Result<(), LoggixError> {
             *self.called.lock().unwrap() = true;
             Ok(())
         }
@@ -818,13 +1235,8 @@ mod test {
         fn fire_async<'a>(
             &'a self,
             entry: &'a Entry,
-        ) -> std::pin::Pin<
-            Box<
-                dyn std::future::Future<Output = Result<(), Box<dyn std::error::Error>>>
-                    + Send
-                    + 'a,
-            >,
-        > {
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), LoggixError>> + Send + 'a>>
+        {
             Box::pin(async move { self.fire(entry) })
         }
     }
@@ -878,6 +1290,116 @@ mod test {
         assert!(*called.lock().unwrap());
     }
 
+    /// A hook that must not be fired from sync code
+    struct AsyncOnlyHook {
+        called: Arc<Mutex<bool>>,
+    }
+
+    impl Hook for AsyncOnlyHook {
+        fn levels(&self) -> Vec<Level> {
+            vec![Level::Info]
+        }
+
+        fn requires_async(&self) -> bool {
+            true
+        }
+
+        fn fire(&self, _entry: &Entry) -> Result<(), LoggixError> {
+            Err(LoggixError::hook("AsyncOnlyHook", "use fire_async"))
+        }
+
+        fn fire_async<'a>(
+            &'a self,
+            _entry: &'a Entry,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), LoggixError>> + Send + 'a>>
+        {
+            Box::pin(async move {
+                // Needs the timer of whichever runtime drives the hook
+                tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+                *self.called.lock().unwrap() = true;
+                Ok(())
+            })
+        }
+    }
+
+    /// A hook whose async path must not be used by sync logging
+    struct SyncOnlyHook {
+        called: Arc<Mutex<bool>>,
+    }
+
+    impl Hook for SyncOnlyHook {
+        fn levels(&self) -> Vec<Level> {
+            vec![Level::Info]
+        }
+
+        fn fire(&self, _entry: &Entry) -> Result<(), LoggixError> {
+            *self.called.lock().unwrap() = true;
+            Ok(())
+        }
+
+        fn fire_async<'a>(
+            &'a self,
+            _entry: &'a Entry,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), LoggixError>> + Send + 'a>>
+        {
+            unreachable!("sync logging must call fire")
+        }
+    }
+
+    #[test]
+    fn test_sync_log_calls_fire() {
+        let called = Arc::new(Mutex::new(false));
+        let logger = Logger::new()
+            .output(io::sink())
+            .add_hook(SyncOnlyHook {
+                called: Arc::clone(&called),
+            })
+            .build();
+
+        logger.log(Level::Info, "direct", Fields::new()).unwrap();
+        assert!(*called.lock().unwrap());
+    }
+
+    #[test]
+    fn test_sync_log_drives_async_only_hooks() {
+        let called = Arc::new(Mutex::new(false));
+        let logger = Logger::new()
+            .output(io::sink())
+            .add_hook(AsyncOnlyHook {
+                called: Arc::clone(&called),
+            })
+            .build();
+
+        logger
+            .log(Level::Info, "no runtime", Fields::new())
+            .unwrap();
+        assert!(*called.lock().unwrap());
+
+        // Also from inside a runtime, where a nested block_on would panic
+        *called.lock().unwrap() = false;
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            logger
+                .log(Level::Info, "in a runtime", Fields::new())
+                .unwrap();
+        });
+        assert!(*called.lock().unwrap());
+
+        *called.lock().unwrap() = false;
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            logger
+                .log(Level::Info, "in a multi-threaded runtime", Fields::new())
+                .unwrap();
+        });
+        assert!(*called.lock().unwrap());
+    }
+
     #[test]
     fn test_with_error_and_time() {
         let (logger, writer) = create_test_logger();
@@ -0,0 +1,152 @@
+//! Logfmt formatter producing `key=value` lines understood by Loki, Heroku and
+//! other logfmt-aware tooling.
+
+use crate::{Entry, Formatter, LoggixError};
+use serde_json::Value;
+use std::io::Write;
+
+const TIME_KEY: &str = "time";
+const LEVEL_KEY: &str = "level";
+const MESSAGE_KEY: &str = "msg";
+
+/// Logfmt formatter
+///
+/// Produces lines such as
+/// `time=2025-04-06T10:00:00.000Z level=info msg="user logged in" user.id=42`.
+/// Fields are written in key order, nested objects and arrays are flattened
+/// into dotted keys, and values are quoted whenever they contain spaces,
+/// quotes, `=` or control characters. Fields that clash with `time`, `level`
+/// or `msg` are prefixed with `fields.`.
+#[derive(Debug, Clone)]
+pub struct LogfmtFormatter {
+    timestamp_format: String,
+    disable_timestamp: bool,
+}
+
+impl Default for LogfmtFormatter {
+    fn default() -> Self {
+        Self {
+            timestamp_format: "%Y-%m-%dT%H:%M:%S%.3fZ".to_string(),
+            disable_timestamp: false,
+        }
+    }
+}
+
+impl LogfmtFormatter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the chrono format string used for the `time` key
+    pub fn timestamp_format(mut self, format: &str) -> Self {
+        self.timestamp_format = format.to_string();
+        self
+    }
+
+    /// Omit the `time` key entirely
+    pub fn disable_timestamp(mut self, disabled: bool) -> Self {
+        self.disable_timestamp = disabled;
+        self
+    }
+}
+
+impl Formatter for LogfmtFormatter {
+    fn format(&self, entry: &Entry) -> Result<Vec<u8>, LoggixError> {
+        let mut pairs = Vec::new();
+
+        if !self.disable_timestamp {
+            pairs.push((
+                TIME_KEY.to_string(),
+                entry.timestamp.format(&self.timestamp_format).to_string(),
+            ));
+        }
+        pairs.push((
+            LEVEL_KEY.to_string(),
+            entry.level.to_string().to_lowercase(),
+        ));
+        pairs.push((MESSAGE_KEY.to_string(), entry.message.clone()));
+
+        let mut fields: Vec<(&String, &Value)> = entry.fields.iter().collect();
+        fields.sort_by(|a, b| a.0.cmp(b.0));
+        for (key, value) in fields {
+            let key = sanitize_key(key);
+            let key = if key == TIME_KEY || key == LEVEL_KEY || key == MESSAGE_KEY {
+                format!("fields.{}", key)
+            } else {
+                key
+            };
+            flatten(&key, value, &mut pairs);
+        }
+
+        let mut output = Vec::new();
+        for (i, (key, value)) in pairs.iter().enumerate() {
+            if i > 0 {
+                output.push(b' ');
+            }
+            write!(output, "{}={}", key, quote(value))?;
+        }
+        output.push(b'\n');
+        Ok(output)
+    }
+}
+
+/// Flatten a JSON value into logfmt pairs, joining nested keys with dots
+fn flatten(prefix: &str, value: &Value, pairs: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, value) in map {
+                flatten(&format!("{}.{}", prefix, sanitize_key(key)), value, pairs);
+            }
+        }
+        Value::Array(items) if !items.is_empty() => {
+            for (i, value) in items.iter().enumerate() {
+                flatten(&format!("{}.{}", prefix, i), value, pairs);
+            }
+        }
+        Value::String(s) => pairs.push((prefix.to_string(), s.clone())),
+        other => pairs.push((prefix.to_string(), other.to_string())),
+    }
+}
+
+/// Replace characters that are not allowed in logfmt keys
+fn sanitize_key(key: &str) -> String {
+    if key.is_empty() {
+        return "_".to_string();
+    }
+    key.chars()
+        .map(|c| {
+            if c == '=' || c == '"' || c.is_whitespace() || c.is_control() {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// Quote and escape a value if it cannot be written bare
+fn quote(value: &str) -> String {
+    let needs_quotes = value.is_empty()
+        || value
+            .chars()
+            .any(|c| c.is_whitespace() || c == '=' || c == '"' || c == '\\' || c.is_control());
+    if !needs_quotes {
+        return value.to_string();
+    }
+
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
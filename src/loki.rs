@@ -0,0 +1,477 @@
+//! Grafana Loki push hook.
+//!
+//! Entries are grouped into streams by their labels and pushed to Loki's
+//! `/loki/api/v1/push` endpoint in batches. Labels come from a fixed set of
+//! static labels, the entry level and an explicit list of fields, so label
+//! cardinality stays under the caller's control; every other field is
+//! rendered into the log line by a [`Formatter`].
+
+use crate::backoff::{send_http, Backoff};
+use crate::batch::{BatchConfig, BatchWorker, BoxError, Export, ExportFuture};
+use crate::{Entry, Formatter, Hook, Level, LogfmtFormatter, LoggixError};
+use flate2::write::GzEncoder;
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+const PUSH_PATH: &str = "/loki/api/v1/push";
+
+/// Payload encoding for Loki pushes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LokiEncoding {
+    /// Snappy-compressed protobuf, Loki's native push format
+    #[default]
+    Protobuf,
+    /// JSON, optionally gzip-compressed
+    Json,
+}
+
+/// Builder for [`LokiHook`]
+pub struct LokiHookBuilder {
+    url: String,
+    static_labels: BTreeMap<String, String>,
+    label_fields: Vec<String>,
+    formatter: Box<dyn Formatter>,
+    encoding: LokiEncoding,
+    gzip: bool,
+    tenant_id: Option<String>,
+    headers: Vec<(String, String)>,
+    backoff: Backoff,
+    max_streams: usize,
+    batch: BatchConfig,
+    timeout: Duration,
+}
+
+impl LokiHookBuilder {
+    /// Add a label attached to every stream, e.g. `app` or `env`
+    pub fn label(mut self, name: &str, value: &str) -> Self {
+        self.static_labels
+            .insert(sanitize_label_name(name), value.to_string());
+        self
+    }
+
+    /// Turn a field into a label instead of writing it into the log line
+    pub fn label_field(mut self, field: &str) -> Self {
+        self.label_fields.push(field.to_string());
+        self
+    }
+
+    /// Formatter used for the log line (`LogfmtFormatter` by default)
+    pub fn formatter<F: Formatter + 'static>(mut self, formatter: F) -> Self {
+        self.formatter = Box::new(formatter);
+        self
+    }
+
+    /// Choose snappy protobuf (default) or JSON payloads
+    pub fn encoding(mut self, encoding: LokiEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Gzip JSON payloads; protobuf payloads are always snappy-compressed
+    pub fn gzip(mut self, enabled: bool) -> Self {
+        self.gzip = enabled;
+        self
+    }
+
+    /// Send `X-Scope-OrgID` for multi-tenant Loki
+    pub fn tenant_id(mut self, tenant: &str) -> Self {
+        self.tenant_id = Some(tenant.to_string());
+        self
+    }
+
+    /// Add an HTTP header such as `Authorization`
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Retry policy for failed pushes
+    pub fn retry(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Maximum number of distinct label sets (1000 by default)
+    ///
+    /// Once reached, entries with a new combination of field labels are sent
+    /// with only the static and level labels, and the label fields stay in
+    /// the log line.
+    pub fn max_streams(mut self, max: usize) -> Self {
+        self.max_streams = max;
+        self
+    }
+
+    /// Maximum number of entries per push (512 by default)
+    pub fn batch_size(mut self, size: usize) -> Self {
+        self.batch.max_items = size;
+        self
+    }
+
+    /// Maximum time an entry waits before its batch is pushed (1s by default)
+    pub fn batch_timeout(mut self, timeout: Duration) -> Self {
+        self.batch.max_latency = timeout;
+        self
+    }
+
+    /// Timeout for a single push request (10s by default)
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Start the push worker and create the hook
+    pub fn build(self) -> Result<LokiHook, LoggixError> {
+        let client = reqwest::Client::builder()
+            .timeout(self.timeout)
+            .build()
+            .map_err(LoggixError::config)?;
+        let mut headers = reqwest::header::HeaderMap::new();
+        for (name, value) in &self.headers {
+            headers.insert(
+                reqwest::header::HeaderName::from_bytes(name.as_bytes())
+                    .map_err(LoggixError::config)?,
+                reqwest::header::HeaderValue::from_str(value).map_err(LoggixError::config)?,
+            );
+        }
+        if let Some(tenant) = &self.tenant_id {
+            headers.insert(
+                "X-Scope-OrgID",
+                reqwest::header::HeaderValue::from_str(tenant).map_err(LoggixError::config)?,
+            );
+        }
+
+        let url = if self.url.ends_with(PUSH_PATH) {
+            self.url
+        } else {
+            format!("{}{}", self.url.trim_end_matches('/'), PUSH_PATH)
+        };
+        let pusher = LokiPusher {
+            client,
+            url,
+            headers,
+            encoding: self.encoding,
+            gzip: self.gzip,
+            backoff: self.backoff,
+        };
+        let worker = BatchWorker::spawn("LokiHook", self.batch, pusher)?;
+
+        Ok(LokiHook {
+            static_labels: self.static_labels,
+            label_fields: self.label_fields,
+            formatter: self.formatter,
+            max_streams: self.max_streams,
+            streams: Mutex::new(HashSet::new()),
+            worker,
+        })
+    }
+}
+
+/// A hook that pushes log entries to Grafana Loki
+pub struct LokiHook {
+    static_labels: BTreeMap<String, String>,
+    label_fields: Vec<String>,
+    formatter: Box<dyn Formatter>,
+    max_streams: usize,
+    streams: Mutex<HashSet<BTreeMap<String, String>>>,
+    worker: BatchWorker<LokiLine>,
+}
+
+impl LokiHook {
+    /// Start building a hook for the Loki instance at `url`, e.g.
+    /// `http://localhost:3100`
+    pub fn builder(url: &str) -> LokiHookBuilder {
+        LokiHookBuilder {
+            url: url.to_string(),
+            static_labels: BTreeMap::new(),
+            label_fields: Vec::new(),
+            formatter: Box::new(LogfmtFormatter::new().disable_timestamp(true)),
+            encoding: LokiEncoding::default(),
+            gzip: false,
+            tenant_id: None,
+            headers: Vec::new(),
+            backoff: Backoff::default(),
+            max_streams: 1000,
+            batch: BatchConfig::default(),
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// Push everything queued so far, waiting up to `timeout`
+    pub fn flush(&self, timeout: Duration) -> Result<(), LoggixError> {
+        self.worker.flush(timeout)
+    }
+
+    /// Work out the stream labels for an entry and the fields left for the line
+    fn split_labels(&self, entry: &Entry) -> (BTreeMap<String, String>, crate::Fields) {
+        let mut labels = self.static_labels.clone();
+        labels.insert("level".to_string(), entry.level.to_string().to_lowercase());
+
+        let mut field_labels = BTreeMap::new();
+        for field in &self.label_fields {
+            if let Some(value) = entry.fields.get(field) {
+                let value = match value {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                field_labels.insert(sanitize_label_name(field), value);
+            }
+        }
+        if field_labels.is_empty() {
+            return (labels, entry.fields.clone());
+        }
+
+        let mut candidate = labels.clone();
+        candidate.extend(field_labels);
+        let accepted = {
+            let mut streams = self
+                .streams
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if streams.contains(&candidate) {
+                true
+            } else if streams.len() < self.max_streams {
+                streams.insert(candidate.clone());
+                true
+            } else {
+                false
+            }
+        };
+        if !accepted {
+            return (labels, entry.fields.clone());
+        }
+
+        let fields = entry
+            .fields
+            .iter()
+            .filter(|(key, _)| !self.label_fields.contains(key))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        (candidate, fields)
+    }
+}
+
+impl Hook for LokiHook {
+    fn levels(&self) -> Vec<Level> {
+        vec![
+            Level::Trace,
+            Level::Debug,
+            Level::Info,
+            Level::Warn,
+            Level::Error,
+            Level::Fatal,
+            Level::Panic,
+        ]
+    }
+
+    fn fire(&self, entry: &Entry) -> Result<(), LoggixError> {
+        let (labels, fields) = self.split_labels(entry);
+        let line_entry = Entry {
+            fields,
+            ..entry.clone()
+        };
+        let mut line =
+            String::from_utf8(self.formatter.format(&line_entry)?).map_err(LoggixError::format)?;
+        while line.ends_with('\n') || line.ends_with('\r') {
+            line.pop();
+        }
+
+        let size = line.len();
+        let line = LokiLine {
+            labels,
+            timestamp_nanos: entry.timestamp.timestamp_nanos_opt().unwrap_or_default(),
+            line,
+        };
+        self.worker.push(line, size)
+    }
+
+    fn flush(&self, timeout: Duration) -> Result<(), LoggixError> {
+        LokiHook::flush(self, timeout)
+    }
+
+    fn flush_async(
+        &self,
+        timeout: Duration,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), LoggixError>> + Send + '_>>
+    {
+        Box::pin(self.worker.flush_async(timeout))
+    }
+}
+
+struct LokiLine {
+    labels: BTreeMap<String, String>,
+    timestamp_nanos: i64,
+    line: String,
+}
+
+/// Request body with its content type and optional content encoding
+type EncodedBody = (Vec<u8>, &'static str, Option<&'static str>);
+
+/// A label set and its `(timestamp, line)` values
+type Stream = (BTreeMap<String, String>, Vec<(i64, String)>);
+
+struct LokiPusher {
+    client: reqwest::Client,
+    url: String,
+    headers: reqwest::header::HeaderMap,
+    encoding: LokiEncoding,
+    gzip: bool,
+    backoff: Backoff,
+}
+
+impl LokiPusher {
+    fn encode(&self, batch: Vec<LokiLine>) -> Result<EncodedBody, BoxError> {
+        // Group lines into streams, keeping each stream in arrival order
+        let mut streams: Vec<Stream> = Vec::new();
+        for line in batch {
+            match streams
+                .iter_mut()
+                .find(|(labels, _)| *labels == line.labels)
+            {
+                Some((_, values)) => values.push((line.timestamp_nanos, line.line)),
+                None => streams.push((line.labels, vec![(line.timestamp_nanos, line.line)])),
+            }
+        }
+
+        match self.encoding {
+            LokiEncoding::Protobuf => {
+                let request = proto::PushRequest {
+                    streams: streams
+                        .into_iter()
+                        .map(|(labels, values)| proto::StreamAdapter {
+                            labels: prometheus_labels(&labels),
+                            entries: values
+                                .into_iter()
+                                .map(|(nanos, line)| proto::EntryAdapter {
+                                    timestamp: Some(proto::Timestamp {
+                                        seconds: nanos.div_euclid(1_000_000_000),
+                                        nanos: nanos.rem_euclid(1_000_000_000) as i32,
+                                    }),
+                                    line,
+                                })
+                                .collect(),
+                        })
+                        .collect(),
+                };
+                let body = snap::raw::Encoder::new()
+                    .compress_vec(&prost::Message::encode_to_vec(&request))?;
+                Ok((body, "application/x-protobuf", None))
+            }
+            LokiEncoding::Json => {
+                let streams: Vec<Value> = streams
+                    .into_iter()
+                    .map(|(labels, values)| {
+                        let stream: Map<String, Value> = labels
+                            .into_iter()
+                            .map(|(name, value)| (name, Value::String(value)))
+                            .collect();
+                        let values: Vec<Value> = values
+                            .into_iter()
+                            .map(|(nanos, line)| json!([nanos.to_string(), line]))
+                            .collect();
+                        json!({ "stream": stream, "values": values })
+                    })
+                    .collect();
+                let body = serde_json::to_vec(&json!({ "streams": streams }))?;
+                if self.gzip {
+                    let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+                    encoder.write_all(&body)?;
+                    Ok((encoder.finish()?, "application/json", Some("gzip")))
+                } else {
+                    Ok((body, "application/json", None))
+                }
+            }
+        }
+    }
+}
+
+impl Export<LokiLine> for LokiPusher {
+    fn export(&self, batch: Vec<LokiLine>) -> ExportFuture<'_> {
+        Box::pin(async move {
+            let (body, content_type, content_encoding) = self.encode(batch)?;
+            send_http(&self.backoff, || {
+                let request = self
+                    .client
+                    .post(&self.url)
+                    .headers(self.headers.clone())
+                    .header(reqwest::header::CONTENT_TYPE, content_type)
+                    .body(body.clone());
+                match content_encoding {
+                    Some(encoding) => request.header(reqwest::header::CONTENT_ENCODING, encoding),
+                    None => request,
+                }
+            })
+            .await?;
+            Ok(())
+        })
+    }
+}
+
+/// Render labels in the Prometheus selector syntax Loki's protobuf API expects
+fn prometheus_labels(labels: &BTreeMap<String, String>) -> String {
+    let pairs: Vec<String> = labels
+        .iter()
+        .map(|(name, value)| {
+            let escaped = value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            format!("{}=\"{}\"", name, escaped)
+        })
+        .collect();
+    format!("{{{}}}", pairs.join(", "))
+}
+
+/// Label names must match `[a-zA-Z_][a-zA-Z0-9_]*`
+fn sanitize_label_name(name: &str) -> String {
+    let mut sanitized: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if sanitized.is_empty() || sanitized.starts_with(|c: char| c.is_ascii_digit()) {
+        sanitized.insert(0, '_');
+    }
+    sanitized
+}
+
+/// Messages from Loki's `logproto` push API
+mod proto {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct PushRequest {
+        #[prost(message, repeated, tag = "1")]
+        pub streams: Vec<StreamAdapter>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct StreamAdapter {
+        #[prost(string, tag = "1")]
+        pub labels: String,
+        #[prost(message, repeated, tag = "2")]
+        pub entries: Vec<EntryAdapter>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct EntryAdapter {
+        #[prost(message, optional, tag = "1")]
+        pub timestamp: Option<Timestamp>,
+        #[prost(string, tag = "2")]
+        pub line: String,
+    }
+
+    /// `google.protobuf.Timestamp`
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Timestamp {
+        #[prost(int64, tag = "1")]
+        pub seconds: i64,
+        #[prost(int32, tag = "2")]
+        pub nanos: i32,
+    }
+}
@@ -0,0 +1,101 @@
+//! Insertion-ordered JSON objects for formatters whose consumers expect a
+//! particular key order (serde_json's `Map` sorts its keys).
+
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde_json::{Map, Value};
+
+/// A JSON object that serializes its keys in insertion order
+#[derive(Debug, Default)]
+pub(crate) struct OrderedObject {
+    entries: Vec<(String, Value)>,
+}
+
+impl OrderedObject {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert a top-level key, replacing any previous value in place
+    pub(crate) fn insert(&mut self, key: impl Into<String>, value: Value) {
+        let key = key.into();
+        match self.entries.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, existing)) => *existing = value,
+            None => self.entries.push((key, value)),
+        }
+    }
+
+    /// Whether a top-level key is present
+    pub(crate) fn contains_key(&self, key: &str) -> bool {
+        self.entries.iter().any(|(k, _)| k == key)
+    }
+
+    /// Top-level keys in insertion order
+    pub(crate) fn keys(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|(k, _)| k.as_str())
+    }
+
+    /// Insert a value at a dotted path, creating nested objects as needed
+    ///
+    /// `insert_path("trace.id", v)` produces `{"trace": {"id": v}}` and merges
+    /// with whatever already lives under `trace`.
+    pub(crate) fn insert_path(&mut self, path: &str, value: Value) {
+        let (root, rest) = match path.split_once('.') {
+            Some((root, rest)) => (root, Some(rest)),
+            None => (path, None),
+        };
+        let Some(rest) = rest else {
+            match (self.entries.iter_mut().find(|(k, _)| k == root), value) {
+                (Some((_, Value::Object(existing))), Value::Object(incoming)) => {
+                    existing.extend(incoming);
+                }
+                (_, value) => self.insert(root, value),
+            }
+            return;
+        };
+
+        let position = self.entries.iter().position(|(k, _)| k == root);
+        let slot = match position {
+            Some(i) => &mut self.entries[i].1,
+            None => {
+                self.entries
+                    .push((root.to_string(), Value::Object(Map::new())));
+                &mut self.entries.last_mut().unwrap().1
+            }
+        };
+        insert_nested(slot, rest, value);
+    }
+}
+
+fn insert_nested(slot: &mut Value, path: &str, value: Value) {
+    if !slot.is_object() {
+        *slot = Value::Object(Map::new());
+    }
+    let map = slot.as_object_mut().unwrap();
+    match path.split_once('.') {
+        Some((head, rest)) => {
+            let child = map
+                .entry(head.to_string())
+                .or_insert_with(|| Value::Object(Map::new()));
+            insert_nested(child, rest, value);
+        }
+        None => match (map.get_mut(path), value) {
+            // Merging an object into an existing object keeps both sides
+            (Some(Value::Object(existing)), Value::Object(incoming)) => {
+                existing.extend(incoming);
+            }
+            (_, value) => {
+                map.insert(path.to_string(), value);
+            }
+        },
+    }
+}
+
+impl Serialize for OrderedObject {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.entries.len()))?;
+        for (key, value) in &self.entries {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
@@ -0,0 +1,594 @@
+//! OpenTelemetry Logs data model and an OTLP/HTTP exporter hook.
+//!
+//! [`OtlpHook`] converts each [`Entry`] into an OpenTelemetry [`LogRecord`]
+//! (severity from the level, body from the message, attributes from the
+//! fields, trace and span IDs from well-known fields) and exports batches to
+//! an OTLP/HTTP collector using either the protobuf or the JSON encoding.
+
+use crate::batch::{BatchConfig, BatchWorker, BoxError, Export, ExportFuture};
+use crate::encoding::base64_encode;
+use crate::{Entry, Hook, Level, LoggixError};
+use serde_json::{json, Map, Value};
+use std::time::Duration;
+
+pub use proto::{
+    any_value, AnyValue, ArrayValue, ExportLogsServiceRequest, InstrumentationScope, KeyValue,
+    KeyValueList, LogRecord, Resource, ResourceLogs, ScopeLogs,
+};
+
+/// Protobuf messages from `opentelemetry/proto/{common,resource,logs}/v1`
+pub mod proto {
+    /// `opentelemetry.proto.collector.logs.v1.ExportLogsServiceRequest`
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ExportLogsServiceRequest {
+        #[prost(message, repeated, tag = "1")]
+        pub resource_logs: Vec<ResourceLogs>,
+    }
+
+    /// `opentelemetry.proto.logs.v1.ResourceLogs`
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ResourceLogs {
+        #[prost(message, optional, tag = "1")]
+        pub resource: Option<Resource>,
+        #[prost(message, repeated, tag = "2")]
+        pub scope_logs: Vec<ScopeLogs>,
+        #[prost(string, tag = "3")]
+        pub schema_url: String,
+    }
+
+    /// `opentelemetry.proto.resource.v1.Resource`
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Resource {
+        #[prost(message, repeated, tag = "1")]
+        pub attributes: Vec<KeyValue>,
+        #[prost(uint32, tag = "2")]
+        pub dropped_attributes_count: u32,
+    }
+
+    /// `opentelemetry.proto.logs.v1.ScopeLogs`
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ScopeLogs {
+        #[prost(message, optional, tag = "1")]
+        pub scope: Option<InstrumentationScope>,
+        #[prost(message, repeated, tag = "2")]
+        pub log_records: Vec<LogRecord>,
+        #[prost(string, tag = "3")]
+        pub schema_url: String,
+    }
+
+    /// `opentelemetry.proto.common.v1.InstrumentationScope`
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct InstrumentationScope {
+        #[prost(string, tag = "1")]
+        pub name: String,
+        #[prost(string, tag = "2")]
+        pub version: String,
+    }
+
+    /// `opentelemetry.proto.logs.v1.LogRecord`
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct LogRecord {
+        #[prost(fixed64, tag = "1")]
+        pub time_unix_nano: u64,
+        #[prost(fixed64, tag = "11")]
+        pub observed_time_unix_nano: u64,
+        #[prost(int32, tag = "2")]
+        pub severity_number: i32,
+        #[prost(string, tag = "3")]
+        pub severity_text: String,
+        #[prost(message, optional, tag = "5")]
+        pub body: Option<AnyValue>,
+        #[prost(message, repeated, tag = "6")]
+        pub attributes: Vec<KeyValue>,
+        #[prost(uint32, tag = "7")]
+        pub dropped_attributes_count: u32,
+        #[prost(fixed32, tag = "8")]
+        pub flags: u32,
+        #[prost(bytes = "vec", tag = "9")]
+        pub trace_id: Vec<u8>,
+        #[prost(bytes = "vec", tag = "10")]
+        pub span_id: Vec<u8>,
+    }
+
+    /// `opentelemetry.proto.common.v1.AnyValue`
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct AnyValue {
+        #[prost(oneof = "any_value::Value", tags = "1, 2, 3, 4, 5, 6, 7")]
+        pub value: Option<any_value::Value>,
+    }
+
+    pub mod any_value {
+        #[derive(Clone, PartialEq, prost::Oneof)]
+        pub enum Value {
+            #[prost(string, tag = "1")]
+            StringValue(String),
+            #[prost(bool, tag = "2")]
+            BoolValue(bool),
+            #[prost(int64, tag = "3")]
+            IntValue(i64),
+            #[prost(double, tag = "4")]
+            DoubleValue(f64),
+            #[prost(message, tag = "5")]
+            ArrayValue(super::ArrayValue),
+            #[prost(message, tag = "6")]
+            KvlistValue(super::KeyValueList),
+            #[prost(bytes, tag = "7")]
+            BytesValue(Vec<u8>),
+        }
+    }
+
+    /// `opentelemetry.proto.common.v1.ArrayValue`
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ArrayValue {
+        #[prost(message, repeated, tag = "1")]
+        pub values: Vec<AnyValue>,
+    }
+
+    /// `opentelemetry.proto.common.v1.KeyValueList`
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct KeyValueList {
+        #[prost(message, repeated, tag = "1")]
+        pub values: Vec<KeyValue>,
+    }
+
+    /// `opentelemetry.proto.common.v1.KeyValue`
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct KeyValue {
+        #[prost(string, tag = "1")]
+        pub key: String,
+        #[prost(message, optional, tag = "2")]
+        pub value: Option<AnyValue>,
+    }
+}
+
+/// Map a level to an OpenTelemetry `SeverityNumber`
+pub fn severity_number(level: Level) -> i32 {
+    match level {
+        Level::Trace => 1,
+        Level::Debug => 5,
+        Level::Info => 9,
+        Level::Warn => 13,
+        Level::Error => 17,
+        Level::Fatal => 21,
+        Level::Panic => 24,
+    }
+}
+
+impl AnyValue {
+    /// Convert a JSON field value into an OpenTelemetry value
+    pub fn from_json(value: &Value) -> Self {
+        let value = match value {
+            Value::Null => None,
+            Value::Bool(b) => Some(any_value::Value::BoolValue(*b)),
+            Value::Number(n) => Some(match n.as_i64() {
+                Some(i) => any_value::Value::IntValue(i),
+                None => any_value::Value::DoubleValue(n.as_f64().unwrap_or_default()),
+            }),
+            Value::String(s) => Some(any_value::Value::StringValue(s.clone())),
+            Value::Array(items) => Some(any_value::Value::ArrayValue(ArrayValue {
+                values: items.iter().map(AnyValue::from_json).collect(),
+            })),
+            Value::Object(map) => Some(any_value::Value::KvlistValue(KeyValueList {
+                values: map
+                    .iter()
+                    .map(|(key, value)| KeyValue::new(key, AnyValue::from_json(value)))
+                    .collect(),
+            })),
+        };
+        AnyValue { value }
+    }
+
+    /// Encode using the OTLP/JSON mapping
+    pub fn to_json(&self) -> Value {
+        match &self.value {
+            None => json!({}),
+            Some(any_value::Value::StringValue(s)) => json!({ "stringValue": s }),
+            Some(any_value::Value::BoolValue(b)) => json!({ "boolValue": b }),
+            // 64-bit integers are encoded as decimal strings in proto3 JSON
+            Some(any_value::Value::IntValue(i)) => json!({ "intValue": i.to_string() }),
+            Some(any_value::Value::DoubleValue(d)) => json!({ "doubleValue": d }),
+            Some(any_value::Value::ArrayValue(array)) => json!({
+                "arrayValue": {
+                    "values": array.values.iter().map(AnyValue::to_json).collect::<Vec<_>>()
+                }
+            }),
+            Some(any_value::Value::KvlistValue(list)) => json!({
+                "kvlistValue": {
+                    "values": list.values.iter().map(KeyValue::to_json).collect::<Vec<_>>()
+                }
+            }),
+            Some(any_value::Value::BytesValue(bytes)) => {
+                json!({ "bytesValue": base64_encode(bytes) })
+            }
+        }
+    }
+}
+
+impl KeyValue {
+    pub fn new(key: &str, value: AnyValue) -> Self {
+        KeyValue {
+            key: key.to_string(),
+            value: Some(value),
+        }
+    }
+
+    /// Encode using the OTLP/JSON mapping
+    pub fn to_json(&self) -> Value {
+        json!({
+            "key": self.key,
+            "value": self.value.as_ref().map(AnyValue::to_json).unwrap_or_else(|| json!({})),
+        })
+    }
+}
+
+impl LogRecord {
+    /// Encode using the OTLP/JSON mapping, with hex trace and span IDs
+    pub fn to_json(&self) -> Value {
+        let mut record = Map::new();
+        record.insert(
+            "timeUnixNano".to_string(),
+            Value::String(self.time_unix_nano.to_string()),
+        );
+        record.insert(
+            "observedTimeUnixNano".to_string(),
+            Value::String(self.observed_time_unix_nano.to_string()),
+        );
+        record.insert("severityNumber".to_string(), json!(self.severity_number));
+        record.insert("severityText".to_string(), json!(self.severity_text));
+        if let Some(body) = &self.body {
+            record.insert("body".to_string(), body.to_json());
+        }
+        record.insert(
+            "attributes".to_string(),
+            Value::Array(self.attributes.iter().map(KeyValue::to_json).collect()),
+        );
+        if self.flags != 0 {
+            record.insert("flags".to_string(), json!(self.flags));
+        }
+        if !self.trace_id.is_empty() {
+            record.insert("traceId".to_string(), json!(hex_encode(&self.trace_id)));
+        }
+        if !self.span_id.is_empty() {
+            record.insert("spanId".to_string(), json!(hex_encode(&self.span_id)));
+        }
+        Value::Object(record)
+    }
+}
+
+impl ExportLogsServiceRequest {
+    /// Encode using the OTLP/JSON mapping
+    pub fn to_json(&self) -> Value {
+        let resource_logs = self
+            .resource_logs
+            .iter()
+            .map(|resource_logs| {
+                let attributes: Vec<Value> = resource_logs
+                    .resource
+                    .as_ref()
+                    .map(|resource| resource.attributes.iter().map(KeyValue::to_json).collect())
+                    .unwrap_or_default();
+                let scope_logs: Vec<Value> = resource_logs
+                    .scope_logs
+                    .iter()
+                    .map(|scope_logs| {
+                        let scope = scope_logs.scope.clone().unwrap_or_default();
+                        json!({
+                            "scope": { "name": scope.name, "version": scope.version },
+                            "logRecords": scope_logs
+                                .log_records
+                                .iter()
+                                .map(LogRecord::to_json)
+                                .collect::<Vec<_>>(),
+                        })
+                    })
+                    .collect();
+                json!({
+                    "resource": { "attributes": attributes },
+                    "scopeLogs": scope_logs,
+                })
+            })
+            .collect::<Vec<_>>();
+        json!({ "resourceLogs": resource_logs })
+    }
+}
+
+/// Wire encoding used for OTLP/HTTP export
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OtlpEncoding {
+    /// `application/x-protobuf`
+    #[default]
+    Protobuf,
+    /// `application/json`
+    Json,
+}
+
+/// Builder for [`OtlpHook`]
+#[derive(Debug, Clone)]
+pub struct OtlpHookBuilder {
+    endpoint: String,
+    encoding: OtlpEncoding,
+    headers: Vec<(String, String)>,
+    resource_attributes: Vec<(String, Value)>,
+    trace_id_field: String,
+    span_id_field: String,
+    batch: BatchConfig,
+    export_timeout: Duration,
+}
+
+impl OtlpHookBuilder {
+    /// Choose protobuf (default) or JSON encoding
+    pub fn encoding(mut self, encoding: OtlpEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Add an HTTP header sent with every export request
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Add a resource attribute such as `service.name`
+    pub fn resource_attribute<V: Into<Value>>(mut self, key: &str, value: V) -> Self {
+        self.resource_attributes
+            .push((key.to_string(), value.into()));
+        self
+    }
+
+    /// Field holding the hex trace ID (`trace_id` by default)
+    pub fn trace_id_field(mut self, field: &str) -> Self {
+        self.trace_id_field = field.to_string();
+        self
+    }
+
+    /// Field holding the hex span ID (`span_id` by default)
+    pub fn span_id_field(mut self, field: &str) -> Self {
+        self.span_id_field = field.to_string();
+        self
+    }
+
+    /// Maximum number of records per export request (512 by default)
+    pub fn batch_size(mut self, size: usize) -> Self {
+        self.batch.max_items = size;
+        self
+    }
+
+    /// Maximum time a record waits before its batch is exported (1s by default)
+    pub fn batch_timeout(mut self, timeout: Duration) -> Self {
+        self.batch.max_latency = timeout;
+        self
+    }
+
+    /// Maximum number of records waiting for export before new ones are dropped
+    pub fn queue_capacity(mut self, capacity: usize) -> Self {
+        self.batch.queue_capacity = capacity;
+        self
+    }
+
+    /// Timeout for a single export request (10s by default)
+    pub fn export_timeout(mut self, timeout: Duration) -> Self {
+        self.export_timeout = timeout;
+        self
+    }
+
+    /// Start the export worker and create the hook
+    pub fn build(self) -> Result<OtlpHook, LoggixError> {
+        let client = reqwest::Client::builder()
+            .timeout(self.export_timeout)
+            .build()
+            .map_err(LoggixError::config)?;
+        let mut headers = reqwest::header::HeaderMap::new();
+        for (name, value) in &self.headers {
+            headers.insert(
+                reqwest::header::HeaderName::from_bytes(name.as_bytes())
+                    .map_err(LoggixError::config)?,
+                reqwest::header::HeaderValue::from_str(value).map_err(LoggixError::config)?,
+            );
+        }
+        let resource = Resource {
+            attributes: self
+                .resource_attributes
+                .iter()
+                .map(|(key, value)| KeyValue::new(key, AnyValue::from_json(value)))
+                .collect(),
+            dropped_attributes_count: 0,
+        };
+        let exporter = OtlpExporter {
+            client,
+            endpoint: self.endpoint,
+            encoding: self.encoding,
+            headers,
+            resource,
+        };
+        let worker = BatchWorker::spawn("OtlpHook", self.batch, exporter)?;
+
+        Ok(OtlpHook {
+            trace_id_field: self.trace_id_field,
+            span_id_field: self.span_id_field,
+            worker,
+        })
+    }
+}
+
+/// A hook that exports log entries to an OpenTelemetry collector over OTLP/HTTP
+///
+/// Entries are converted on the logging thread and exported in batches from a
+/// background thread, so `fire` never waits for the network.
+pub struct OtlpHook {
+    trace_id_field: String,
+    span_id_field: String,
+    worker: BatchWorker<LogRecord>,
+}
+
+impl OtlpHook {
+    /// Start building a hook that exports to `endpoint`, e.g.
+    /// `http://localhost:4318/v1/logs`
+    pub fn builder(endpoint: &str) -> OtlpHookBuilder {
+        OtlpHookBuilder {
+            endpoint: endpoint.to_string(),
+            encoding: OtlpEncoding::default(),
+            headers: Vec::new(),
+            resource_attributes: Vec::new(),
+            trace_id_field: "trace_id".to_string(),
+            span_id_field: "span_id".to_string(),
+            batch: BatchConfig::default(),
+            export_timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// Convert an entry into an OpenTelemetry log record
+    ///
+    /// Trace and span IDs are taken from their fields when they hold valid
+    /// hex IDs of the right length; otherwise they stay ordinary attributes.
+    pub fn to_log_record(&self, entry: &Entry) -> LogRecord {
+        let trace_id = entry
+            .fields
+            .get(&self.trace_id_field)
+            .and_then(Value::as_str)
+            .and_then(|id| hex_decode(id, 16));
+        let span_id = entry
+            .fields
+            .get(&self.span_id_field)
+            .and_then(Value::as_str)
+            .and_then(|id| hex_decode(id, 8));
+
+        let mut fields: Vec<(&String, &Value)> = entry
+            .fields
+            .iter()
+            .filter(|(key, _)| {
+                let is_trace = trace_id.is_some() && **key == self.trace_id_field;
+                let is_span = span_id.is_some() && **key == self.span_id_field;
+                !is_trace && !is_span
+            })
+            .collect();
+        fields.sort_by(|a, b| a.0.cmp(b.0));
+
+        let time_unix_nano = entry.timestamp.timestamp_nanos_opt().unwrap_or_default() as u64;
+        LogRecord {
+            time_unix_nano,
+            observed_time_unix_nano: chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+                as u64,
+            severity_number: severity_number(entry.level),
+            severity_text: entry.level.to_string(),
+            body: Some(AnyValue {
+                value: Some(any_value::Value::StringValue(entry.message.clone())),
+            }),
+            attributes: fields
+                .into_iter()
+                .map(|(key, value)| KeyValue::new(key, AnyValue::from_json(value)))
+                .collect(),
+            dropped_attributes_count: 0,
+            flags: 0,
+            trace_id: trace_id.unwrap_or_default(),
+            span_id: span_id.unwrap_or_default(),
+        }
+    }
+
+    /// Export everything queued so far, waiting up to `timeout`
+    pub fn flush(&self, timeout: Duration) -> Result<(), LoggixError> {
+        self.worker.flush(timeout)
+    }
+}
+
+impl Hook for OtlpHook {
+    fn levels(&self) -> Vec<Level> {
+        vec![
+            Level::Trace,
+            Level::Debug,
+            Level::Info,
+            Level::Warn,
+            Level::Error,
+            Level::Fatal,
+            Level::Panic,
+        ]
+    }
+
+    fn fire(&self, entry: &Entry) -> Result<(), LoggixError> {
+        let record = self.to_log_record(entry);
+        self.worker.push(record, 0)
+    }
+
+    fn flush(&self, timeout: Duration) -> Result<(), LoggixError> {
+        OtlpHook::flush(self, timeout)
+    }
+
+    fn flush_async(
+        &self,
+        timeout: Duration,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), LoggixError>> + Send + '_>>
+    {
+        Box::pin(self.worker.flush_async(timeout))
+    }
+}
+
+struct OtlpExporter {
+    client: reqwest::Client,
+    endpoint: String,
+    encoding: OtlpEncoding,
+    headers: reqwest::header::HeaderMap,
+    resource: Resource,
+}
+
+impl Export<LogRecord> for OtlpExporter {
+    fn export(&self, batch: Vec<LogRecord>) -> ExportFuture<'_> {
+        Box::pin(async move {
+            let request = ExportLogsServiceRequest {
+                resource_logs: vec![ResourceLogs {
+                    resource: Some(self.resource.clone()),
+                    scope_logs: vec![ScopeLogs {
+                        scope: Some(InstrumentationScope {
+                            name: "loggix".to_string(),
+                            version: env!("CARGO_PKG_VERSION").to_string(),
+                        }),
+                        log_records: batch,
+                        schema_url: String::new(),
+                    }],
+                    schema_url: String::new(),
+                }],
+            };
+            let (content_type, body) = match self.encoding {
+                OtlpEncoding::Protobuf => (
+                    "application/x-protobuf",
+                    prost::Message::encode_to_vec(&request),
+                ),
+                OtlpEncoding::Json => ("application/json", serde_json::to_vec(&request.to_json())?),
+            };
+
+            let response = self
+                .client
+                .post(&self.endpoint)
+                .headers(self.headers.clone())
+                .header(reqwest::header::CONTENT_TYPE, content_type)
+                .body(body)
+                .send()
+                .await?;
+            let status = response.status();
+            if !status.is_success() {
+                let text = response.text().await.unwrap_or_default();
+                return Err(BoxError::from(format!(
+                    "collector responded with {}: {}",
+                    status, text
+                )));
+            }
+            Ok(())
+        })
+    }
+}
+
+fn hex_decode(hex: &str, len: usize) -> Option<Vec<u8>> {
+    if hex.len() != len * 2 || !hex.is_ascii() {
+        return None;
+    }
+    let bytes = (0..len)
+        .map(|i| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    // All-zero IDs are invalid in OpenTelemetry
+    if bytes.iter().all(|b| *b == 0) {
+        return None;
+    }
+    Some(bytes)
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,198 @@
+//! Retrying wrapper for any hook.
+//!
+//! [`RetryHook`] calls the wrapped hook again after a failure, waiting
+//! between attempts as a [`Backoff`] policy says. Errors marked with
+//! [`PermanentError`], and errors from an open
+//! [`CircuitBreakerHook`](crate::CircuitBreakerHook), are returned at once; a
+//! custom classifier can decide for other errors.
+
+use crate::backoff::Backoff;
+use crate::circuit_breaker::CircuitOpen;
+use crate::{Entry, Hook, Level, LoggixError};
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+/// A hook error that retrying cannot fix, such as a rejected payload
+///
+/// Hooks return it as the source of a [`LoggixError::Hook`] to tell
+/// [`RetryHook`] to give up right away and
+/// [`CircuitBreakerHook`](crate::CircuitBreakerHook) that the destination
+/// itself is healthy.
+#[derive(Debug)]
+pub struct PermanentError(Box<dyn Error + Send + Sync>);
+
+impl PermanentError {
+    pub fn new<E: Into<Box<dyn Error + Send + Sync>>>(error: E) -> Self {
+        Self(error.into())
+    }
+}
+
+impl fmt::Display for PermanentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Error for PermanentError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.0.as_ref())
+    }
+}
+
+/// Whether `error`, or an error it wraps, is a [`PermanentError`] or
+/// [`CircuitOpen`]
+pub(crate) fn is_permanent(error: &(dyn Error + 'static)) -> bool {
+    let mut current = Some(error);
+    while let Some(error) = current {
+        if error.is::<PermanentError>() || error.is::<CircuitOpen>() {
+            return true;
+        }
+        current = error.source();
+    }
+    false
+}
+
+type Classifier = Box<dyn Fn(&LoggixError) -> bool + Send + Sync>;
+
+/// A hook that retries the wrapped hook with exponential backoff
+///
+/// The backoff's `max_retries` caps the attempts; once they run out, the
+/// last error is returned with the number of retries. Sync `fire` sleeps the
+/// calling thread between attempts, `fire_async` awaits a timer.
+pub struct RetryHook<H: Hook> {
+    hook: H,
+    backoff: Backoff,
+    retry_if: Option<Classifier>,
+}
+
+impl<H: Hook> RetryHook<H> {
+    /// Retry `hook` with the default [`Backoff`] (5 retries, 100ms doubling
+    /// to at most 10s, with jitter)
+    pub fn new(hook: H) -> Self {
+        Self {
+            hook,
+            backoff: Backoff::default(),
+            retry_if: None,
+        }
+    }
+
+    /// Delays between attempts and the maximum number of retries
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Decide which errors are worth retrying
+    ///
+    /// By default every error is, except [`PermanentError`] and
+    /// [`CircuitOpen`]. Those are never retried, whatever `retry_if` says.
+    pub fn retry_if<F>(mut self, classify: F) -> Self
+    where
+        F: Fn(&LoggixError) -> bool + Send + Sync + 'static,
+    {
+        self.retry_if = Some(Box::new(classify));
+        self
+    }
+
+    /// The wrapped hook
+    pub fn inner(&self) -> &H {
+        &self.hook
+    }
+
+    /// The delay before retry number `retry`, or `None` to give up
+    fn next_delay(&self, error: &LoggixError, retry: u32) -> Option<Duration> {
+        let transient = !is_permanent(error) && self.retry_if.as_ref().is_none_or(|f| f(error));
+        (transient && retry < self.backoff.retries()).then(|| self.backoff.delay(retry))
+    }
+}
+
+/// The last error once retries run out, keeping it as the source so
+/// wrappers can still classify it
+#[derive(Debug)]
+struct GaveUp {
+    error: Box<dyn Error + Send + Sync>,
+    retries: u32,
+}
+
+impl fmt::Display for GaveUp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (gave up after {} retries)", self.error, self.retries)
+    }
+}
+
+impl Error for GaveUp {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.error.as_ref())
+    }
+}
+
+/// Mark the last error as final, still naming the hook that failed
+fn gave_up(error: LoggixError, retries: u32) -> LoggixError {
+    if retries == 0 {
+        return error;
+    }
+    let (name, error) = match error {
+        LoggixError::Hook { name, source } => (name, source),
+        other => ("RetryHook".to_string(), other.into()),
+    };
+    LoggixError::hook(name, GaveUp { error, retries })
+}
+
+impl<H: Hook> Hook for RetryHook<H> {
+    fn levels(&self) -> Vec<Level> {
+        self.hook.levels()
+    }
+
+    fn fire(&self, entry: &Entry) -> Result<(), LoggixError> {
+        let mut retry = 0;
+        loop {
+            let error = match self.hook.fire(entry) {
+                Ok(()) => return Ok(()),
+                Err(error) => error,
+            };
+            match self.next_delay(&error, retry) {
+                Some(delay) => std::thread::sleep(delay),
+                None => return Err(gave_up(error, retry)),
+            }
+            retry += 1;
+        }
+    }
+
+    fn fire_async<'a>(
+        &'a self,
+        entry: &'a Entry,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), LoggixError>> + Send + 'a>>
+    {
+        Box::pin(async move {
+            let mut retry = 0;
+            loop {
+                let delay = match self.hook.fire_async(entry).await {
+                    Ok(()) => return Ok(()),
+                    Err(error) => match self.next_delay(&error, retry) {
+                        Some(delay) => delay,
+                        None => return Err(gave_up(error, retry)),
+                    },
+                };
+                tokio::time::sleep(delay).await;
+                retry += 1;
+            }
+        })
+    }
+
+    fn requires_async(&self) -> bool {
+        self.hook.requires_async()
+    }
+
+    fn flush(&self, timeout: Duration) -> Result<(), LoggixError> {
+        self.hook.flush(timeout)
+    }
+
+    fn flush_async(
+        &self,
+        timeout: Duration,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), LoggixError>> + Send + '_>>
+    {
+        self.hook.flush_async(timeout)
+    }
+}
@@ -0,0 +1,187 @@
+//! Graceful shutdown of a [`Logger`].
+//!
+//! [`Logger::shutdown`] stops the logger from accepting entries, waits for
+//! entries that are being logged to finish, then flushes the output and every
+//! hook within one timeout. Anything that could not be delivered is listed in
+//! the [`ShutdownError`] returned as [`LoggixError::Shutdown`]. [`ShutdownGuard`] does the same when it
+//! goes out of scope, for `main` functions and jobs that may return early.
+
+use crate::{Logger, LoggixError};
+use std::fmt;
+use std::io::Write;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// What a shutdown or flush could not complete
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ShutdownError {
+    /// Entries still being logged when the timeout ran out
+    pub in_flight: usize,
+    /// Why the output could not be flushed
+    pub output: Option<String>,
+    /// Hooks that could not deliver everything they had accepted, by their
+    /// position in the logger, with the hook's error
+    pub hooks: Vec<(usize, String)>,
+}
+
+impl ShutdownError {
+    pub(crate) fn into_result(self) -> Result<(), LoggixError> {
+        if self.in_flight == 0 && self.output.is_none() && self.hooks.is_empty() {
+            Ok(())
+        } else {
+            Err(LoggixError::Shutdown(self))
+        }
+    }
+}
+
+impl fmt::Display for ShutdownError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut problems = Vec::new();
+        if self.in_flight > 0 {
+            problems.push(format!("{} entries still being logged", self.in_flight));
+        }
+        if let Some(err) = &self.output {
+            problems.push(format!("output: {}", err));
+        }
+        for (index, err) in &self.hooks {
+            problems.push(format!("hook {}: {}", index, err));
+        }
+        write!(
+            f,
+            "logger did not flush completely: {}",
+            problems.join("; ")
+        )
+    }
+}
+
+impl std::error::Error for ShutdownError {}
+
+/// Marks an entry as being logged until dropped
+pub(crate) struct InFlight<'a>(&'a Logger);
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Logger {
+    /// Register an entry about to be logged, unless the logger is shut down
+    pub(crate) fn admit(&self) -> Result<InFlight<'_>, LoggixError> {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        let guard = InFlight(self);
+        if self.closed.load(Ordering::SeqCst) {
+            return Err(LoggixError::Filtered(
+                "logger has been shut down".to_string(),
+            ));
+        }
+        Ok(guard)
+    }
+
+    /// Whether [`shutdown`](Self::shutdown) has been called
+    pub fn is_shut_down(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    /// Stop accepting entries, then flush the output and all hooks, waiting
+    /// up to `timeout` in total
+    ///
+    /// Logging afterwards fails. Hooks are flushed even if one fails, and
+    /// every failure is reported in a [`LoggixError::Shutdown`].
+    pub fn shutdown(&self, timeout: Duration) -> Result<(), LoggixError> {
+        let deadline = Instant::now() + timeout;
+        self.closed.store(true, Ordering::SeqCst);
+        while self.in_flight.load(Ordering::SeqCst) > 0 && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        self.drain(deadline).into_result()
+    }
+
+    /// Like [`shutdown`](Self::shutdown), awaiting hooks with
+    /// [`Hook::flush_async`](crate::Hook::flush_async)
+    pub async fn shutdown_async(&self, timeout: Duration) -> Result<(), LoggixError> {
+        let deadline = Instant::now() + timeout;
+        self.closed.store(true, Ordering::SeqCst);
+        while self.in_flight.load(Ordering::SeqCst) > 0 && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+
+        let mut report = ShutdownError {
+            in_flight: self.in_flight.load(Ordering::SeqCst),
+            output: self.flush_output(),
+            hooks: Vec::new(),
+        };
+        for (index, hook) in self.hooks.iter().enumerate() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let result = tokio::time::timeout(remaining, hook.flush_async(remaining))
+                .await
+                .unwrap_or_else(|_| Err(LoggixError::hook("flush", "timed out")));
+            if let Err(e) = result {
+                report.hooks.push((index, e.to_string()));
+            }
+        }
+        report.into_result()
+    }
+
+    /// Build the logger together with a guard that shuts it down, waiting
+    /// up to `timeout`, when dropped
+    pub fn build_with_guard(self, timeout: Duration) -> (Arc<Self>, ShutdownGuard) {
+        let logger = self.build();
+        let guard = ShutdownGuard {
+            logger: Arc::clone(&logger),
+            timeout,
+        };
+        (logger, guard)
+    }
+
+    /// Flush the output and hooks until `deadline`, collecting failures
+    pub(crate) fn drain(&self, deadline: Instant) -> ShutdownError {
+        let mut report = ShutdownError {
+            in_flight: self.in_flight.load(Ordering::SeqCst),
+            output: self.flush_output(),
+            hooks: Vec::new(),
+        };
+        for (index, hook) in self.hooks.iter().enumerate() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if let Err(e) = hook.flush(remaining) {
+                report.hooks.push((index, e.to_string()));
+            }
+        }
+        report
+    }
+
+    fn flush_output(&self) -> Option<String> {
+        let mut output = self
+            .output
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        output.flush().err().map(|err| err.to_string())
+    }
+}
+
+/// Shuts a logger down when dropped, see [`Logger::build_with_guard`]
+///
+/// Failures are printed to stderr, since there is nobody left to return
+/// them to. Keep the guard alive for as long as the logger is used, e.g. as
+/// `let (logger, _guard) = ...` in `main`.
+#[must_use = "the logger is shut down as soon as the guard is dropped"]
+pub struct ShutdownGuard {
+    logger: Arc<Logger>,
+    timeout: Duration,
+}
+
+impl ShutdownGuard {
+    /// The guarded logger
+    pub fn logger(&self) -> &Arc<Logger> {
+        &self.logger
+    }
+}
+
+impl Drop for ShutdownGuard {
+    fn drop(&mut self) {
+        if let Err(e) = self.logger.shutdown(self.timeout) {
+            crate::handle_error(e);
+        }
+    }
+}
@@ -0,0 +1,391 @@
+//! Splunk HTTP Event Collector (HEC) hook.
+//!
+//! Entries are wrapped in the HEC event envelope and sent in batches to
+//! `/services/collector/event`. Responses carry a HEC status code, which
+//! decides whether a failed request is retried: a busy or unhealthy
+//! collector is retried with backoff, while token, index and format errors
+//! are reported right away. With indexer acknowledgements enabled, a batch
+//! only counts as delivered once Splunk confirms it has been indexed.
+
+use crate::backoff::{is_retryable_status, retry, Attempt, Backoff};
+use crate::batch::{BatchConfig, BatchWorker, BoxError, Export, ExportFuture};
+use crate::{Entry, Hook, Level, LoggixError};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+const EVENT_PATH: &str = "/services/collector/event";
+const ACK_PATH: &str = "/services/collector/ack";
+
+/// Builder for [`SplunkHook`]
+pub struct SplunkHookBuilder {
+    url: String,
+    token: String,
+    host: Option<String>,
+    source: Option<String>,
+    sourcetype: Option<String>,
+    index: Option<String>,
+    channel: Option<String>,
+    acknowledgements: bool,
+    ack_timeout: Duration,
+    ack_poll_interval: Duration,
+    backoff: Backoff,
+    batch: BatchConfig,
+    timeout: Duration,
+}
+
+impl SplunkHookBuilder {
+    /// Event `host` (the machine's hostname by default)
+    pub fn host(mut self, host: &str) -> Self {
+        self.host = Some(host.to_string());
+        self
+    }
+
+    /// Event `source`
+    pub fn source(mut self, source: &str) -> Self {
+        self.source = Some(source.to_string());
+        self
+    }
+
+    /// Event `sourcetype`
+    pub fn sourcetype(mut self, sourcetype: &str) -> Self {
+        self.sourcetype = Some(sourcetype.to_string());
+        self
+    }
+
+    /// Target index; the token's default index is used otherwise
+    pub fn index(mut self, index: &str) -> Self {
+        self.index = Some(index.to_string());
+        self
+    }
+
+    /// Data channel sent as `X-Splunk-Request-Channel` (a random UUID is
+    /// generated when acknowledgements are enabled and none is set)
+    pub fn channel(mut self, channel: &str) -> Self {
+        self.channel = Some(channel.to_string());
+        self
+    }
+
+    /// Wait for indexer acknowledgements before a batch counts as delivered
+    pub fn acknowledgements(mut self, enabled: bool) -> Self {
+        self.acknowledgements = enabled;
+        self
+    }
+
+    /// How long to wait for an acknowledgement before sending the batch
+    /// again, within the retry policy (60s by default)
+    pub fn ack_timeout(mut self, timeout: Duration) -> Self {
+        self.ack_timeout = timeout;
+        self
+    }
+
+    /// How often to poll for acknowledgements (1s by default)
+    pub fn ack_poll_interval(mut self, interval: Duration) -> Self {
+        self.ack_poll_interval = interval;
+        self
+    }
+
+    /// Retry policy for failed requests
+    pub fn retry(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Maximum number of events per request (512 by default)
+    pub fn batch_size(mut self, size: usize) -> Self {
+        self.batch.max_items = size;
+        self
+    }
+
+    /// Maximum size of a request body in bytes (1 MiB by default)
+    pub fn batch_bytes(mut self, bytes: usize) -> Self {
+        self.batch.max_bytes = bytes;
+        self
+    }
+
+    /// Maximum time an event waits before its batch is sent (1s by default)
+    pub fn batch_timeout(mut self, timeout: Duration) -> Self {
+        self.batch.max_latency = timeout;
+        self
+    }
+
+    /// Timeout for a single request (10s by default)
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Start the sender and create the hook
+    pub fn build(self) -> Result<SplunkHook, LoggixError> {
+        let client = reqwest::Client::builder()
+            .timeout(self.timeout)
+            .build()
+            .map_err(LoggixError::config)?;
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(
+            reqwest::header::AUTHORIZATION,
+            reqwest::header::HeaderValue::from_str(&format!("Splunk {}", self.token))
+                .map_err(LoggixError::config)?,
+        );
+        let channel = match self.channel {
+            Some(channel) => Some(channel),
+            None if self.acknowledgements => Some(uuid::Uuid::new_v4().to_string()),
+            None => None,
+        };
+        if let Some(channel) = &channel {
+            headers.insert(
+                "X-Splunk-Request-Channel",
+                reqwest::header::HeaderValue::from_str(channel).map_err(LoggixError::config)?,
+            );
+        }
+
+        let base = self.url.trim_end_matches('/');
+        let base = base.strip_suffix(EVENT_PATH).unwrap_or(base);
+        let sender = HecSender {
+            client,
+            event_url: format!("{}{}", base, EVENT_PATH),
+            ack_url: format!("{}{}", base, ACK_PATH),
+            headers,
+            acknowledgements: self.acknowledgements,
+            ack_timeout: self.ack_timeout,
+            ack_poll_interval: self.ack_poll_interval,
+            backoff: self.backoff,
+        };
+        let worker = BatchWorker::spawn("SplunkHook", self.batch, sender)?;
+
+        let host = self.host.or_else(|| {
+            hostname::get()
+                .ok()
+                .map(|host| host.to_string_lossy().into_owned())
+        });
+        Ok(SplunkHook {
+            host,
+            source: self.source,
+            sourcetype: self.sourcetype,
+            index: self.index,
+            worker,
+        })
+    }
+}
+
+/// A hook that sends log entries to a Splunk HTTP Event Collector
+pub struct SplunkHook {
+    host: Option<String>,
+    source: Option<String>,
+    sourcetype: Option<String>,
+    index: Option<String>,
+    worker: BatchWorker<String>,
+}
+
+impl SplunkHook {
+    /// Start building a hook for the collector at `url`, e.g.
+    /// `https://splunk:8088`, authenticating with a HEC `token`
+    pub fn builder(url: &str, token: &str) -> SplunkHookBuilder {
+        SplunkHookBuilder {
+            url: url.to_string(),
+            token: token.to_string(),
+            host: None,
+            source: None,
+            sourcetype: None,
+            index: None,
+            channel: None,
+            acknowledgements: false,
+            ack_timeout: Duration::from_secs(60),
+            ack_poll_interval: Duration::from_secs(1),
+            backoff: Backoff::default(),
+            batch: BatchConfig {
+                max_bytes: 1024 * 1024,
+                ..BatchConfig::default()
+            },
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// Send everything queued so far, waiting up to `timeout`
+    pub fn flush(&self, timeout: Duration) -> Result<(), LoggixError> {
+        self.worker.flush(timeout)
+    }
+
+    /// Wrap an entry in the HEC event envelope
+    pub fn to_event(&self, entry: &Entry) -> Value {
+        let mut event = json!({
+            "time": entry.timestamp.timestamp_millis() as f64 / 1000.0,
+            "event": {
+                "message": entry.message,
+                "level": entry.level.to_string().to_lowercase(),
+                "fields": entry.fields,
+            },
+        });
+        for (key, value) in [
+            ("host", &self.host),
+            ("source", &self.source),
+            ("sourcetype", &self.sourcetype),
+            ("index", &self.index),
+        ] {
+            if let Some(value) = value {
+                event[key] = Value::String(value.clone());
+            }
+        }
+        event
+    }
+}
+
+impl Hook for SplunkHook {
+    fn levels(&self) -> Vec<Level> {
+        vec![
+            Level::Trace,
+            Level::Debug,
+            Level::Info,
+            Level::Warn,
+            Level::Error,
+            Level::Fatal,
+            Level::Panic,
+        ]
+    }
+
+    fn fire(&self, entry: &Entry) -> Result<(), LoggixError> {
+        let event = serde_json::to_string(&self.to_event(entry))?;
+        let size = event.len() + 1;
+        self.worker.push(event, size)
+    }
+
+    fn flush(&self, timeout: Duration) -> Result<(), LoggixError> {
+        SplunkHook::flush(self, timeout)
+    }
+
+    fn flush_async(
+        &self,
+        timeout: Duration,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), LoggixError>> + Send + '_>>
+    {
+        Box::pin(self.worker.flush_async(timeout))
+    }
+}
+
+/// Body of a HEC response, e.g. `{"text": "Success", "code": 0, "ackId": 7}`
+#[derive(Debug, Default, Deserialize)]
+struct HecResponse {
+    #[serde(default)]
+    text: String,
+    code: Option<u64>,
+    #[serde(rename = "ackId")]
+    ack_id: Option<u64>,
+}
+
+/// Whether a HEC status code signals a temporary problem
+///
+/// `8` internal error, `9` server busy and `18`-`20` unhealthy collector are
+/// worth retrying; token, index and data format errors are not.
+fn is_retryable_code(code: u64) -> bool {
+    matches!(code, 8 | 9 | 18..=20)
+}
+
+struct HecSender {
+    client: reqwest::Client,
+    event_url: String,
+    ack_url: String,
+    headers: reqwest::header::HeaderMap,
+    acknowledgements: bool,
+    ack_timeout: Duration,
+    ack_poll_interval: Duration,
+    backoff: Backoff,
+}
+
+impl HecSender {
+    /// Send a batch body and return the acknowledgement ID, if any
+    async fn send(&self, body: &str) -> Result<Option<u64>, BoxError> {
+        retry(&self.backoff, || async {
+            let response = match self
+                .client
+                .post(&self.event_url)
+                .headers(self.headers.clone())
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body.to_string())
+                .send()
+                .await
+            {
+                Ok(response) => response,
+                Err(err) => return Attempt::Transient(err.into()),
+            };
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            let hec: HecResponse = serde_json::from_str(&text).unwrap_or_default();
+            if status.is_success() && hec.code.unwrap_or(0) == 0 {
+                return Attempt::Done(hec.ack_id);
+            }
+
+            let err = match hec.code {
+                Some(code) => format!("HEC error {} ({}): {}", code, status, hec.text),
+                None => format!("server responded with {}: {}", status, text.trim()),
+            }
+            .into();
+            let retryable = match hec.code {
+                Some(code) => is_retryable_code(code),
+                None => is_retryable_status(status),
+            };
+            if retryable {
+                Attempt::Transient(err)
+            } else {
+                Attempt::Permanent(err)
+            }
+        })
+        .await
+    }
+
+    /// Poll the ack endpoint until Splunk confirms `ack_id` was indexed
+    async fn wait_for_ack(&self, ack_id: u64) -> Result<(), BoxError> {
+        let deadline = tokio::time::Instant::now() + self.ack_timeout;
+        let body = json!({ "acks": [ack_id] }).to_string();
+        loop {
+            let response = self
+                .client
+                .post(&self.ack_url)
+                .headers(self.headers.clone())
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body.clone())
+                .send()
+                .await;
+            // Poll failures are treated like a pending ack until the deadline
+            if let Ok(response) = response {
+                if response.status().is_success() {
+                    let acks: Value = serde_json::from_slice(&response.bytes().await?)?;
+                    if acks["acks"][ack_id.to_string()] == Value::Bool(true) {
+                        return Ok(());
+                    }
+                }
+            }
+            if tokio::time::Instant::now() + self.ack_poll_interval > deadline {
+                return Err(
+                    format!("ack {} not confirmed within {:?}", ack_id, self.ack_timeout).into(),
+                );
+            }
+            tokio::time::sleep(self.ack_poll_interval).await;
+        }
+    }
+}
+
+impl Export<String> for HecSender {
+    fn export(&self, batch: Vec<String>) -> ExportFuture<'_> {
+        Box::pin(async move {
+            let body = batch.join("\n");
+            let mut retry = 0;
+            loop {
+                let ack_id = self.send(&body).await?;
+                let result = match ack_id {
+                    _ if !self.acknowledgements => return Ok(()),
+                    Some(ack_id) => self.wait_for_ack(ack_id).await,
+                    None => {
+                        return Err("acknowledgements are enabled but HEC returned no ackId".into())
+                    }
+                };
+                // An unconfirmed batch may not have been indexed, so send it
+                // again; Splunk documents this as at-least-once delivery
+                if result.is_ok() || retry >= self.backoff.retries() {
+                    return result;
+                }
+                tokio::time::sleep(self.backoff.delay(retry)).await;
+                retry += 1;
+            }
+        })
+    }
+}
@@ -0,0 +1,717 @@
+//! Durable on-disk spool for hooks whose destination is unavailable.
+//!
+//! [`SpoolHook`] wraps another hook. Entries the wrapped hook fails to
+//! deliver are appended to a write-ahead log in a local directory, and so are
+//! all entries that arrive while older ones are still waiting, so order is
+//! kept. Spooled entries are replayed oldest first once the wrapped hook
+//! accepts entries again, with retries spaced out by a [`Backoff`].
+//!
+//! The log is split into segment files named `<sequence>.wal`. Each record is
+//! a little-endian `u32` length, the CRC-32 of the payload and the entry as
+//! JSON. A `cursor` file records how far replay has got, so entries survive
+//! restarts and are replayed by the next process using the directory; a
+//! record cut short by a crash is discarded when the spool is opened. When
+//! the spool would grow past its disk budget, whole segments are dropped
+//! oldest first.
+
+use crate::backoff::Backoff;
+use crate::batch::{block_on_shared, block_on_thread, BoxError};
+use crate::{Entry, Hook, Level, LoggixError, Record, RECORD_OWNER};
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Size of the length and checksum in front of every record
+const RECORD_HEADER: u64 = 8;
+const CURSOR_FILE: &str = "cursor";
+const SEGMENT_EXTENSION: &str = "wal";
+
+/// Builder for [`SpoolHook`]
+pub struct SpoolHookBuilder {
+    hook: Box<dyn Hook>,
+    dir: PathBuf,
+    segment_size: u64,
+    max_bytes: u64,
+    retry: Backoff,
+    sync: bool,
+}
+
+impl SpoolHookBuilder {
+    /// Start a new segment file once the current one reaches `bytes`
+    /// (8 MiB by default)
+    pub fn segment_size(mut self, bytes: u64) -> Self {
+        self.segment_size = bytes;
+        self
+    }
+
+    /// Disk budget for all segments (256 MiB by default)
+    ///
+    /// When an entry does not fit, the oldest segments are deleted, together
+    /// with the entries in them that were not replayed yet.
+    pub fn max_bytes(mut self, bytes: u64) -> Self {
+        self.max_bytes = bytes;
+        self
+    }
+
+    /// Delays between replay attempts while the wrapped hook keeps failing
+    ///
+    /// Only the delays are used; replay is attempted until it succeeds.
+    pub fn retry(mut self, backoff: Backoff) -> Self {
+        self.retry = backoff;
+        self
+    }
+
+    /// Sync every write to disk before returning (enabled by default)
+    ///
+    /// Without it, entries spooled shortly before a power failure or kernel
+    /// crash may be lost; a crash of the process alone loses nothing.
+    pub fn sync(mut self, enabled: bool) -> Self {
+        self.sync = enabled;
+        self
+    }
+
+    /// Open the spool directory, creating it if needed
+    ///
+    /// Entries left by a previous run are replayed on the next `fire` or
+    /// `flush`.
+    pub fn build(self) -> Result<SpoolHook, LoggixError> {
+        if self.segment_size == 0 {
+            return Err(LoggixError::config("segment size must be positive"));
+        }
+        if self.max_bytes < self.segment_size {
+            return Err(LoggixError::config(
+                "disk budget must be at least one segment",
+            ));
+        }
+        let spool = Spool::open(self.dir, self.segment_size, self.max_bytes, self.sync)?;
+        Ok(SpoolHook {
+            hook: self.hook,
+            retry: self.retry,
+            spool: Mutex::new(spool),
+            replaying: AtomicBool::new(false),
+        })
+    }
+}
+
+/// A hook that keeps entries on disk while the wrapped hook is failing
+///
+/// `fire` succeeds once the entry is delivered or spooled, and only fails
+/// when the spool itself cannot be written. Entries are delivered at least
+/// once: an entry whose delivery succeeded just before a crash may be
+/// replayed after the restart. A spool directory must only be used by one
+/// hook at a time.
+///
+/// Only failures the wrapped hook returns are spooled, so wrap a hook that
+/// delivers before `fire` returns. `BatchingHook` and the network hooks
+/// (`OtlpHook`, `LokiHook`, `ElasticsearchHook`, `SplunkHook`, `FluentHook`,
+/// `HttpHook`) accept an entry once it is queued for their background
+/// exporter; entries that exporter fails to deliver never reach the spool.
+pub struct SpoolHook {
+    hook: Box<dyn Hook>,
+    retry: Backoff,
+    spool: Mutex<Spool>,
+    replaying: AtomicBool,
+}
+
+impl SpoolHook {
+    /// Start building a hook that spools entries for `hook` in `dir`
+    pub fn builder<H: Hook + 'static, P: AsRef<Path>>(hook: H, dir: P) -> SpoolHookBuilder {
+        SpoolHookBuilder {
+            hook: Box::new(hook),
+            dir: dir.as_ref().to_path_buf(),
+            segment_size: 8 * 1024 * 1024,
+            max_bytes: 256 * 1024 * 1024,
+            retry: Backoff::default(),
+            sync: true,
+        }
+    }
+
+    /// Number of spooled entries waiting for replay
+    pub fn pending(&self) -> usize {
+        self.lock().pending
+    }
+
+    /// Number of entries dropped to stay within the disk budget, or because
+    /// their record was corrupted
+    pub fn dropped(&self) -> u64 {
+        self.lock().dropped
+    }
+
+    /// Bytes used by segment files
+    pub fn disk_usage(&self) -> u64 {
+        self.lock().disk_usage()
+    }
+
+    /// Replay spooled entries, then flush the wrapped hook, waiting up to
+    /// `timeout`
+    ///
+    /// Fails if entries are still spooled afterwards; they stay on disk.
+    pub fn flush(&self, timeout: Duration) -> Result<(), LoggixError> {
+        let deadline = Instant::now() + timeout;
+        if self.pending() > 0 {
+            let replay = async { tokio::time::timeout(timeout, self.replay(true)).await };
+            if let Ok(result) =
+                block_on_thread(replay).map_err(|e| LoggixError::hook("SpoolHook", e))?
+            {
+                result.map_err(|e| LoggixError::hook("SpoolHook", e))?;
+            }
+        }
+        self.hook
+            .flush(deadline.saturating_duration_since(Instant::now()))?;
+        self.check_drained()
+    }
+
+    /// Fail if entries are still spooled after a flush
+    fn check_drained(&self) -> Result<(), LoggixError> {
+        let spool = self.lock();
+        match spool.pending {
+            0 => Ok(()),
+            pending => Err(LoggixError::hook(
+                "SpoolHook",
+                format!(
+                    "{} entries remain spooled in {}",
+                    pending,
+                    spool.dir.display()
+                ),
+            )),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Spool> {
+        self.spool
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Spool `record` behind the entries already waiting, returning whether
+    /// there were any
+    ///
+    /// With an empty spool nothing is written and the caller delivers the
+    /// entry itself.
+    fn enqueue(&self, record: &Record) -> io::Result<bool> {
+        let mut spool = self.lock();
+        if spool.pending == 0 {
+            return Ok(false);
+        }
+        spool.append(record)?;
+        Ok(true)
+    }
+
+    /// Spool an entry the wrapped hook failed to deliver
+    fn spool_failed(&self, record: &Record) -> io::Result<()> {
+        let mut spool = self.lock();
+        spool.append(record)?;
+        spool.failed(&self.retry);
+        Ok(())
+    }
+
+    /// Deliver spooled entries in order until one fails or none are left
+    ///
+    /// Only one replay runs at a time; callers that find one in progress
+    /// return immediately. Unless `force` is set, nothing is attempted
+    /// before the backoff delay after the last failure has passed.
+    async fn replay(&self, force: bool) -> Result<(), BoxError> {
+        let Some(_guard) = ReplayGuard::acquire(&self.replaying) else {
+            return Ok(());
+        };
+        loop {
+            let next = {
+                let mut spool = self.lock();
+                if !force && !spool.due() {
+                    return Ok(());
+                }
+                spool.peek()?
+            };
+            let Some((record, sequence, end)) = next else {
+                return Ok(());
+            };
+            let delivered = self
+                .hook
+                .fire_async(&record.to_entry(&RECORD_OWNER))
+                .await
+                .is_ok();
+            let mut spool = self.lock();
+            if !delivered {
+                spool.failed(&self.retry);
+                return Ok(());
+            }
+            spool.ack(sequence, end)?;
+        }
+    }
+
+    /// Like [`replay`](Self::replay), delivering with the wrapped hook's
+    /// `fire`
+    fn replay_blocking(&self) -> io::Result<()> {
+        let Some(_guard) = ReplayGuard::acquire(&self.replaying) else {
+            return Ok(());
+        };
+        loop {
+            let next = {
+                let mut spool = self.lock();
+                if !spool.due() {
+                    return Ok(());
+                }
+                spool.peek()?
+            };
+            let Some((record, sequence, end)) = next else {
+                return Ok(());
+            };
+            let delivered = self.hook.fire(&record.to_entry(&RECORD_OWNER)).is_ok();
+            let mut spool = self.lock();
+            if !delivered {
+                spool.failed(&self.retry);
+                return Ok(());
+            }
+            spool.ack(sequence, end)?;
+        }
+    }
+}
+
+impl Hook for SpoolHook {
+    fn levels(&self) -> Vec<Level> {
+        self.hook.levels()
+    }
+
+    fn requires_async(&self) -> bool {
+        self.hook.requires_async()
+    }
+
+    fn fire(&self, entry: &Entry) -> Result<(), LoggixError> {
+        if self.hook.requires_async() {
+            // Delivery and replay both need the wrapped hook's `fire_async`
+            return block_on_shared(self.fire_async(entry))
+                .map_err(|e| LoggixError::hook("SpoolHook", e))
+                .and_then(|result| result);
+        }
+        let record = Record::from(entry);
+        if self.enqueue(&record)? {
+            self.replay_blocking()?;
+        } else if self.hook.fire(entry).is_err() {
+            self.spool_failed(&record)?;
+        }
+        Ok(())
+    }
+
+    fn fire_async<'a>(
+        &'a self,
+        entry: &'a Entry,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), LoggixError>> + Send + 'a>>
+    {
+        Box::pin(async move {
+            let record = Record::from(entry);
+            if self.enqueue(&record)? {
+                self.replay(false)
+                    .await
+                    .map_err(|e| LoggixError::hook("SpoolHook", e))?;
+            } else if self.hook.fire_async(entry).await.is_err() {
+                self.spool_failed(&record)?;
+            }
+            Ok(())
+        })
+    }
+
+    fn flush(&self, timeout: Duration) -> Result<(), LoggixError> {
+        SpoolHook::flush(self, timeout)
+    }
+
+    fn flush_async(
+        &self,
+        timeout: Duration,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), LoggixError>> + Send + '_>>
+    {
+        Box::pin(async move {
+            let deadline = Instant::now() + timeout;
+            if self.pending() > 0 {
+                if let Ok(result) = tokio::time::timeout(timeout, self.replay(true)).await {
+                    result.map_err(|e| LoggixError::hook("SpoolHook", e))?;
+                }
+            }
+            self.hook
+                .flush_async(deadline.saturating_duration_since(Instant::now()))
+                .await?;
+            self.check_drained()
+        })
+    }
+}
+
+/// Marks a replay in progress until dropped
+struct ReplayGuard<'a>(&'a AtomicBool);
+
+impl<'a> ReplayGuard<'a> {
+    fn acquire(flag: &'a AtomicBool) -> Option<Self> {
+        flag.compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .ok()
+            .map(|_| ReplayGuard(flag))
+    }
+}
+
+impl Drop for ReplayGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+/// A segment file and the entries in it not yet replayed
+struct Segment {
+    sequence: u64,
+    size: u64,
+    entries: usize,
+}
+
+/// The write-ahead log in a spool directory
+///
+/// Replay reads from the front segment at `read_offset`; appends go to the
+/// back segment through `writer`.
+struct Spool {
+    dir: PathBuf,
+    segment_size: u64,
+    max_bytes: u64,
+    sync: bool,
+    segments: VecDeque<Segment>,
+    read_offset: u64,
+    next_sequence: u64,
+    writer: Option<File>,
+    reader: Option<(u64, File)>,
+    pending: usize,
+    dropped: u64,
+    failures: u32,
+    retry_at: Option<Instant>,
+}
+
+impl Spool {
+    fn open(dir: PathBuf, segment_size: u64, max_bytes: u64, sync: bool) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        let mut sequences = Vec::new();
+        for file in fs::read_dir(&dir)? {
+            let path = file?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(SEGMENT_EXTENSION) {
+                continue;
+            }
+            if let Some(sequence) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.parse::<u64>().ok())
+            {
+                sequences.push(sequence);
+            }
+        }
+        sequences.sort_unstable();
+
+        let mut spool = Spool {
+            segment_size,
+            max_bytes,
+            sync,
+            segments: VecDeque::new(),
+            read_offset: 0,
+            next_sequence: sequences.last().map_or(0, |last| last + 1),
+            writer: None,
+            reader: None,
+            pending: 0,
+            dropped: 0,
+            failures: 0,
+            retry_at: None,
+            dir,
+        };
+        // A cursor pointing at a missing segment, or into the middle of a
+        // record, is ignored and everything left is replayed
+        let cursor = spool.read_cursor();
+        for sequence in sequences {
+            let path = spool.segment_path(sequence);
+            let start = match cursor {
+                Some((cursor_sequence, _)) if sequence < cursor_sequence => {
+                    fs::remove_file(&path)?;
+                    continue;
+                }
+                Some((cursor_sequence, offset)) if sequence == cursor_sequence => offset,
+                _ => 0,
+            };
+            let (size, offsets) = scan_segment(&path)?;
+            let start = if start == size || offsets.contains(&start) {
+                start
+            } else {
+                0
+            };
+            let entries = offsets.iter().filter(|&&offset| offset >= start).count();
+            if spool.segments.is_empty() {
+                spool.read_offset = start;
+            }
+            spool.pending += entries;
+            spool.segments.push_back(Segment {
+                sequence,
+                size,
+                entries,
+            });
+        }
+        if spool.segments.is_empty() || spool.pending == 0 {
+            spool.reset()?;
+        }
+        Ok(spool)
+    }
+
+    fn segment_path(&self, sequence: u64) -> PathBuf {
+        self.dir
+            .join(format!("{:020}.{}", sequence, SEGMENT_EXTENSION))
+    }
+
+    fn disk_usage(&self) -> u64 {
+        self.segments.iter().map(|segment| segment.size).sum()
+    }
+
+    /// Whether the backoff delay after the last failure has passed
+    fn due(&self) -> bool {
+        self.retry_at
+            .is_none_or(|retry_at| Instant::now() >= retry_at)
+    }
+
+    fn failed(&mut self, retry: &Backoff) {
+        self.retry_at = Some(Instant::now() + retry.delay(self.failures));
+        self.failures = self.failures.saturating_add(1);
+    }
+
+    fn append(&mut self, record: &Record) -> io::Result<()> {
+        let payload = serde_json::to_vec(record)?;
+        let length = u32::try_from(payload.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "entry too large"))?;
+        let needed = RECORD_HEADER + payload.len() as u64;
+        if needed > self.max_bytes {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "entry larger than the spool's disk budget",
+            ));
+        }
+        while !self.segments.is_empty() && self.disk_usage() + needed > self.max_bytes {
+            self.drop_oldest()?;
+        }
+
+        let rotate = match (&self.writer, self.segments.back()) {
+            (Some(_), Some(last)) => last.size > 0 && last.size + needed > self.segment_size,
+            _ => true,
+        };
+        if rotate {
+            let sequence = self.next_sequence;
+            self.next_sequence += 1;
+            let file = OpenOptions::new()
+                .create_new(true)
+                .append(true)
+                .open(self.segment_path(sequence))?;
+            if self.segments.is_empty() {
+                self.read_offset = 0;
+            }
+            self.segments.push_back(Segment {
+                sequence,
+                size: 0,
+                entries: 0,
+            });
+            self.writer = Some(file);
+        }
+
+        let mut buffer = Vec::with_capacity(needed as usize);
+        buffer.extend_from_slice(&length.to_le_bytes());
+        buffer.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+        buffer.extend_from_slice(&payload);
+        let writer = self.writer.as_mut().expect("writer opened above");
+        writer.write_all(&buffer)?;
+        if self.sync {
+            writer.sync_data()?;
+        }
+        let last = self.segments.back_mut().expect("segment added above");
+        last.size += needed;
+        last.entries += 1;
+        self.pending += 1;
+        Ok(())
+    }
+
+    /// The oldest spooled record, the sequence of its segment and the offset
+    /// just past it
+    fn peek(&mut self) -> io::Result<Option<(Record, u64, u64)>> {
+        loop {
+            let Some(front) = self.segments.front() else {
+                return Ok(None);
+            };
+            let sequence = front.sequence;
+            if self.read_offset >= front.size {
+                if self.segments.len() == 1 {
+                    return Ok(None);
+                }
+                self.remove_front()?;
+                continue;
+            }
+
+            if self.reader.as_ref().map(|(s, _)| *s) != Some(sequence) {
+                self.reader = Some((sequence, File::open(self.segment_path(sequence))?));
+            }
+            let (_, file) = self.reader.as_mut().expect("reader opened above");
+            file.seek(SeekFrom::Start(self.read_offset))?;
+            match read_record(file)? {
+                Some((record, length)) => {
+                    return Ok(Some((record, sequence, self.read_offset + length)))
+                }
+                None => {
+                    // Corrupted after the spool was opened; skip the rest of
+                    // the segment
+                    let front = self.segments.front_mut().expect("checked above");
+                    self.dropped += front.entries as u64;
+                    self.pending -= front.entries;
+                    front.entries = 0;
+                    self.read_offset = front.size;
+                    if self.pending == 0 {
+                        self.reset()?;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Mark the record ending at `end` in segment `sequence` as delivered
+    ///
+    /// The lock is not held during delivery, so the segment may have been
+    /// dropped to stay within the disk budget in the meantime. The record
+    /// was then already accounted for, except that it was not lost after all.
+    fn ack(&mut self, sequence: u64, end: u64) -> io::Result<()> {
+        self.failures = 0;
+        self.retry_at = None;
+        let Some(front) = self
+            .segments
+            .front_mut()
+            .filter(|front| front.sequence == sequence)
+        else {
+            self.dropped = self.dropped.saturating_sub(1);
+            return Ok(());
+        };
+        front.entries -= 1;
+        self.pending -= 1;
+        self.read_offset = end;
+        if self.pending == 0 {
+            return self.reset();
+        }
+        if end >= front.size && self.segments.len() > 1 {
+            self.remove_front()
+        } else {
+            self.write_cursor()
+        }
+    }
+
+    /// Delete the front segment, which has been replayed
+    fn remove_front(&mut self) -> io::Result<()> {
+        if let Some(front) = self.segments.pop_front() {
+            if self.reader.as_ref().map(|(s, _)| *s) == Some(front.sequence) {
+                self.reader = None;
+            }
+            if self.segments.is_empty() {
+                self.writer = None;
+            }
+            fs::remove_file(self.segment_path(front.sequence))?;
+        }
+        self.read_offset = 0;
+        self.write_cursor()
+    }
+
+    /// Delete the oldest segment to make room, with its pending entries
+    fn drop_oldest(&mut self) -> io::Result<()> {
+        if let Some(front) = self.segments.front() {
+            self.dropped += front.entries as u64;
+            self.pending -= front.entries;
+        }
+        self.remove_front()
+    }
+
+    /// Delete every segment once all entries are replayed
+    fn reset(&mut self) -> io::Result<()> {
+        self.writer = None;
+        self.reader = None;
+        while let Some(segment) = self.segments.pop_front() {
+            fs::remove_file(self.segment_path(segment.sequence))?;
+        }
+        self.read_offset = 0;
+        self.pending = 0;
+        self.write_cursor()
+    }
+
+    /// Persist the replay position, replacing the cursor file atomically
+    fn write_cursor(&self) -> io::Result<()> {
+        let Some(front) = self.segments.front() else {
+            return match fs::remove_file(self.dir.join(CURSOR_FILE)) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+                _ => Ok(()),
+            };
+        };
+        let mut cursor = Vec::with_capacity(20);
+        cursor.extend_from_slice(&front.sequence.to_le_bytes());
+        cursor.extend_from_slice(&self.read_offset.to_le_bytes());
+        cursor.extend_from_slice(&crc32fast::hash(&cursor).to_le_bytes());
+
+        let temporary = self.dir.join(format!("{}.tmp", CURSOR_FILE));
+        let mut file = File::create(&temporary)?;
+        file.write_all(&cursor)?;
+        if self.sync {
+            file.sync_data()?;
+        }
+        fs::rename(temporary, self.dir.join(CURSOR_FILE))
+    }
+
+    /// The saved replay position, if the cursor file is intact
+    fn read_cursor(&self) -> Option<(u64, u64)> {
+        let cursor = fs::read(self.dir.join(CURSOR_FILE)).ok()?;
+        if cursor.len() != 20 {
+            return None;
+        }
+        let (data, checksum) = cursor.split_at(16);
+        if crc32fast::hash(data).to_le_bytes() != checksum {
+            return None;
+        }
+        let sequence = u64::from_le_bytes(data[..8].try_into().ok()?);
+        let offset = u64::from_le_bytes(data[8..].try_into().ok()?);
+        Some((sequence, offset))
+    }
+}
+
+/// Read the record at the current position, with its length on disk
+///
+/// A truncated record, checksum mismatch or unparsable payload reads as
+/// `None`.
+fn read_record(file: &mut File) -> io::Result<Option<(Record, u64)>> {
+    let mut header = [0u8; RECORD_HEADER as usize];
+    if let Err(err) = file.read_exact(&mut header) {
+        return match err.kind() {
+            io::ErrorKind::UnexpectedEof => Ok(None),
+            _ => Err(err),
+        };
+    }
+    let length = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+    let checksum = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+    let mut payload = Vec::new();
+    file.take(u64::from(length)).read_to_end(&mut payload)?;
+    if payload.len() != length as usize || crc32fast::hash(&payload) != checksum {
+        return Ok(None);
+    }
+    Ok(serde_json::from_slice(&payload)
+        .ok()
+        .map(|record| (record, RECORD_HEADER + u64::from(length))))
+}
+
+/// Find the records in a segment, truncating it after the last intact one
+///
+/// Returns the resulting size and the offset of every record.
+fn scan_segment(path: &Path) -> io::Result<(u64, Vec<u64>)> {
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    let size = file.metadata()?.len();
+    let mut offsets = Vec::new();
+    let mut offset = 0;
+    while offset < size {
+        match read_record(&mut file)? {
+            Some((_, length)) => {
+                offsets.push(offset);
+                offset += length;
+            }
+            None => {
+                file.set_len(offset)?;
+                break;
+            }
+        }
+    }
+    Ok((offset, offsets))
+}
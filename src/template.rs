@@ -0,0 +1,49 @@
+//! `{field}` templates shared by hooks that build names from entries, such
+//! as Kafka topics and keys or Fluentd tags.
+
+use crate::LoggixError;
+
+/// A piece of a compiled template
+pub(crate) enum TemplatePart {
+    Text(String),
+    /// `{level}`, the lowercase level
+    Level,
+    /// Any other `{name}`, the value of that field
+    Field(String),
+}
+
+/// Split a template into text and placeholders; `kind` names the template
+/// in error messages, e.g. `"topic"`
+pub(crate) fn compile_template(
+    template: &str,
+    kind: &str,
+) -> Result<Vec<TemplatePart>, LoggixError> {
+    let mut parts = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..].find('}').ok_or_else(|| {
+            LoggixError::config(format!(
+                "unclosed placeholder in {} template: {}",
+                kind, template
+            ))
+        })?;
+        if start > 0 {
+            parts.push(TemplatePart::Text(rest[..start].to_string()));
+        }
+        match rest[start + 1..start + end].trim() {
+            "" => {
+                return Err(LoggixError::config(format!(
+                    "empty placeholder in {} template: {}",
+                    kind, template
+                )))
+            }
+            "level" => parts.push(TemplatePart::Level),
+            name => parts.push(TemplatePart::Field(name.to_string())),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    if !rest.is_empty() {
+        parts.push(TemplatePart::Text(rest.to_string()));
+    }
+    Ok(parts)
+}
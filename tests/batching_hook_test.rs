@@ -0,0 +1,190 @@
+use loggix::{BatchingHook, Entry, Fields, Hook, Level, Logger, LoggixError, Record};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A hook that records the batches it receives
+#[derive(Clone, Default)]
+struct BatchRecorder {
+    batches: Arc<Mutex<Vec<Vec<String>>>>,
+}
+
+impl BatchRecorder {
+    fn sizes(&self) -> Vec<usize> {
+        self.batches.lock().unwrap().iter().map(Vec::len).collect()
+    }
+}
+
+impl Hook for BatchRecorder {
+    fn levels(&self) -> Vec<Level> {
+        vec![Level::Info, Level::Warn]
+    }
+
+    fn fire(&self, entry: &Entry) -> Result<(), LoggixError> {
+        self.fire_batch(&[Record::from(entry)])
+    }
+
+    fn fire_batch(&self, records: &[Record]) -> Result<(), LoggixError> {
+        let messages = records.iter().map(|r| r.message.clone()).collect();
+        self.batches.lock().unwrap().push(messages);
+        Ok(())
+    }
+}
+
+/// A hook that only handles single entries, failing on `fail`
+#[derive(Clone, Default)]
+struct EntryRecorder {
+    messages: Arc<Mutex<Vec<String>>>,
+}
+
+impl Hook for EntryRecorder {
+    fn levels(&self) -> Vec<Level> {
+        vec![Level::Info]
+    }
+
+    fn fire(&self, entry: &Entry) -> Result<(), LoggixError> {
+        if entry.message == "fail" {
+            return Err(LoggixError::hook("PickyHook", "rejected"));
+        }
+        self.messages.lock().unwrap().push(entry.message.clone());
+        Ok(())
+    }
+}
+
+/// A hook that can only deliver through `fire_async`
+#[derive(Clone, Default)]
+struct AsyncOnlyRecorder(EntryRecorder);
+
+impl Hook for AsyncOnlyRecorder {
+    fn levels(&self) -> Vec<Level> {
+        self.0.levels()
+    }
+
+    fn requires_async(&self) -> bool {
+        true
+    }
+
+    fn fire(&self, _entry: &Entry) -> Result<(), LoggixError> {
+        Err(LoggixError::hook("AsyncOnlyRecorder", "use fire_async"))
+    }
+
+    fn fire_async<'a>(
+        &'a self,
+        entry: &'a Entry,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), LoggixError>> + Send + 'a>>
+    {
+        Box::pin(async move { self.0.fire(entry) })
+    }
+}
+
+fn entry<'a>(logger: &'a Logger, message: &str) -> Entry<'a> {
+    Entry {
+        timestamp: chrono::Utc::now(),
+        level: Level::Info,
+        message: message.to_string(),
+        fields: Fields::new(),
+        logger,
+    }
+}
+
+#[test]
+fn test_batches_by_count() {
+    let recorder = BatchRecorder::default();
+    let hook = BatchingHook::builder(recorder.clone())
+        .batch_size(3)
+        .batch_timeout(Duration::from_secs(60))
+        .build()
+        .unwrap();
+    assert_eq!(hook.levels(), vec![Level::Info, Level::Warn]);
+
+    let logger = Logger::new();
+    for i in 0..7 {
+        hook.fire(&entry(&logger, &format!("entry {}", i))).unwrap();
+    }
+    hook.flush(TIMEOUT).unwrap();
+    assert_eq!(recorder.sizes(), vec![3, 3, 1]);
+    assert_eq!(recorder.batches.lock().unwrap()[2], vec!["entry 6"]);
+}
+
+#[test]
+fn test_batches_by_bytes() {
+    let recorder = BatchRecorder::default();
+    let hook = BatchingHook::builder(recorder.clone())
+        .batch_bytes(400)
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    for _ in 0..4 {
+        hook.fire(&entry(&logger, &"x".repeat(100))).unwrap();
+    }
+    hook.flush(TIMEOUT).unwrap();
+    // Each entry is about 180 bytes as JSON, so two fit in a batch
+    assert_eq!(recorder.sizes(), vec![2, 2]);
+}
+
+#[test]
+fn test_batches_by_latency() {
+    let recorder = BatchRecorder::default();
+    let hook = BatchingHook::builder(recorder.clone())
+        .batch_timeout(Duration::from_millis(50))
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    hook.fire(&entry(&logger, "first")).unwrap();
+    hook.fire(&entry(&logger, "second")).unwrap();
+    std::thread::sleep(Duration::from_millis(500));
+    assert_eq!(recorder.sizes(), vec![2]);
+}
+
+#[test]
+fn test_per_entry_fallback() {
+    let recorder = EntryRecorder::default();
+    let hook = BatchingHook::builder(recorder.clone()).build().unwrap();
+
+    let logger = Logger::new();
+    for message in ["one", "fail", "two"] {
+        hook.fire(&entry(&logger, message)).unwrap();
+    }
+    let err = hook.flush(TIMEOUT).unwrap_err();
+    assert!(err.to_string().contains("1 of 3 entries failed: rejected"));
+    assert_eq!(*recorder.messages.lock().unwrap(), vec!["one", "two"]);
+
+    // The failure is reported once
+    hook.flush(TIMEOUT).unwrap();
+}
+
+#[test]
+fn test_async_only_hook_gets_entries_through_fire_async() {
+    let recorder = AsyncOnlyRecorder::default();
+    let hook = BatchingHook::builder(recorder.clone()).build().unwrap();
+    assert!(hook.requires_async());
+
+    let logger = Logger::new();
+    for message in ["one", "fail", "two"] {
+        hook.fire(&entry(&logger, message)).unwrap();
+    }
+    let err = hook.flush(TIMEOUT).unwrap_err();
+    assert!(err.to_string().contains("1 of 3 entries failed: rejected"));
+    assert_eq!(*recorder.0.messages.lock().unwrap(), vec!["one", "two"]);
+}
+
+#[test]
+fn test_dropping_the_logger_delivers_queued_entries() {
+    let recorder = BatchRecorder::default();
+    let hook = BatchingHook::builder(recorder.clone())
+        .batch_timeout(Duration::from_secs(60))
+        .build()
+        .unwrap();
+
+    let logger = Logger::new().output(std::io::sink()).add_hook(hook);
+    logger.log(Level::Info, "queued", Fields::new()).unwrap();
+    logger
+        .log(Level::Warn, "also queued", Fields::new())
+        .unwrap();
+    assert!(recorder.sizes().is_empty());
+    drop(logger);
+    assert_eq!(recorder.sizes(), vec![2]);
+}
@@ -0,0 +1,135 @@
+use loggix::{
+    CircuitBreakerHook, CircuitOpen, CircuitState, Entry, Fields, Hook, Level, Logger, LoggixError,
+    PermanentError, RetryHook,
+};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::time::Duration;
+
+/// A hook that fails while `down` is set and counts its calls
+#[derive(Default)]
+struct Destination {
+    down: AtomicBool,
+    reject: AtomicBool,
+    calls: AtomicU32,
+}
+
+impl Destination {
+    fn down() -> Self {
+        let destination = Self::default();
+        destination.down.store(true, Ordering::SeqCst);
+        destination
+    }
+
+    fn calls(&self) -> u32 {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+impl Hook for Destination {
+    fn levels(&self) -> Vec<Level> {
+        vec![Level::Info]
+    }
+
+    fn fire(&self, _entry: &Entry) -> Result<(), LoggixError> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        if self.reject.load(Ordering::SeqCst) {
+            Err(LoggixError::hook(
+                "Destination",
+                PermanentError::new("invalid payload"),
+            ))
+        } else if self.down.load(Ordering::SeqCst) {
+            Err(LoggixError::hook("Destination", "connection timed out"))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+fn entry(logger: &Logger) -> Entry<'_> {
+    Entry {
+        timestamp: chrono::Utc::now(),
+        level: Level::Info,
+        message: "request served".to_string(),
+        fields: Fields::new(),
+        logger,
+    }
+}
+
+#[test]
+fn test_opens_after_consecutive_failures() {
+    let hook = CircuitBreakerHook::new(Destination::down())
+        .failure_threshold(3)
+        .cool_down(Duration::from_secs(60));
+    let logger = Logger::new();
+
+    for _ in 0..3 {
+        let err = hook.fire(&entry(&logger)).unwrap_err();
+        assert_eq!(err.to_string(), "Destination: connection timed out");
+    }
+    assert_eq!(hook.state(), CircuitState::Open);
+
+    // Rejected without calling the destination
+    let err = hook.fire(&entry(&logger)).unwrap_err();
+    let LoggixError::Hook { name, source } = err else {
+        panic!("expected a hook error");
+    };
+    assert_eq!(name, "CircuitBreakerHook");
+    let open = source.downcast_ref::<CircuitOpen>().unwrap();
+    assert!(open.retry_in > Duration::from_secs(50));
+    assert_eq!(hook.inner().calls(), 3);
+}
+
+#[test]
+fn test_probes_after_cool_down() {
+    let hook = CircuitBreakerHook::new(Destination::down())
+        .failure_threshold(1)
+        .success_threshold(2)
+        .cool_down(Duration::from_millis(20));
+    let logger = Logger::new();
+
+    assert!(hook.fire(&entry(&logger)).is_err());
+    assert_eq!(hook.state(), CircuitState::Open);
+    std::thread::sleep(Duration::from_millis(30));
+    assert_eq!(hook.state(), CircuitState::HalfOpen);
+
+    // A failed probe reopens the circuit
+    assert!(hook.fire(&entry(&logger)).is_err());
+    assert_eq!(hook.state(), CircuitState::Open);
+    assert_eq!(hook.inner().calls(), 2);
+
+    hook.inner().down.store(false, Ordering::SeqCst);
+    std::thread::sleep(Duration::from_millis(30));
+    hook.fire(&entry(&logger)).unwrap();
+    assert_eq!(hook.state(), CircuitState::HalfOpen);
+    hook.fire(&entry(&logger)).unwrap();
+    assert_eq!(hook.state(), CircuitState::Closed);
+}
+
+#[test]
+fn test_permanent_errors_do_not_open_the_circuit() {
+    let destination = Destination::default();
+    destination.reject.store(true, Ordering::SeqCst);
+    let hook = CircuitBreakerHook::new(destination).failure_threshold(2);
+    let logger = Logger::new();
+
+    for _ in 0..5 {
+        assert!(hook.fire(&entry(&logger)).is_err());
+    }
+    assert_eq!(hook.state(), CircuitState::Closed);
+    assert_eq!(hook.inner().calls(), 5);
+}
+
+#[tokio::test]
+async fn test_open_circuit_is_not_retried() {
+    let hook = RetryHook::new(
+        CircuitBreakerHook::new(Destination::down())
+            .failure_threshold(1)
+            .cool_down(Duration::from_secs(60)),
+    );
+    let logger = Logger::new();
+
+    // The first failure opens the circuit, and the retry stops on it
+    let err = hook.fire_async(&entry(&logger)).await.unwrap_err();
+    assert!(err.to_string().contains("circuit open"));
+    assert_eq!(hook.inner().inner().calls(), 1);
+}
@@ -0,0 +1,115 @@
+use chrono::{TimeZone, Utc};
+use loggix::{
+    cloud_logging_severity, CloudLoggingFormatter, Entry, Fields, Formatter, Level, Logger,
+};
+use serde_json::json;
+
+fn format_entry(
+    formatter: &CloudLoggingFormatter,
+    level: Level,
+    msg: &str,
+    fields: Fields,
+) -> String {
+    let logger = Logger::new();
+    let entry = Entry {
+        timestamp: Utc.with_ymd_and_hms(2025, 4, 6, 10, 30, 0).unwrap()
+            + chrono::Duration::milliseconds(250),
+        level,
+        message: msg.to_string(),
+        fields,
+        logger: &logger,
+    };
+    String::from_utf8(formatter.format(&entry).unwrap()).unwrap()
+}
+
+#[test]
+fn test_cloud_logging_basic_golden() {
+    let mut fields = Fields::new();
+    fields.insert("port".to_string(), json!(8080));
+    fields.insert("env".to_string(), json!("prod"));
+
+    let output = format_entry(
+        &CloudLoggingFormatter::new(),
+        Level::Info,
+        "server started",
+        fields,
+    );
+    assert_eq!(output, include_str!("golden/cloud_logging/basic.json"));
+}
+
+#[test]
+fn test_cloud_logging_trace_golden() {
+    let mut fields = Fields::new();
+    fields.insert(
+        "trace_id".to_string(),
+        json!("4bf92f3577b34da6a3ce929d0e0e4736"),
+    );
+    fields.insert("span_id".to_string(), json!("00f067aa0ba902b7"));
+    fields.insert("trace_sampled".to_string(), json!(true));
+    fields.insert("order_id".to_string(), json!(42));
+
+    let output = format_entry(
+        &CloudLoggingFormatter::new().project_id("my-project"),
+        Level::Error,
+        "payment failed",
+        fields,
+    );
+    assert_eq!(output, include_str!("golden/cloud_logging/trace.json"));
+}
+
+#[test]
+fn test_cloud_logging_source_location_golden() {
+    let mut fields = Fields::new();
+    fields.insert("file".to_string(), json!("src/db.rs"));
+    fields.insert("line".to_string(), json!(42));
+    fields.insert("function".to_string(), json!("db::query"));
+    fields.insert("duration_ms".to_string(), json!(1200));
+
+    let output = format_entry(
+        &CloudLoggingFormatter::new(),
+        Level::Warn,
+        "slow query",
+        fields,
+    );
+    assert_eq!(
+        output,
+        include_str!("golden/cloud_logging/source_location.json")
+    );
+}
+
+#[test]
+fn test_cloud_logging_http_request_golden() {
+    let mut fields = Fields::new();
+    fields.insert(
+        "httpRequest".to_string(),
+        json!({
+            "requestMethod": "GET",
+            "requestUrl": "/health",
+            "status": 200,
+            "latency": "0.003s"
+        }),
+    );
+    fields.insert("severity".to_string(), json!("custom"));
+
+    let output = format_entry(
+        &CloudLoggingFormatter::new(),
+        Level::Info,
+        "request handled",
+        fields,
+    );
+    assert_eq!(
+        output,
+        include_str!("golden/cloud_logging/http_request.json")
+    );
+}
+
+#[test]
+fn test_cloud_logging_severity_mapping() {
+    assert_eq!(cloud_logging_severity(Level::Trace), "DEBUG");
+    assert_eq!(cloud_logging_severity(Level::Debug), "DEBUG");
+    assert_eq!(cloud_logging_severity(Level::Info), "INFO");
+    assert_eq!(cloud_logging_severity(Level::Warn), "WARNING");
+    assert_eq!(cloud_logging_severity(Level::Error), "ERROR");
+    assert_eq!(cloud_logging_severity(Level::Fatal), "CRITICAL");
+    assert_eq!(cloud_logging_severity(Level::Panic), "EMERGENCY");
+}
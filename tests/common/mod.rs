@@ -0,0 +1,157 @@
+//! A minimal HTTP server standing in for collectors and log backends
+
+#![allow(dead_code)]
+
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A request received by the mock server
+#[derive(Debug, Clone)]
+pub struct CapturedRequest {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl CapturedRequest {
+    /// Look up a header by case-insensitive name
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn body_str(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+/// HTTP server that records requests and replies with queued responses
+///
+/// Responses default to `200 OK` with an empty body once the queue is empty.
+pub struct MockServer {
+    port: u16,
+    requests: Arc<Mutex<Vec<CapturedRequest>>>,
+    responses: Arc<Mutex<VecDeque<(u16, String)>>>,
+}
+
+impl MockServer {
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let responses = Arc::new(Mutex::new(VecDeque::new()));
+
+        let server_requests = Arc::clone(&requests);
+        let server_responses = Arc::clone(&responses);
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else { break };
+                let requests = Arc::clone(&server_requests);
+                let responses = Arc::clone(&server_responses);
+                std::thread::spawn(move || handle(stream, requests, responses));
+            }
+        });
+
+        Self {
+            port,
+            requests,
+            responses,
+        }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://127.0.0.1:{}{}", self.port, path)
+    }
+
+    /// Queue a response for the next unanswered request
+    pub fn respond_with(&self, status: u16, body: &str) {
+        self.responses
+            .lock()
+            .unwrap()
+            .push_back((status, body.to_string()));
+    }
+
+    pub fn requests(&self) -> Vec<CapturedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Wait until at least `count` requests arrived or the timeout passed
+    pub fn wait_for_requests(&self, count: usize, timeout: Duration) -> Vec<CapturedRequest> {
+        let deadline = Instant::now() + timeout;
+        while self.requests.lock().unwrap().len() < count && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        self.requests()
+    }
+}
+
+fn handle(
+    stream: TcpStream,
+    requests: Arc<Mutex<Vec<CapturedRequest>>>,
+    responses: Arc<Mutex<VecDeque<(u16, String)>>>,
+) {
+    let mut reader = BufReader::new(stream);
+    loop {
+        let mut request_line = String::new();
+        if reader.read_line(&mut request_line).unwrap_or(0) == 0 {
+            return;
+        }
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or_default().to_string();
+        let path = parts.next().unwrap_or_default().to_string();
+
+        let mut headers = Vec::new();
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                return;
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                headers.push((name.trim().to_lowercase(), value.trim().to_string()));
+            }
+        }
+
+        let length = headers
+            .iter()
+            .find(|(name, _)| name == "content-length")
+            .and_then(|(_, value)| value.parse::<usize>().ok())
+            .unwrap_or(0);
+        let mut body = vec![0; length];
+        if reader.read_exact(&mut body).is_err() {
+            return;
+        }
+
+        // Record before replying so callers that saw the response also see the request
+        requests.lock().unwrap().push(CapturedRequest {
+            method,
+            path,
+            headers,
+            body,
+        });
+
+        let (status, body) = responses
+            .lock()
+            .unwrap()
+            .pop_front()
+            .unwrap_or((200, String::new()));
+        let response = format!(
+            "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+        if reader.get_mut().write_all(response.as_bytes()).is_err() {
+            return;
+        }
+    }
+}
@@ -1,6 +1,5 @@
-use loggix::{Entry, Formatter, Level, Logger};
+use loggix::{Entry, Formatter, Level, Logger, LoggixError};
 use serde_json::Value;
-use std::error::Error;
 use std::io::Write;
 use std::sync::{Arc, Mutex};
 
@@ -46,7 +45,7 @@ impl TestFormatter {
 }
 
 impl Formatter for TestFormatter {
-    fn format(&self, entry: &Entry) -> Result<Vec<u8>, Box<dyn Error>> {
+    fn format(&self, entry: &Entry) -> Result<Vec<u8>, LoggixError> {
         let mut output = Vec::new();
         write!(
             output,
@@ -113,8 +112,8 @@ fn test_formatter_error_handling() {
     struct FailingFormatter;
 
     impl Formatter for FailingFormatter {
-        fn format(&self, _entry: &Entry) -> Result<Vec<u8>, Box<dyn Error>> {
-            Err("Formatter error".into())
+        fn format(&self, _entry: &Entry) -> Result<Vec<u8>, LoggixError> {
+            Err(LoggixError::format("Formatter error"))
         }
     }
 
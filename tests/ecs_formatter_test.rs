@@ -0,0 +1,136 @@
+use loggix::{ECSFormatter, Fields, Level, Logger, ECS_VERSION};
+use serde_json::{json, Value};
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, Default)]
+struct TestWriter {
+    buffer: Arc<Mutex<Vec<u8>>>,
+}
+
+impl TestWriter {
+    fn get_output(&self) -> String {
+        String::from_utf8(self.buffer.lock().unwrap().clone()).unwrap()
+    }
+}
+
+impl Write for TestWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn create_test_logger(formatter: ECSFormatter) -> (Arc<Logger>, TestWriter) {
+    let writer = TestWriter::default();
+    let logger = Logger::new()
+        .formatter(formatter)
+        .output(writer.clone())
+        .build();
+    (logger, writer)
+}
+
+#[test]
+fn test_ecs_base_fields_come_first() {
+    let (logger, writer) = create_test_logger(ECSFormatter::new());
+    logger
+        .log(Level::Warn, "disk almost full", Fields::new())
+        .unwrap();
+
+    let output = writer.get_output();
+    assert!(output.starts_with("{\"@timestamp\":\""));
+    assert!(output.contains(
+        "\"log.level\":\"warn\",\"message\":\"disk almost full\",\"ecs.version\":\"8.11.0\""
+    ));
+
+    let parsed: Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(parsed["ecs.version"], ECS_VERSION);
+    assert!(parsed["@timestamp"].as_str().unwrap().ends_with('Z'));
+}
+
+#[test]
+fn test_ecs_maps_well_known_fields() {
+    let (logger, writer) = create_test_logger(ECSFormatter::new());
+    let error = io::Error::new(io::ErrorKind::NotFound, "no such file");
+
+    logger
+        .with_fields(Fields::new())
+        .with_error(&error)
+        .with_field("trace_id", "4bf92f3577b34da6a3ce929d0e0e4736")
+        .with_field("span_id", "00f067aa0ba902b7")
+        .with_field("service.name", "billing")
+        .with_field("order_id", 42)
+        .error("payment failed")
+        .unwrap();
+
+    let parsed: Value = serde_json::from_str(&writer.get_output()).unwrap();
+    assert_eq!(parsed["error"]["message"], "no such file");
+    assert_eq!(parsed["trace"]["id"], "4bf92f3577b34da6a3ce929d0e0e4736");
+    assert_eq!(parsed["span"]["id"], "00f067aa0ba902b7");
+    assert_eq!(parsed["service"]["name"], "billing");
+    assert_eq!(parsed["fields"], json!({"order_id": 42}));
+}
+
+#[test]
+fn test_ecs_custom_namespace_and_mapping() {
+    let (logger, writer) = create_test_logger(
+        ECSFormatter::new()
+            .namespace("app")
+            .map_field("method", "http.request.method"),
+    );
+
+    logger
+        .with_fields(Fields::new())
+        .with_field("method", "POST")
+        .with_field("tenant", "acme")
+        .info("request handled")
+        .unwrap();
+
+    let parsed: Value = serde_json::from_str(&writer.get_output()).unwrap();
+    assert_eq!(parsed["http"]["request"]["method"], "POST");
+    assert_eq!(parsed["app"]["tenant"], "acme");
+    assert!(parsed.get("fields").is_none());
+}
+
+#[test]
+fn test_ecs_empty_namespace_writes_top_level() {
+    let (logger, writer) = create_test_logger(ECSFormatter::new().namespace(""));
+
+    logger
+        .with_fields(Fields::new())
+        .with_field("tenant", "acme")
+        .with_field("message", "shadowed")
+        .info("original")
+        .unwrap();
+
+    let parsed: Value = serde_json::from_str(&writer.get_output()).unwrap();
+    assert_eq!(parsed["tenant"], "acme");
+    assert_eq!(parsed["message"], "original");
+}
+
+#[test]
+fn test_ecs_empty_namespace_keeps_clashing_fields() {
+    let (logger, writer) = create_test_logger(ECSFormatter::new().namespace(""));
+
+    logger
+        .with_fields(Fields::new())
+        .with_field("message", "shadowed")
+        .with_field("log", "app.log")
+        .with_field("ecs.version", "1.0")
+        .with_field("tenant", "acme")
+        .info("original")
+        .unwrap();
+
+    let parsed: Value = serde_json::from_str(&writer.get_output()).unwrap();
+    assert_eq!(parsed["message"], "original");
+    assert_eq!(parsed["log.level"], "info");
+    assert_eq!(parsed["ecs.version"], "8.11.0");
+    assert_eq!(parsed["fields"]["message"], "shadowed");
+    assert_eq!(parsed["fields"]["log"], "app.log");
+    assert_eq!(parsed["fields"]["ecs"]["version"], "1.0");
+    assert_eq!(parsed["tenant"], "acme");
+}
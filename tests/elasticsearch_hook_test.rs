@@ -0,0 +1,199 @@
+mod common;
+
+use chrono::TimeZone;
+use common::MockServer;
+use loggix::{Backoff, ElasticsearchHook, Entry, Fields, Hook, Level, Logger, TextFormatter};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+fn entry<'a>(logger: &'a Logger, level: Level, msg: &str, fields: Fields) -> Entry<'a> {
+    Entry {
+        timestamp: chrono::Utc.with_ymd_and_hms(2025, 4, 6, 10, 30, 0).unwrap(),
+        level,
+        message: msg.to_string(),
+        fields,
+        logger,
+    }
+}
+
+/// Split an NDJSON bulk body into `(action, document)` pairs
+fn bulk_pairs(body: &[u8]) -> Vec<(Value, Value)> {
+    let lines: Vec<Value> = std::str::from_utf8(body)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    lines
+        .chunks(2)
+        .map(|pair| (pair[0].clone(), pair[1].clone()))
+        .collect()
+}
+
+fn bulk_response(statuses: &[u16]) -> String {
+    let items: Vec<Value> = statuses
+        .iter()
+        .map(|status| {
+            if *status < 300 {
+                json!({"create": {"_index": "logs", "status": status}})
+            } else {
+                json!({"create": {
+                    "_index": "logs",
+                    "status": status,
+                    "error": {"type": "rejected", "reason": "test rejection"}
+                }})
+            }
+        })
+        .collect();
+    let errors = statuses.iter().any(|status| *status >= 300);
+    json!({"took": 1, "errors": errors, "items": items}).to_string()
+}
+
+#[test]
+fn test_bulk_request_format() {
+    let server = MockServer::start();
+    server.respond_with(200, &bulk_response(&[201, 201]));
+    let hook = ElasticsearchHook::builder(&server.url(""))
+        .basic_auth("elastic", "changeme")
+        .batch_size(10)
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    let mut fields = Fields::new();
+    fields.insert("user".to_string(), json!("john"));
+    hook.fire(&entry(&logger, Level::Info, "logged in", fields))
+        .unwrap();
+    hook.fire(&entry(&logger, Level::Warn, "slow", Fields::new()))
+        .unwrap();
+    hook.flush(Duration::from_secs(5)).unwrap();
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].path, "/_bulk");
+    assert_eq!(
+        requests[0].header("content-type"),
+        Some("application/x-ndjson")
+    );
+    assert!(requests[0]
+        .header("authorization")
+        .unwrap()
+        .starts_with("Basic "));
+    assert!(requests[0].body_str().ends_with('\n'));
+
+    let pairs = bulk_pairs(&requests[0].body);
+    assert_eq!(pairs.len(), 2);
+    assert_eq!(pairs[0].0, json!({"create": {"_index": "logs-2025.04.06"}}));
+    assert_eq!(pairs[0].1["message"], "logged in");
+    assert_eq!(pairs[0].1["fields"]["user"], "john");
+    assert_eq!(pairs[1].1["level"], "warn");
+}
+
+#[test]
+fn test_only_rejected_documents_are_retried() {
+    let server = MockServer::start();
+    server.respond_with(200, &bulk_response(&[201, 429, 400]));
+    server.respond_with(200, &bulk_response(&[201]));
+    let hook = ElasticsearchHook::builder(&server.url(""))
+        .index("app-%Y.%m")
+        .retry(Backoff::new().initial(Duration::from_millis(10)))
+        .batch_size(3)
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    for msg in ["accepted", "throttled", "malformed"] {
+        hook.fire(&entry(&logger, Level::Info, msg, Fields::new()))
+            .unwrap();
+    }
+    let err = hook.flush(Duration::from_secs(5)).unwrap_err();
+    assert!(err.to_string().contains("1 documents rejected"));
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(bulk_pairs(&requests[0].body).len(), 3);
+    let retried = bulk_pairs(&requests[1].body);
+    assert_eq!(retried.len(), 1);
+    assert_eq!(retried[0].0["create"]["_index"], "app-2025.04");
+    assert_eq!(retried[0].1["message"], "throttled");
+}
+
+#[test]
+fn test_documents_missing_from_the_response_are_retried() {
+    let server = MockServer::start();
+    server.respond_with(200, &bulk_response(&[201]));
+    server.respond_with(200, &bulk_response(&[201, 201]));
+    let hook = ElasticsearchHook::builder(&server.url(""))
+        .retry(Backoff::new().initial(Duration::from_millis(10)))
+        .batch_size(3)
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    for msg in ["first", "second", "third"] {
+        hook.fire(&entry(&logger, Level::Info, msg, Fields::new()))
+            .unwrap();
+    }
+    hook.flush(Duration::from_secs(5)).unwrap();
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    let retried = bulk_pairs(&requests[1].body);
+    assert_eq!(retried.len(), 2);
+    assert_eq!(retried[0].1["message"], "second");
+    assert_eq!(retried[1].1["message"], "third");
+}
+
+#[test]
+fn test_non_json_formatter_is_wrapped() {
+    let server = MockServer::start();
+    server.respond_with(200, &bulk_response(&[201]));
+    let hook = ElasticsearchHook::builder(&server.url("/_bulk"))
+        .formatter(TextFormatter::new().colors(false))
+        .pipeline("logs")
+        .batch_size(1)
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    hook.fire(&entry(&logger, Level::Error, "boom", Fields::new()))
+        .unwrap();
+    hook.flush(Duration::from_secs(5)).unwrap();
+
+    let requests = server.requests();
+    assert_eq!(requests[0].path, "/_bulk?pipeline=logs");
+    let pairs = bulk_pairs(&requests[0].body);
+    assert_eq!(pairs[0].1["@timestamp"], "2025-04-06T10:30:00.000Z");
+    let message = pairs[0].1["message"].as_str().unwrap();
+    assert!(message.contains("boom"));
+    assert!(!message.ends_with('\n'));
+}
+
+#[test]
+fn test_pipeline_is_encoded_into_the_query() {
+    let server = MockServer::start();
+    server.respond_with(200, &bulk_response(&[201]));
+    let hook = ElasticsearchHook::builder(&server.url("/?refresh=true"))
+        .pipeline("logs&v=2 #1")
+        .batch_size(1)
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    hook.fire(&entry(&logger, Level::Info, "hello", Fields::new()))
+        .unwrap();
+    hook.flush(Duration::from_secs(5)).unwrap();
+
+    let requests = server.requests();
+    assert_eq!(
+        requests[0].path,
+        "/_bulk?refresh=true&pipeline=logs%26v%3D2+%231"
+    );
+}
+
+#[test]
+fn test_invalid_index_pattern_is_rejected() {
+    let result = ElasticsearchHook::builder("http://127.0.0.1:1")
+        .index("logs-%Q")
+        .build();
+    assert!(result.is_err());
+}
@@ -0,0 +1,235 @@
+use loggix::{
+    error, error_count, handle_error, info, set_error_handler, try_error, try_info, Entry,
+    ErrorHandler, Hook, Level, Logger, LoggixError,
+};
+use std::io::{self, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+// The handler is process-wide, so tests that change it take turns
+static HANDLER: Mutex<()> = Mutex::new(());
+
+/// An output that always fails
+struct BrokenPipe;
+
+impl Write for BrokenPipe {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(io::ErrorKind::BrokenPipe, "stdout closed"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// An output that panics on its first write, then captures lines
+#[derive(Clone, Default)]
+struct PanicsOnce {
+    panicked: Arc<AtomicBool>,
+    written: Arc<Mutex<Vec<u8>>>,
+}
+
+impl Write for PanicsOnce {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.panicked.swap(true, Ordering::SeqCst) {
+            panic!("writer bug");
+        }
+        self.written.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+struct FailingHook;
+
+impl Hook for FailingHook {
+    fn levels(&self) -> Vec<Level> {
+        vec![Level::Error]
+    }
+
+    fn fire(&self, _entry: &Entry) -> Result<(), LoggixError> {
+        Err(LoggixError::hook("FailingHook", "collector unreachable"))
+    }
+}
+
+/// A hook whose queued entries can never be delivered
+struct StuckHook;
+
+impl Hook for StuckHook {
+    fn levels(&self) -> Vec<Level> {
+        vec![Level::Info]
+    }
+
+    fn fire(&self, _entry: &Entry) -> Result<(), LoggixError> {
+        Ok(())
+    }
+
+    fn flush(&self, _timeout: std::time::Duration) -> Result<(), LoggixError> {
+        Err(LoggixError::hook("StuckHook", "3 entries still queued"))
+    }
+}
+
+fn broken_pipe() -> LoggixError {
+    std::io::Error::new(std::io::ErrorKind::BrokenPipe, "stdout closed").into()
+}
+
+#[test]
+fn test_custom_handler_receives_errors() {
+    let _turn = HANDLER
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&seen);
+    set_error_handler(ErrorHandler::custom(move |err| {
+        sink.lock().unwrap().push(err.to_string())
+    }));
+
+    handle_error(broken_pipe());
+    assert_eq!(*seen.lock().unwrap(), vec!["stdout closed"]);
+    set_error_handler(ErrorHandler::Stderr);
+}
+
+#[test]
+fn test_errors_are_counted() {
+    let _turn = HANDLER
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    set_error_handler(ErrorHandler::Count);
+    let before = error_count();
+    handle_error(broken_pipe());
+    handle_error(LoggixError::hook("AuditHook", "timed out"));
+    assert!(error_count() >= before + 2);
+    set_error_handler(ErrorHandler::Stderr);
+}
+
+#[test]
+fn test_filtered_entries_are_not_errors() {
+    let _turn = HANDLER
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let seen = Arc::new(Mutex::new(0));
+    let sink = Arc::clone(&seen);
+    set_error_handler(ErrorHandler::custom(move |_| *sink.lock().unwrap() += 1));
+
+    handle_error(LoggixError::Filtered(
+        "logger has been shut down".to_string(),
+    ));
+    assert_eq!(*seen.lock().unwrap(), 0);
+    set_error_handler(ErrorHandler::Stderr);
+}
+
+#[test]
+fn test_macros_do_not_panic() {
+    info!("A walrus appears");
+    try_info!("A walrus appears").unwrap();
+    let result: Result<(), LoggixError> = try_error!(String::from("The ice is melting"));
+    assert!(result.is_ok());
+}
+
+#[test]
+fn test_macro_failures_reach_the_handler() {
+    let _turn = HANDLER
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&seen);
+    set_error_handler(ErrorHandler::custom(move |err| {
+        sink.lock().unwrap().push(err.to_string())
+    }));
+    let before = error_count();
+
+    let broken = Logger::new().output(BrokenPipe).build();
+    info!(logger: broken, "lost");
+    let hooked = Logger::new()
+        .output(io::sink())
+        .add_hook(FailingHook)
+        .build();
+    error!(logger: hooked, attempt = 1; "written, not forwarded");
+    hooked
+        .log(Level::Error, "forwarded by log", Default::default())
+        .unwrap();
+
+    let seen = seen.lock().unwrap().clone();
+    assert!(seen.contains(&"stdout closed".to_string()));
+    let hook_failures = seen
+        .iter()
+        .filter(|err| *err == "FailingHook: collector unreachable")
+        .count();
+    assert_eq!(hook_failures, 2);
+    assert!(error_count() >= before + 3);
+    set_error_handler(ErrorHandler::Stderr);
+}
+
+#[tokio::test]
+async fn test_macros_inside_a_runtime_do_not_panic() {
+    let _turn = HANDLER
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    set_error_handler(ErrorHandler::Count);
+    let before = error_count();
+
+    let logger = Logger::new()
+        .output(io::sink())
+        .add_hook(FailingHook)
+        .build();
+    error!(logger: logger, "inside a runtime");
+    assert!(error_count() > before);
+    set_error_handler(ErrorHandler::Stderr);
+}
+
+#[test]
+fn test_poisoned_output_keeps_logging() {
+    let _turn = HANDLER
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&seen);
+    set_error_handler(ErrorHandler::custom(move |err| {
+        sink.lock().unwrap().push(err.to_string())
+    }));
+
+    let output = PanicsOnce::default();
+    let logger = Logger::new().output(output.clone()).build();
+    // The panic poisons the output lock
+    let result = panic::catch_unwind(AssertUnwindSafe(|| info!(logger: logger, "first")));
+    assert!(result.is_err());
+
+    info!(logger: logger, "second");
+    let written = String::from_utf8(output.written.lock().unwrap().clone()).unwrap();
+    assert!(written.contains("second"));
+    assert!(seen.lock().unwrap().is_empty());
+    set_error_handler(ErrorHandler::Stderr);
+}
+
+#[test]
+fn test_drop_shutdown_failures_reach_the_handler() {
+    let _turn = HANDLER
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&seen);
+    set_error_handler(ErrorHandler::custom(move |err| {
+        sink.lock().unwrap().push(err.to_string())
+    }));
+
+    let logger = Logger::new().output(io::sink()).add_hook(StuckHook);
+    drop(logger);
+    let (logger, guard) = Logger::new()
+        .output(io::sink())
+        .add_hook(StuckHook)
+        .build_with_guard(std::time::Duration::from_millis(10));
+    drop(guard);
+    // Already shut down, so dropping the logger reports nothing more
+    drop(logger);
+
+    let seen = seen.lock().unwrap().clone();
+    assert_eq!(seen.len(), 2);
+    assert!(seen
+        .iter()
+        .all(|err| err.contains("3 entries still queued")));
+    set_error_handler(ErrorHandler::Stderr);
+}
@@ -0,0 +1,113 @@
+#[cfg(feature = "kafka")]
+use loggix::KafkaHook;
+use loggix::{
+    Entry, Fields, Formatter, Hook, Level, Logger, LoggixError, PermanentError, SpoolHook,
+};
+use std::error::Error;
+use std::io::{self, Write};
+use std::time::Duration;
+
+/// An output that always fails
+struct BrokenPipe;
+
+impl Write for BrokenPipe {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(io::ErrorKind::BrokenPipe, "stdout closed"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+struct NullHook;
+
+impl Hook for NullHook {
+    fn levels(&self) -> Vec<Level> {
+        vec![Level::Info]
+    }
+
+    fn fire(&self, _entry: &Entry) -> Result<(), LoggixError> {
+        Ok(())
+    }
+}
+
+struct RejectingFormatter;
+
+impl Formatter for RejectingFormatter {
+    fn format(&self, _entry: &Entry) -> Result<Vec<u8>, LoggixError> {
+        Err(LoggixError::format("message too long"))
+    }
+}
+
+fn assert_send_sync<T: Send + Sync + 'static>() {}
+
+#[test]
+fn test_error_is_send_and_sync() {
+    assert_send_sync::<LoggixError>();
+
+    let err = LoggixError::hook("AuditHook", "connection reset");
+    let handle = std::thread::spawn(move || err.to_string());
+    assert_eq!(handle.join().unwrap(), "AuditHook: connection reset");
+}
+
+#[test]
+fn test_output_failure_is_io() {
+    let logger = Logger::new().output(BrokenPipe).build();
+    let err = logger.log(Level::Info, "hello", Fields::new()).unwrap_err();
+    match err {
+        LoggixError::Io(err) => assert_eq!(err.kind(), io::ErrorKind::BrokenPipe),
+        other => panic!("expected an I/O error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_formatter_failure_is_format() {
+    let logger = Logger::new().formatter(RejectingFormatter).build();
+    let err = logger.log(Level::Info, "hello", Fields::new()).unwrap_err();
+    assert!(matches!(err, LoggixError::Format(_)));
+    assert_eq!(err.to_string(), "cannot format entry: message too long");
+}
+
+#[test]
+fn test_builder_failures_are_config() {
+    let dir = std::env::temp_dir().join(format!("loggix-error-{}", std::process::id()));
+    let err = SpoolHook::builder(NullHook, &dir)
+        .segment_size(0)
+        .build()
+        .err()
+        .unwrap();
+    assert!(matches!(err, LoggixError::Config(_)));
+    assert_eq!(err.to_string(), "segment size must be positive");
+}
+
+#[cfg(feature = "kafka")]
+#[test]
+fn test_kafka_builder_failures_are_config() {
+    let err = KafkaHook::builder("localhost:9092", "")
+        .build()
+        .err()
+        .unwrap();
+    assert!(matches!(err, LoggixError::Config(_)));
+}
+
+#[test]
+fn test_logging_after_shutdown_is_filtered() {
+    let logger = Logger::new().output(io::sink()).build();
+    logger.shutdown(Duration::from_secs(1)).unwrap();
+    let err = logger
+        .log(Level::Info, "too late", Fields::new())
+        .unwrap_err();
+    assert!(matches!(err, LoggixError::Filtered(_)));
+}
+
+#[test]
+fn test_hook_error_keeps_its_source() {
+    let err = LoggixError::hook("AuditHook", PermanentError::new("payload rejected"));
+    let LoggixError::Hook { name, source } = &err else {
+        panic!("expected a hook error");
+    };
+    assert_eq!(name, "AuditHook");
+    assert!(source.is::<PermanentError>());
+    assert!(err.source().unwrap().is::<PermanentError>());
+}
@@ -0,0 +1,232 @@
+use chrono::TimeZone;
+use loggix::{Backoff, Entry, Fields, FluentHook, Hook, Level, Logger};
+use rmpv::Value;
+use serde_json::json;
+use std::io::{Read, Write};
+use std::sync::mpsc;
+use std::time::Duration;
+
+fn entry<'a>(logger: &'a Logger, level: Level, msg: &str, fields: Fields) -> Entry<'a> {
+    Entry {
+        timestamp: chrono::Utc
+            .timestamp_opt(1_743_935_400, 250_000_123)
+            .unwrap(),
+        level,
+        message: msg.to_string(),
+        fields,
+        logger,
+    }
+}
+
+fn service_fields(service: &str) -> Fields {
+    let mut fields = Fields::new();
+    fields.insert("service".to_string(), json!(service));
+    fields
+}
+
+/// Decode every message from `stream`, answering acks when `ack` is set
+fn serve<S: Read + Write>(mut stream: S, ack: bool, messages: mpsc::Sender<Value>) {
+    while let Ok(message) = rmpv::decode::read_value(&mut stream) {
+        if ack {
+            let chunk = option(&message, "chunk").unwrap().clone();
+            let reply = Value::Map(vec![(Value::from("ack"), chunk)]);
+            rmpv::encode::write_value(&mut stream, &reply).unwrap();
+        }
+        if messages.send(message).is_err() {
+            break;
+        }
+    }
+}
+
+/// A Forward server on a random local TCP port
+fn start_tcp_server(ack: bool) -> (String, mpsc::Receiver<Value>) {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            serve(stream.unwrap(), ack, sender.clone());
+        }
+    });
+    (address, receiver)
+}
+
+fn option<'a>(message: &'a Value, key: &str) -> Option<&'a Value> {
+    message[2]
+        .as_map()?
+        .iter()
+        .find(|(k, _)| k.as_str() == Some(key))
+        .map(|(_, v)| v)
+}
+
+fn field<'a>(record: &'a Value, key: &str) -> Option<&'a Value> {
+    record
+        .as_map()?
+        .iter()
+        .find(|(k, _)| k.as_str() == Some(key))
+        .map(|(_, v)| v)
+}
+
+/// Split a PackedForward entries blob into its `[time, record]` events
+fn events(message: &Value) -> Vec<Value> {
+    let mut stream = message[1].as_slice().unwrap();
+    let mut events = Vec::new();
+    while !stream.is_empty() {
+        events.push(rmpv::decode::read_value(&mut stream).unwrap());
+    }
+    events
+}
+
+#[test]
+fn test_packed_forward_message() {
+    let (address, messages) = start_tcp_server(false);
+    let hook = FluentHook::builder(&format!("tcp://{}", address))
+        .tag("app.{level}")
+        .batch_size(2)
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    hook.fire(&entry(&logger, Level::Info, "first", service_fields("api")))
+        .unwrap();
+    hook.fire(&entry(&logger, Level::Info, "second", Fields::new()))
+        .unwrap();
+    hook.flush(Duration::from_secs(5)).unwrap();
+
+    let message = messages.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(message[0].as_str(), Some("app.info"));
+    assert_eq!(option(&message, "size").unwrap().as_u64(), Some(2));
+    assert!(option(&message, "chunk").is_none());
+
+    let events = events(&message);
+    assert_eq!(events.len(), 2);
+    // EventTime: ext type 0 with big-endian seconds and nanoseconds
+    let (kind, time) = events[0][0].as_ext().unwrap();
+    assert_eq!(kind, 0);
+    assert_eq!(time[..4], 1_743_935_400u32.to_be_bytes());
+    assert_eq!(time[4..], 250_000_123u32.to_be_bytes());
+
+    let record = &events[0][1];
+    assert_eq!(field(record, "message").unwrap().as_str(), Some("first"));
+    assert_eq!(field(record, "level").unwrap().as_str(), Some("info"));
+    assert_eq!(field(record, "service").unwrap().as_str(), Some("api"));
+    assert_eq!(
+        field(&events[1][1], "message").unwrap().as_str(),
+        Some("second")
+    );
+}
+
+#[test]
+fn test_tag_template_from_fields() {
+    let (address, messages) = start_tcp_server(false);
+    let hook = FluentHook::builder(&address)
+        .tag("app.{service}.{level}")
+        .batch_size(3)
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    hook.fire(&entry(&logger, Level::Info, "a", service_fields("api")))
+        .unwrap();
+    hook.fire(&entry(&logger, Level::Error, "b", service_fields("db")))
+        .unwrap();
+    hook.fire(&entry(&logger, Level::Info, "c", Fields::new()))
+        .unwrap();
+    hook.flush(Duration::from_secs(5)).unwrap();
+
+    let tags: Vec<String> = (0..3)
+        .map(|_| {
+            let message = messages.recv_timeout(Duration::from_secs(5)).unwrap();
+            message[0].as_str().unwrap().to_string()
+        })
+        .collect();
+    assert_eq!(
+        tags,
+        vec!["app.api.info", "app.db.error", "app.unknown.info"]
+    );
+}
+
+#[test]
+fn test_chunk_acknowledgements() {
+    let (address, messages) = start_tcp_server(true);
+    let hook = FluentHook::builder(&address)
+        .acknowledgements(true)
+        .batch_size(1)
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    hook.fire(&entry(&logger, Level::Warn, "acked", Fields::new()))
+        .unwrap();
+    hook.fire(&entry(&logger, Level::Warn, "acked again", Fields::new()))
+        .unwrap();
+    hook.flush(Duration::from_secs(5)).unwrap();
+
+    let first = messages.recv_timeout(Duration::from_secs(5)).unwrap();
+    let second = messages.recv_timeout(Duration::from_secs(5)).unwrap();
+    let first_chunk = option(&first, "chunk").unwrap().as_str().unwrap();
+    let second_chunk = option(&second, "chunk").unwrap().as_str().unwrap();
+    assert_eq!(first_chunk.len(), 24);
+    assert_ne!(first_chunk, second_chunk);
+}
+
+#[test]
+fn test_missing_ack_is_resent() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let (sender, messages) = mpsc::channel();
+    std::thread::spawn(move || {
+        // Drop the first connection without acknowledging, then ack normally
+        let (mut stream, _) = listener.accept().unwrap();
+        let message = rmpv::decode::read_value(&mut stream).unwrap();
+        sender.send(message).unwrap();
+        drop(stream);
+        let (stream, _) = listener.accept().unwrap();
+        serve(stream, true, sender);
+    });
+
+    let hook = FluentHook::builder(&address)
+        .acknowledgements(true)
+        .ack_timeout(Duration::from_millis(500))
+        .retry(Backoff::new().initial(Duration::from_millis(10)))
+        .batch_size(1)
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    hook.fire(&entry(&logger, Level::Info, "at least once", Fields::new()))
+        .unwrap();
+    hook.flush(Duration::from_secs(5)).unwrap();
+
+    let first = messages.recv_timeout(Duration::from_secs(5)).unwrap();
+    let resent = messages.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(first, resent);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_unix_socket() {
+    let dir = std::env::temp_dir().join(format!("loggix-fluent-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("forward.sock");
+    let _ = std::fs::remove_file(&path);
+    let listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+    let (sender, messages) = mpsc::channel();
+    std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        serve(stream, false, sender);
+    });
+
+    let hook = FluentHook::builder(&format!("unix://{}", path.display()))
+        .batch_size(1)
+        .build()
+        .unwrap();
+    let logger = Logger::new();
+    hook.fire(&entry(&logger, Level::Info, "over unix", Fields::new()))
+        .unwrap();
+    hook.flush(Duration::from_secs(5)).unwrap();
+
+    let message = messages.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(message[0].as_str(), Some("loggix"));
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,181 @@
+mod common;
+
+use common::MockServer;
+use flate2::read::GzDecoder;
+use loggix::{Backoff, BodyTemplate, Entry, Fields, Hook, HttpBody, HttpHook, Level, Logger};
+use serde_json::{json, Value};
+use std::io::Read;
+use std::time::Duration;
+
+fn entry<'a>(logger: &'a Logger, level: Level, msg: &str, fields: Fields) -> Entry<'a> {
+    Entry {
+        timestamp: chrono::Utc::now(),
+        level,
+        message: msg.to_string(),
+        fields,
+        logger,
+    }
+}
+
+fn service_fields(service: &str) -> Fields {
+    let mut fields = Fields::new();
+    fields.insert("service".to_string(), json!(service));
+    fields
+}
+
+#[test]
+fn test_json_array_body() {
+    let server = MockServer::start();
+    let hook = HttpHook::builder(&server.url("/audit"))
+        .bearer_token("secret")
+        .batch_size(2)
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    hook.fire(&entry(
+        &logger,
+        Level::Info,
+        "login",
+        service_fields("auth"),
+    ))
+    .unwrap();
+    hook.fire(&entry(&logger, Level::Warn, "logout", Fields::new()))
+        .unwrap();
+    hook.flush(Duration::from_secs(5)).unwrap();
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].method, "POST");
+    assert_eq!(requests[0].path, "/audit");
+    assert_eq!(requests[0].header("authorization"), Some("Bearer secret"));
+    assert_eq!(requests[0].header("content-type"), Some("application/json"));
+
+    let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!(body.as_array().unwrap().len(), 2);
+    assert_eq!(body[0]["message"], "login");
+    assert_eq!(body[0]["level"], "info");
+    assert_eq!(body[0]["fields"]["service"], "auth");
+    assert_eq!(body[1]["level"], "warn");
+}
+
+#[test]
+fn test_gzipped_ndjson_body() {
+    let server = MockServer::start();
+    let hook = HttpHook::builder(&server.url("/ingest"))
+        .method("put")
+        .basic_auth("user", "pass")
+        .body(HttpBody::Ndjson)
+        .gzip(true)
+        .batch_size(2)
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    hook.fire(&entry(&logger, Level::Info, "one", Fields::new()))
+        .unwrap();
+    hook.fire(&entry(&logger, Level::Info, "two", Fields::new()))
+        .unwrap();
+    hook.flush(Duration::from_secs(5)).unwrap();
+
+    let requests = server.requests();
+    assert_eq!(requests[0].method, "PUT");
+    assert_eq!(requests[0].header("content-encoding"), Some("gzip"));
+    assert_eq!(
+        requests[0].header("content-type"),
+        Some("application/x-ndjson")
+    );
+    assert!(requests[0]
+        .header("authorization")
+        .unwrap()
+        .starts_with("Basic "));
+
+    let mut body = String::new();
+    GzDecoder::new(requests[0].body.as_slice())
+        .read_to_string(&mut body)
+        .unwrap();
+    let lines: Vec<Value> = body
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[1]["message"], "two");
+}
+
+#[test]
+fn test_template_body() {
+    let server = MockServer::start();
+    let template = BodyTemplate::new(
+        r#"{"text": {{message|json}}, "service": {{field.service|json}}, "level": "{{level}}"}"#,
+    )
+    .separator(",")
+    .envelope(r#"{"count": {{count}}, "alerts": [{{entries}}]}"#);
+    let hook = HttpHook::builder(&server.url("/alerts"))
+        .body(HttpBody::Template(template))
+        .content_type("application/json")
+        .batch_size(2)
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    hook.fire(&entry(
+        &logger,
+        Level::Error,
+        "disk \"full\"",
+        service_fields("db"),
+    ))
+    .unwrap();
+    hook.fire(&entry(&logger, Level::Fatal, "oom", Fields::new()))
+        .unwrap();
+    hook.flush(Duration::from_secs(5)).unwrap();
+
+    let requests = server.requests();
+    let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!(
+        body,
+        json!({
+            "count": 2,
+            "alerts": [
+                {"text": "disk \"full\"", "service": "db", "level": "error"},
+                {"text": "oom", "service": null, "level": "fatal"}
+            ]
+        })
+    );
+}
+
+#[test]
+fn test_unknown_template_placeholder_is_rejected() {
+    let result = HttpHook::builder("http://127.0.0.1:1")
+        .body(HttpBody::Template(BodyTemplate::new("{{severity}}")))
+        .build();
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_failures_are_reported_after_retries() {
+    let server = MockServer::start();
+    server.respond_with(500, "boom");
+    server.respond_with(500, "boom");
+    let hook = HttpHook::builder(&server.url("/audit"))
+        .retry(
+            Backoff::new()
+                .initial(Duration::from_millis(10))
+                .max_retries(1),
+        )
+        .batch_size(1)
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    hook.fire(&entry(&logger, Level::Info, "lost", Fields::new()))
+        .unwrap();
+    let err = hook.flush(Duration::from_secs(5)).unwrap_err();
+    assert!(err.to_string().contains("500"));
+    assert_eq!(server.requests().len(), 2);
+
+    // The failure is reported once; later deliveries succeed again
+    hook.fire(&entry(&logger, Level::Info, "delivered", Fields::new()))
+        .unwrap();
+    hook.flush(Duration::from_secs(5)).unwrap();
+    assert_eq!(server.requests().len(), 3);
+}
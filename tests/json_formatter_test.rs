@@ -0,0 +1,97 @@
+use chrono::{SecondsFormat, TimeZone, Utc};
+use loggix::{Entry, Fields, Formatter, JSONFormatter, Level, Logger, TimestampFormat};
+use serde_json::{json, Value};
+
+fn format_entry(formatter: &JSONFormatter, fields: Fields) -> String {
+    let logger = Logger::new();
+    let entry = Entry {
+        timestamp: Utc.with_ymd_and_hms(2025, 4, 6, 10, 30, 0).unwrap()
+            + chrono::Duration::milliseconds(250),
+        level: Level::Info,
+        message: "user logged in".to_string(),
+        fields,
+        logger: &logger,
+    };
+    String::from_utf8(formatter.format(&entry).unwrap()).unwrap()
+}
+
+fn sample_fields() -> Fields {
+    let mut fields = Fields::new();
+    fields.insert("user".to_string(), json!("john"));
+    fields.insert("attempts".to_string(), json!(3));
+    fields
+}
+
+#[test]
+fn test_default_layout_is_unchanged() {
+    let output = format_entry(&JSONFormatter::new(), sample_fields());
+    assert_eq!(
+        output,
+        "{\"timestamp\":\"2025-04-06T10:30:00.250Z\",\"level\":\"info\",\
+         \"message\":\"user logged in\",\"fields\":{\"attempts\":3,\"user\":\"john\"}}\n"
+    );
+}
+
+#[test]
+fn test_renamed_keys_and_data_key() {
+    let formatter = JSONFormatter::new()
+        .timestamp_key("@timestamp")
+        .level_key("severity")
+        .message_key("msg")
+        .data_key("data");
+    let parsed: Value = serde_json::from_str(&format_entry(&formatter, sample_fields())).unwrap();
+
+    assert_eq!(parsed["@timestamp"], "2025-04-06T10:30:00.250Z");
+    assert_eq!(parsed["severity"], "info");
+    assert_eq!(parsed["msg"], "user logged in");
+    assert_eq!(parsed["data"], json!({"attempts": 3, "user": "john"}));
+    assert!(parsed.get("fields").is_none());
+}
+
+#[test]
+fn test_flattened_fields_with_clashes() {
+    let mut fields = sample_fields();
+    fields.insert("message".to_string(), json!("shadowed"));
+
+    let formatter = JSONFormatter::new().flatten_fields(true);
+    let parsed: Value = serde_json::from_str(&format_entry(&formatter, fields)).unwrap();
+
+    assert_eq!(parsed["user"], "john");
+    assert_eq!(parsed["attempts"], 3);
+    assert_eq!(parsed["message"], "user logged in");
+    assert_eq!(parsed["fields.message"], "shadowed");
+    assert!(parsed.get("fields").is_none());
+}
+
+#[test]
+fn test_timestamp_formats() {
+    let cases = [
+        (
+            TimestampFormat::Rfc3339(SecondsFormat::Secs),
+            json!("2025-04-06T10:30:00Z"),
+        ),
+        (
+            TimestampFormat::Rfc3339(SecondsFormat::Micros),
+            json!("2025-04-06T10:30:00.250000Z"),
+        ),
+        (TimestampFormat::UnixSeconds, json!(1743935400)),
+        (TimestampFormat::UnixMillis, json!(1743935400250_i64)),
+        (TimestampFormat::UnixNanos, json!(1743935400250000000_i64)),
+    ];
+
+    for (format, expected) in cases {
+        let formatter = JSONFormatter::new().timestamp_format(format);
+        let parsed: Value = serde_json::from_str(&format_entry(&formatter, Fields::new())).unwrap();
+        assert_eq!(parsed["timestamp"], expected, "{:?}", format);
+    }
+}
+
+#[test]
+fn test_disable_timestamp() {
+    let formatter = JSONFormatter::new().disable_timestamp(true);
+    let output = format_entry(&formatter, Fields::new());
+    assert_eq!(
+        output,
+        "{\"level\":\"info\",\"message\":\"user logged in\",\"fields\":{}}\n"
+    );
+}
@@ -0,0 +1,112 @@
+use loggix::{KafkaConfig, KafkaConfigError, KafkaHook, LoggixError};
+use std::collections::HashMap;
+
+/// The detailed error wrapped by a hook builder failure
+fn config_error(err: LoggixError) -> KafkaConfigError {
+    match err {
+        LoggixError::Config(source) => *source.downcast::<KafkaConfigError>().unwrap(),
+        other => panic!("expected a configuration error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_shipped_config_file() {
+    let config = KafkaConfig::from_yaml_file("config.yaml").unwrap();
+    assert_eq!(config.bootstrap_servers.as_deref(), Some("localhost:9092"));
+    assert_eq!(config.socket_timeout_ms, Some(3000));
+    assert_eq!(config.partitions, Some(1));
+    assert_eq!(config.replication_factor, Some(1));
+    assert!(!config.create_topic);
+
+    // Building the producer does not need a broker
+    assert!(KafkaHook::from_config(&config, "logs").is_ok());
+}
+
+#[test]
+fn test_overrides_take_precedence() {
+    let mut config = KafkaConfig::from_yaml_str(
+        r#"
+kafka:
+  bootstrap_servers: "localhost:9092"
+  partitions: 1
+  properties:
+    compression.type: lz4
+"#,
+    )
+    .unwrap();
+    let env: HashMap<&str, &str> = [
+        (
+            "LOGGIX_KAFKA_BOOTSTRAP_SERVERS",
+            "kafka-1:9092,kafka-2:9092",
+        ),
+        ("LOGGIX_KAFKA_PARTITIONS", "6"),
+        ("LOGGIX_KAFKA_CREATE_TOPIC", "yes"),
+    ]
+    .into_iter()
+    .collect();
+    config
+        .apply_overrides(|name| env.get(name).map(|value| value.to_string()))
+        .unwrap();
+
+    assert_eq!(
+        config.bootstrap_servers.as_deref(),
+        Some("kafka-1:9092,kafka-2:9092")
+    );
+    assert_eq!(config.partitions, Some(6));
+    assert!(config.create_topic);
+    assert_eq!(config.properties["compression.type"], "lz4");
+}
+
+#[test]
+fn test_invalid_override_is_reported() {
+    let mut config = KafkaConfig::default();
+    let err = config
+        .apply_overrides(|name| (name == "LOGGIX_KAFKA_PARTITIONS").then(|| "many".to_string()))
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        KafkaConfigError::Invalid { ref setting, .. } if setting == "partitions"
+    ));
+}
+
+#[test]
+fn test_missing_and_invalid_settings() {
+    let err = KafkaHook::from_config(&KafkaConfig::default(), "logs")
+        .err()
+        .unwrap();
+    assert!(matches!(
+        config_error(err),
+        KafkaConfigError::Missing("bootstrap_servers")
+    ));
+
+    let config = KafkaConfig {
+        bootstrap_servers: Some("localhost:9092".to_string()),
+        partitions: Some(0),
+        ..KafkaConfig::default()
+    };
+    let err = KafkaHook::from_config(&config, "logs").err().unwrap();
+    assert_eq!(
+        err.to_string(),
+        "invalid Kafka setting `partitions`: must be at least 1"
+    );
+
+    let config = KafkaConfig {
+        bootstrap_servers: Some("localhost:9092".to_string()),
+        properties: [("linger.msec".to_string(), "5".to_string())].into(),
+        ..KafkaConfig::default()
+    };
+    let err = KafkaHook::from_config(&config, "logs").err().unwrap();
+    assert!(matches!(config_error(err), KafkaConfigError::Producer(_)));
+}
+
+#[test]
+fn test_file_errors() {
+    let err = KafkaConfig::from_yaml_file("does-not-exist.yaml").unwrap_err();
+    assert!(matches!(err, KafkaConfigError::Io(_)));
+
+    let err = KafkaConfig::from_yaml_str("kafka: [").unwrap_err();
+    assert!(matches!(err, KafkaConfigError::Parse(_)));
+
+    let err = KafkaConfig::from_yaml_str("logging: {}").unwrap_err();
+    assert!(matches!(err, KafkaConfigError::Missing("kafka")));
+}
@@ -0,0 +1,444 @@
+mod common;
+
+use common::MockServer;
+use loggix::{
+    Entry, Fields, Hook, KafkaEncoding, KafkaHook, KafkaPartitioner, Level, LogfmtFormatter,
+    Logger, SchemaRegistry,
+};
+use serde_json::json;
+use std::sync::{mpsc, Mutex};
+use std::time::Duration;
+
+// Creating a producer does not connect, so none of these need a broker
+
+#[test]
+fn test_property_passthrough() {
+    let hook = KafkaHook::builder("localhost:9092", "logs")
+        .property("compression.type", "lz4")
+        .property("linger.ms", "50")
+        .properties([("acks", "all"), ("enable.idempotence", "true")])
+        .key_field("tenant_id")
+        .send_timeout(Duration::from_secs(5))
+        .build();
+    assert!(hook.is_ok());
+}
+
+#[test]
+fn test_sasl_ssl_configuration() {
+    let hook = KafkaHook::builder("broker:9093", "logs")
+        .sasl("SCRAM-SHA-512", "logger", "secret")
+        .build();
+    assert!(hook.is_ok());
+}
+
+#[test]
+fn test_unknown_property_is_rejected() {
+    let err = KafkaHook::builder("localhost:9092", "logs")
+        .property("linger.msec", "50")
+        .build()
+        .err()
+        .unwrap();
+    assert!(err
+        .to_string()
+        .contains("invalid Kafka producer configuration"));
+    assert!(err.to_string().contains("linger.msec"));
+}
+
+#[test]
+fn test_inconsistent_security_settings_are_rejected() {
+    let err = KafkaHook::builder("localhost:9092", "logs")
+        .property("sasl.mechanism", "PLAIN")
+        .build()
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("requires security.protocol"));
+
+    let err = KafkaHook::builder("localhost:9092", "logs")
+        .property("security.protocol", "SASL_SSL")
+        .property("sasl.mechanism", "SCRAM-SHA-256")
+        .build()
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("sasl.username"));
+
+    let err = KafkaHook::builder("localhost:9092", "logs")
+        .property("enable.idempotence", "true")
+        .property("acks", "1")
+        .build()
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("acks=all"));
+}
+
+#[test]
+fn test_missing_bootstrap_servers_are_rejected() {
+    assert!(KafkaHook::builder("", "logs").build().is_err());
+    assert!(KafkaHook::builder("localhost:9092", "").build().is_err());
+}
+
+fn entry(logger: &Logger) -> Entry<'_> {
+    Entry {
+        timestamp: chrono::Utc::now(),
+        level: Level::Info,
+        message: "from a plain thread".to_string(),
+        fields: Fields::new(),
+        logger,
+    }
+}
+
+#[test]
+fn test_threaded_fire_without_runtime() {
+    // Nothing listens on this port, so delivery fails once the message times out
+    let (sender, reports) = mpsc::channel();
+    let sender = Mutex::new(sender);
+    let hook = KafkaHook::builder("127.0.0.1:1", "logs")
+        .property("message.timeout.ms", "200")
+        .threaded(true)
+        .on_delivery(move |report| {
+            sender.lock().unwrap().send(report.clone()).unwrap();
+        })
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    hook.fire(&entry(&logger)).unwrap();
+
+    let report = reports.recv_timeout(Duration::from_secs(10)).unwrap();
+    assert_eq!(report.topic, "logs");
+    assert!(!report.is_ok());
+    assert_eq!(report.offset, None);
+    assert!(report.error.is_some());
+
+    let metrics = hook.metrics();
+    assert_eq!(metrics.enqueued, 1);
+    assert_eq!(metrics.delivered, 0);
+    assert_eq!(metrics.failed, 1);
+    assert_eq!(metrics.in_flight(), 0);
+}
+
+#[test]
+fn test_async_only_hook_rejects_sync_fire() {
+    let hook = KafkaHook::builder("127.0.0.1:1", "logs").build().unwrap();
+    let logger = Logger::new();
+    let err = hook.fire(&entry(&logger)).unwrap_err();
+    assert!(err.to_string().contains("threaded(true)"));
+}
+
+fn routed_entry<'a>(logger: &'a Logger, level: Level, fields: Fields) -> Entry<'a> {
+    Entry {
+        timestamp: chrono::Utc::now(),
+        level,
+        message: "routed".to_string(),
+        fields,
+        logger,
+    }
+}
+
+fn tenant(value: &str) -> Fields {
+    let mut fields = Fields::new();
+    fields.insert("tenant".to_string(), json!(value));
+    fields
+}
+
+#[test]
+fn test_route_by_level() {
+    let hook = KafkaHook::builder("localhost:9092", "logs")
+        .route_level(Level::Error, "logs.errors")
+        .build()
+        .unwrap();
+    let logger = Logger::new();
+    let error = routed_entry(&logger, Level::Error, Fields::new());
+    let fatal = routed_entry(&logger, Level::Fatal, Fields::new());
+    let info = routed_entry(&logger, Level::Info, Fields::new());
+    assert_eq!(hook.topic_for(&error), "logs.errors");
+    assert_eq!(hook.topic_for(&fatal), "logs.errors");
+    assert_eq!(hook.topic_for(&info), "logs");
+}
+
+#[test]
+fn test_route_by_template() {
+    let hook = KafkaHook::builder("localhost:9092", "logs")
+        .route_level(Level::Error, "logs.errors")
+        .route_template("logs.{tenant}.{level}")
+        .build()
+        .unwrap();
+    let logger = Logger::new();
+    let acme = routed_entry(&logger, Level::Info, tenant("acme"));
+    let failed = routed_entry(&logger, Level::Error, tenant("acme"));
+    let unknown = routed_entry(&logger, Level::Info, Fields::new());
+    assert_eq!(hook.topic_for(&acme), "logs.acme.info");
+    // The first matching route wins
+    assert_eq!(hook.topic_for(&failed), "logs.errors");
+    // Entries without the field fall back to the default topic
+    assert_eq!(hook.topic_for(&unknown), "logs");
+}
+
+#[test]
+fn test_routed_topic_names_are_sanitized() {
+    let hook = KafkaHook::builder("localhost:9092", "logs")
+        .route_template("logs.{tenant}")
+        .build()
+        .unwrap();
+    let logger = Logger::new();
+    let entry = routed_entry(&logger, Level::Info, tenant("acme corp/eu"));
+    assert_eq!(hook.topic_for(&entry), "logs.acme_corp_eu");
+
+    let long = routed_entry(&logger, Level::Info, tenant(&"x".repeat(300)));
+    assert_eq!(hook.topic_for(&long), "logs");
+}
+
+#[test]
+fn test_max_topics_bounds_routed_topics() {
+    let hook = KafkaHook::builder("localhost:9092", "logs")
+        .route_template("logs.{tenant}")
+        .max_topics(2)
+        .build()
+        .unwrap();
+    let logger = Logger::new();
+    let topic = |name: &str| hook.topic_for(&routed_entry(&logger, Level::Info, tenant(name)));
+    assert_eq!(topic("a"), "logs.a");
+    assert_eq!(topic("b"), "logs.b");
+    assert_eq!(topic("c"), "logs");
+    // Topics already in use keep working
+    assert_eq!(topic("a"), "logs.a");
+}
+
+#[test]
+fn test_invalid_routes_are_rejected() {
+    assert!(KafkaHook::builder("localhost:9092", "logs")
+        .route_level(Level::Error, "logs/errors")
+        .build()
+        .is_err());
+    assert!(KafkaHook::builder("localhost:9092", "logs")
+        .route_template("logs.{tenant")
+        .build()
+        .is_err());
+    assert!(KafkaHook::builder("localhost:9092", "logs")
+        .route_template("logs {tenant}")
+        .build()
+        .is_err());
+}
+
+#[test]
+fn test_key_field_coerces_values() {
+    let hook = KafkaHook::builder("localhost:9092", "logs")
+        .key_field("user_id")
+        .build()
+        .unwrap();
+    let logger = Logger::new();
+    let mut fields = Fields::new();
+    fields.insert("user_id".to_string(), json!(42));
+    let numeric = routed_entry(&logger, Level::Info, fields);
+    assert_eq!(hook.key_for(&numeric), Some("42".to_string()));
+
+    let missing = routed_entry(&logger, Level::Info, Fields::new());
+    assert_eq!(hook.key_for(&missing), None);
+}
+
+#[test]
+fn test_key_template() {
+    let hook = KafkaHook::builder("localhost:9092", "logs")
+        .key_template("{tenant}:{user_id}")
+        .build()
+        .unwrap();
+    let logger = Logger::new();
+    let mut fields = tenant("acme");
+    fields.insert("user_id".to_string(), json!(7));
+    let entry = routed_entry(&logger, Level::Info, fields);
+    assert_eq!(hook.key_for(&entry), Some("acme:7".to_string()));
+
+    let partial = routed_entry(&logger, Level::Info, tenant("acme"));
+    assert_eq!(hook.key_for(&partial), None);
+
+    assert!(KafkaHook::builder("localhost:9092", "logs")
+        .key_template("{tenant")
+        .build()
+        .is_err());
+}
+
+#[test]
+fn test_record_headers() {
+    let hook = KafkaHook::builder("localhost:9092", "logs")
+        .metadata_headers(true)
+        .header("source", "billing")
+        .header_field("tenant")
+        .build()
+        .unwrap();
+    let logger = Logger::new();
+    let headers = hook.headers_for(&routed_entry(&logger, Level::Warn, tenant("acme")));
+    let names: Vec<&str> = headers.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec!["level", "hostname", "content-type", "source", "tenant"]
+    );
+    assert_eq!(headers[0].1, "warn");
+    assert_eq!(headers[2].1, "application/json");
+    assert_eq!(headers[3].1, "billing");
+    assert_eq!(headers[4].1, "acme");
+
+    // Headers for missing fields are left out
+    let headers = hook.headers_for(&routed_entry(&logger, Level::Info, Fields::new()));
+    assert!(!headers.iter().any(|(name, _)| name == "tenant"));
+}
+
+#[test]
+fn test_partitioners() {
+    for partitioner in [
+        KafkaPartitioner::KeyHash,
+        KafkaPartitioner::RoundRobin,
+        KafkaPartitioner::Fixed(2),
+    ] {
+        assert!(KafkaHook::builder("localhost:9092", "logs")
+            .partitioner(partitioner)
+            .build()
+            .is_ok());
+    }
+    assert!(KafkaHook::builder("localhost:9092", "logs")
+        .partitioner(KafkaPartitioner::Fixed(-1))
+        .build()
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_async_fire_waits_for_delivery() {
+    // Nothing listens on this port, so the awaited delivery fails
+    let hook = KafkaHook::builder("127.0.0.1:1", "logs")
+        .property("message.timeout.ms", "200")
+        .partitioner(KafkaPartitioner::RoundRobin)
+        .build()
+        .unwrap();
+    let logger = Logger::new();
+    assert!(hook.fire_async(&entry(&logger)).await.is_err());
+}
+
+#[test]
+fn test_formatter_payload() {
+    let hook = KafkaHook::builder("localhost:9092", "logs")
+        .formatter(LogfmtFormatter::new().disable_timestamp(true))
+        .build()
+        .unwrap();
+    let logger = Logger::new();
+    let payload = hook
+        .payload_for(&routed_entry(&logger, Level::Info, tenant("acme")))
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(payload).unwrap(),
+        "level=info msg=routed tenant=acme"
+    );
+}
+
+#[test]
+fn test_message_pack_payload() {
+    let hook = KafkaHook::builder("localhost:9092", "logs")
+        .encoding(KafkaEncoding::MessagePack)
+        .metadata_headers(true)
+        .build()
+        .unwrap();
+    let logger = Logger::new();
+    let entry = routed_entry(&logger, Level::Warn, tenant("acme"));
+    let payload = hook.payload_for(&entry).unwrap();
+    let value = rmpv::decode::read_value(&mut payload.as_slice()).unwrap();
+    let get = |key: &str| {
+        value
+            .as_map()
+            .unwrap()
+            .iter()
+            .find(|(k, _)| k.as_str() == Some(key))
+            .map(|(_, v)| v.clone())
+            .unwrap()
+    };
+    assert_eq!(get("message").as_str(), Some("routed"));
+    assert_eq!(get("level").as_str(), Some("warn"));
+    assert_eq!(get("fields")["tenant"].as_str(), Some("acme"));
+
+    let headers = hook.headers_for(&entry);
+    assert!(headers.contains(&(
+        "content-type".to_string(),
+        "application/msgpack".to_string()
+    )));
+}
+
+/// Read an Avro zigzag varint `long` from the front of `bytes`
+fn read_long(bytes: &mut &[u8]) -> i64 {
+    let mut n = 0u64;
+    let mut shift = 0;
+    loop {
+        let byte = bytes[0];
+        *bytes = &bytes[1..];
+        n |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+    }
+    (n >> 1) as i64 ^ -((n & 1) as i64)
+}
+
+fn read_string(bytes: &mut &[u8]) -> String {
+    let len = read_long(bytes) as usize;
+    let value = String::from_utf8(bytes[..len].to_vec()).unwrap();
+    *bytes = &bytes[len..];
+    value
+}
+
+#[test]
+fn test_avro_payload_with_schema_registry() {
+    let registry = MockServer::start();
+    registry.respond_with(200, r#"{"id":42}"#);
+    let hook = KafkaHook::builder("localhost:9092", "logs")
+        .encoding(KafkaEncoding::Avro(SchemaRegistry::new(&registry.url(""))))
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    let mut fields = tenant("acme");
+    fields.insert("attempt".to_string(), json!(3));
+    let entry = routed_entry(&logger, Level::Error, fields);
+    let payload = hook.payload_for(&entry).unwrap();
+    // The schema ID is cached after the first registration
+    hook.payload_for(&entry).unwrap();
+
+    let requests = registry.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].path, "/subjects/logs-value/versions");
+    assert_eq!(
+        requests[0].header("content-type"),
+        Some("application/vnd.schemaregistry.v1+json")
+    );
+    let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!(body["schema"].as_str(), Some(loggix::ENTRY_SCHEMA));
+
+    assert_eq!(payload[0], 0);
+    assert_eq!(payload[1..5], 42u32.to_be_bytes());
+    let mut body = &payload[5..];
+    assert_eq!(read_long(&mut body), entry.timestamp.timestamp_micros());
+    assert_eq!(read_long(&mut body), 4); // ERROR
+    assert_eq!(read_string(&mut body), "routed");
+    assert_eq!(read_long(&mut body), 2);
+    assert_eq!(read_string(&mut body), "attempt");
+    assert_eq!(read_string(&mut body), "3");
+    assert_eq!(read_string(&mut body), "tenant");
+    assert_eq!(read_string(&mut body), "acme");
+    assert_eq!(read_long(&mut body), 0);
+    assert!(body.is_empty());
+}
+
+#[test]
+fn test_schema_registry_errors_are_reported() {
+    let registry = MockServer::start();
+    registry.respond_with(409, r#"{"error_code":409,"message":"incompatible schema"}"#);
+    let hook = KafkaHook::builder("localhost:9092", "logs")
+        .encoding(KafkaEncoding::Avro(
+            SchemaRegistry::new(&registry.url("")).subject("audit"),
+        ))
+        .build()
+        .unwrap();
+    let logger = Logger::new();
+    let err = hook
+        .payload_for(&routed_entry(&logger, Level::Info, Fields::new()))
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("schema registration for audit failed"));
+    assert!(err.to_string().contains("incompatible schema"));
+}
@@ -1,33 +1,14 @@
 use chrono;
-use loggix::{KafkaHook, Level, Logger};
+use loggix::{KafkaConfig, KafkaHook, Level, Logger};
 use rdkafka::{
-    admin::{AdminClient, AdminOptions, NewTopic, TopicReplication},
-    client::DefaultClientContext,
     consumer::{BaseConsumer, Consumer, StreamConsumer},
     ClientConfig, Message,
 };
 use serde::Deserialize;
 use serde_json;
-use serde_yaml;
-use std::{fs, path::Path, time::Duration};
+use std::time::Duration;
 use tokio;
 
-#[derive(Debug, Deserialize)]
-struct KafkaConfig {
-    bootstrap_servers: String,
-    group_id: String,
-    auto_offset_reset: String,
-    socket_timeout_ms: u32,
-    session_timeout_ms: u32,
-    replication_factor: i32,
-    partitions: i32,
-}
-
-#[derive(Debug, Deserialize)]
-struct Config {
-    kafka: KafkaConfig,
-}
-
 #[derive(Debug, Deserialize)]
 struct LogEntry {
     message: String,
@@ -38,62 +19,40 @@ struct LogEntry {
     fields: std::collections::HashMap<String, serde_json::Value>,
 }
 
-fn load_config() -> Result<Config, Box<dyn std::error::Error>> {
-    let config_path = Path::new("config.yaml");
-    let config_str = fs::read_to_string(config_path)?;
-    let config: Config = serde_yaml::from_str(&config_str)?;
-    Ok(config)
+fn get_kafka_config() -> KafkaConfig {
+    let mut config = KafkaConfig::from_yaml_file("config.yaml").unwrap_or_else(|_| KafkaConfig {
+        bootstrap_servers: Some("localhost:9092".to_string()),
+        group_id: Some("test_group".to_string()),
+        auto_offset_reset: Some("earliest".to_string()),
+        socket_timeout_ms: Some(3000),
+        session_timeout_ms: Some(6000),
+        replication_factor: Some(1),
+        partitions: Some(1),
+        ..KafkaConfig::default()
+    });
+    config.apply_env().expect("Invalid LOGGIX_KAFKA_* override");
+    config.create_topic = true;
+    config
 }
 
-fn get_kafka_config() -> KafkaConfig {
-    load_config()
-        .map(|c| c.kafka)
-        .unwrap_or_else(|_| KafkaConfig {
-            bootstrap_servers: "localhost:9092".to_string(),
-            group_id: "test_group".to_string(),
-            auto_offset_reset: "earliest".to_string(),
-            socket_timeout_ms: 3000,
-            session_timeout_ms: 6000,
-            replication_factor: 1,
-            partitions: 1,
-        })
+fn bootstrap_servers(config: &KafkaConfig) -> &str {
+    config
+        .bootstrap_servers
+        .as_deref()
+        .unwrap_or("localhost:9092")
 }
 
-async fn ensure_topic_exists(
+async fn wait_for_topic(
     topic: &str,
     config: &KafkaConfig,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    println!("Checking if topic {} exists...", topic);
-
-    // Create admin client to create topic
-    let admin_client: AdminClient<DefaultClientContext> = ClientConfig::new()
-        .set("bootstrap.servers", &config.bootstrap_servers)
-        .create()?;
-
-    // Always try to create the topic
-    println!("Creating topic {}...", topic);
-    let topics = vec![NewTopic::new(
-        topic,
-        config.partitions,
-        TopicReplication::Fixed(config.replication_factor),
-    )];
-
-    // Ignore error if topic already exists
-    match admin_client
-        .create_topics(&topics, &AdminOptions::new())
-        .await
-    {
-        Ok(_) => println!("Topic {} created successfully", topic),
-        Err(e) => println!("Topic creation returned: {}", e),
-    }
-
     // Wait for topic to be fully created/propagated
     println!("Waiting for topic {} to be fully propagated...", topic);
     tokio::time::sleep(Duration::from_secs(5)).await;
 
     // Verify topic exists by trying to get metadata
     let consumer: BaseConsumer = ClientConfig::new()
-        .set("bootstrap.servers", &config.bootstrap_servers)
+        .set("bootstrap.servers", bootstrap_servers(config))
         .create()?;
 
     let metadata = consumer.fetch_metadata(Some(topic), Duration::from_secs(5))?;
@@ -108,11 +67,23 @@ async fn ensure_topic_exists(
 fn create_test_consumer(topic: &str, config: &KafkaConfig) -> StreamConsumer {
     println!("Creating consumer for topic {}...", topic);
     let consumer: StreamConsumer = ClientConfig::new()
-        .set("group.id", &config.group_id)
-        .set("bootstrap.servers", &config.bootstrap_servers)
-        .set("auto.offset.reset", &config.auto_offset_reset)
-        .set("socket.timeout.ms", &config.socket_timeout_ms.to_string())
-        .set("session.timeout.ms", &config.session_timeout_ms.to_string())
+        .set(
+            "group.id",
+            config.group_id.as_deref().unwrap_or("test_group"),
+        )
+        .set("bootstrap.servers", bootstrap_servers(config))
+        .set(
+            "auto.offset.reset",
+            config.auto_offset_reset.as_deref().unwrap_or("earliest"),
+        )
+        .set(
+            "socket.timeout.ms",
+            config.socket_timeout_ms.unwrap_or(3000).to_string(),
+        )
+        .set(
+            "session.timeout.ms",
+            config.session_timeout_ms.unwrap_or(6000).to_string(),
+        )
         .create()
         .expect("Consumer creation failed");
 
@@ -134,14 +105,12 @@ async fn test_kafka_hook_integration() -> Result<(), Box<dyn std::error::Error>>
     let test_topic = format!("test_logs_{}", chrono::Utc::now().timestamp());
     println!("Using test topic: {}", test_topic);
 
-    // Ensure topic exists
-    ensure_topic_exists(&test_topic, &config).await?;
-
-    // Create the Kafka hook with a key field
-    println!("Creating Kafka hook...");
-    let kafka_hook = KafkaHook::new(&config.bootstrap_servers, test_topic.clone())
+    // Create the Kafka hook with a key field; the config creates the topic
+    println!("Creating Kafka hook and topic {}...", test_topic);
+    let kafka_hook = KafkaHook::from_config(&config, &test_topic)
         .expect("Failed to create Kafka hook")
         .with_key_field("correlation_id".to_string());
+    wait_for_topic(&test_topic, &config).await?;
 
     // Create a logger with the Kafka hook
     println!("Creating logger with Kafka hook...");
@@ -0,0 +1,217 @@
+use loggix::{
+    Entry, Fields, Hook, KafkaEncoding, KafkaMetrics, KafkaMockCluster, KafkaPartitioner, Level,
+    Logger,
+};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::{mpsc, Mutex};
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+fn entry<'a>(logger: &'a Logger, level: Level, message: &str, tenant: &str) -> Entry<'a> {
+    let mut fields = Fields::new();
+    fields.insert("tenant".to_string(), json!(tenant));
+    fields.insert("user_id".to_string(), json!(7));
+    Entry {
+        timestamp: chrono::Utc::now(),
+        level,
+        message: message.to_string(),
+        fields,
+        logger,
+    }
+}
+
+#[test]
+fn test_routing_keys_and_headers() {
+    let cluster = KafkaMockCluster::start().unwrap();
+    let hook = cluster
+        .hook("logs")
+        .threaded(true)
+        .route_level(Level::Error, "logs.errors")
+        .route_template("logs.{tenant}")
+        .key_template("{tenant}:{user_id}")
+        .metadata_headers(true)
+        .header_field("tenant")
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    hook.fire(&entry(&logger, Level::Info, "signed in", "acme"))
+        .unwrap();
+    hook.fire(&entry(&logger, Level::Error, "payment failed", "acme"))
+        .unwrap();
+    hook.flush(TIMEOUT).unwrap();
+
+    let consumer = cluster.consumer(&["logs.acme", "logs.errors"]).unwrap();
+    let mut messages = consumer.messages(2, TIMEOUT).unwrap();
+    assert_eq!(messages.len(), 2);
+    messages.sort_by(|a, b| a.topic.cmp(&b.topic));
+
+    assert_eq!(messages[0].topic, "logs.acme");
+    assert_eq!(messages[0].key.as_deref(), Some("acme:7"));
+    assert_eq!(messages[0].header("level"), Some("info"));
+    assert_eq!(messages[0].header("tenant"), Some("acme"));
+    assert_eq!(messages[0].header("content-type"), Some("application/json"));
+    let record = messages[0].record().unwrap();
+    assert_eq!(record.message, "signed in");
+    assert_eq!(record.level, Level::Info);
+    assert_eq!(record.fields["user_id"], json!(7));
+
+    assert_eq!(messages[1].topic, "logs.errors");
+    assert_eq!(messages[1].record().unwrap().message, "payment failed");
+}
+
+#[tokio::test]
+async fn test_async_delivery() {
+    let cluster = KafkaMockCluster::start().unwrap();
+    let hook = cluster.hook("logs").build().unwrap();
+
+    let logger = Logger::new();
+    hook.fire_async(&entry(&logger, Level::Warn, "disk almost full", "acme"))
+        .await
+        .unwrap();
+
+    let consumer = cluster.consumer(&["logs"]).unwrap();
+    let records = consumer.records(1, TIMEOUT).unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].message, "disk almost full");
+    assert_eq!(records[0].level, Level::Warn);
+}
+
+#[test]
+fn test_partitioners() {
+    let cluster = KafkaMockCluster::start().unwrap();
+    cluster.create_topic("fixed", 4).unwrap();
+    cluster.create_topic("hashed", 4).unwrap();
+    let fixed = cluster
+        .hook("fixed")
+        .threaded(true)
+        .partitioner(KafkaPartitioner::Fixed(2))
+        .build()
+        .unwrap();
+    let hashed = cluster
+        .hook("hashed")
+        .threaded(true)
+        .key_field("tenant")
+        .partitioner(KafkaPartitioner::KeyHash)
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    for message in ["a", "b", "c"] {
+        fixed
+            .fire(&entry(&logger, Level::Info, message, "acme"))
+            .unwrap();
+        hashed
+            .fire(&entry(&logger, Level::Info, message, "acme"))
+            .unwrap();
+    }
+    fixed.flush(TIMEOUT).unwrap();
+    hashed.flush(TIMEOUT).unwrap();
+
+    let messages = cluster
+        .consumer(&["fixed", "hashed"])
+        .unwrap()
+        .messages(6, TIMEOUT)
+        .unwrap();
+    assert_eq!(messages.len(), 6);
+    let partitions = |topic: &str| -> Vec<i32> {
+        messages
+            .iter()
+            .filter(|message| message.topic == topic)
+            .map(|message| message.partition)
+            .collect()
+    };
+    assert_eq!(partitions("fixed"), vec![2, 2, 2]);
+    let hashed = partitions("hashed");
+    assert_eq!(hashed.len(), 3);
+    assert!(hashed.iter().all(|partition| *partition == hashed[0]));
+}
+
+#[test]
+fn test_message_pack_round_trip() {
+    let cluster = KafkaMockCluster::start().unwrap();
+    let hook = cluster
+        .hook("logs")
+        .threaded(true)
+        .encoding(KafkaEncoding::MessagePack)
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    hook.fire(&entry(&logger, Level::Info, "packed", "acme"))
+        .unwrap();
+    hook.flush(TIMEOUT).unwrap();
+
+    let messages = cluster
+        .consumer(&["logs"])
+        .unwrap()
+        .messages(1, TIMEOUT)
+        .unwrap();
+    // Not JSON, but the raw payload is available
+    assert!(messages[0].record().is_err());
+    let value = rmpv::decode::read_value(&mut messages[0].payload.as_slice()).unwrap();
+    assert_eq!(value["message"].as_str(), Some("packed"));
+}
+
+#[test]
+fn test_metrics_and_statistics() {
+    let cluster = KafkaMockCluster::start().unwrap();
+    let (sender, statistics) = mpsc::channel();
+    let sender = Mutex::new(sender);
+    let hook = cluster
+        .hook("logs")
+        .threaded(true)
+        .on_statistics(Duration::from_millis(100), move |json| {
+            let _ = sender.lock().unwrap().send(json.to_string());
+        })
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    for message in ["one", "two", "three"] {
+        hook.fire(&entry(&logger, Level::Info, message, "acme"))
+            .unwrap();
+    }
+    hook.flush(TIMEOUT).unwrap();
+    assert_eq!(
+        hook.metrics(),
+        KafkaMetrics {
+            enqueued: 3,
+            delivered: 3,
+            failed: 0,
+            retried: 0,
+        }
+    );
+
+    let json = statistics.recv_timeout(TIMEOUT).unwrap();
+    let statistics: HashMap<String, serde_json::Value> = serde_json::from_str(&json).unwrap();
+    assert_eq!(statistics["type"], json!("producer"));
+}
+
+#[test]
+fn test_dropping_the_logger_flushes_hooks() {
+    let cluster = KafkaMockCluster::start().unwrap();
+    let (sender, reports) = mpsc::channel();
+    let sender = Mutex::new(sender);
+    // Without a flush, the message would wait in the queue for a minute
+    let hook = cluster
+        .hook("logs")
+        .threaded(true)
+        .property("linger.ms", "60000")
+        .on_delivery(move |report| {
+            let _ = sender.lock().unwrap().send(report.is_ok());
+        })
+        .build()
+        .unwrap();
+
+    let logger = Logger::new().output(std::io::sink()).add_hook(hook);
+    logger
+        .log(Level::Info, "last words", Fields::new())
+        .unwrap();
+    assert!(reports.try_recv().is_err());
+    drop(logger);
+
+    assert_eq!(reports.try_recv(), Ok(true));
+}
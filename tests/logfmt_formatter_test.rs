@@ -0,0 +1,117 @@
+use loggix::{Fields, Level, LogfmtFormatter, Logger};
+use serde_json::json;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, Default)]
+struct TestWriter {
+    buffer: Arc<Mutex<Vec<u8>>>,
+}
+
+impl TestWriter {
+    fn get_output(&self) -> String {
+        String::from_utf8(self.buffer.lock().unwrap().clone()).unwrap()
+    }
+}
+
+impl Write for TestWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.buffer.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn log_line(formatter: LogfmtFormatter, msg: &str, fields: Fields) -> String {
+    let writer = TestWriter::default();
+    let logger = Logger::new()
+        .formatter(formatter)
+        .output(writer.clone())
+        .build();
+    logger.log(Level::Info, msg, fields).unwrap();
+    writer.get_output()
+}
+
+#[test]
+fn test_logfmt_basic_line() {
+    let mut fields = Fields::new();
+    fields.insert("user".to_string(), json!("john"));
+    fields.insert("attempts".to_string(), json!(3));
+
+    let output = log_line(
+        LogfmtFormatter::new().disable_timestamp(true),
+        "login",
+        fields,
+    );
+    assert_eq!(output, "level=info msg=login attempts=3 user=john\n");
+}
+
+#[test]
+fn test_logfmt_timestamp() {
+    let output = log_line(LogfmtFormatter::new(), "hello", Fields::new());
+    assert!(output.starts_with("time="));
+    assert!(output.contains(" level=info msg=hello"));
+}
+
+#[test]
+fn test_logfmt_quoting_and_escaping() {
+    let mut fields = Fields::new();
+    fields.insert("path".to_string(), json!("C:\\temp"));
+    fields.insert("quote".to_string(), json!("say \"hi\""));
+    fields.insert("multi".to_string(), json!("line1\nline2"));
+    fields.insert("empty".to_string(), json!(""));
+    fields.insert("eq".to_string(), json!("a=b"));
+
+    let output = log_line(
+        LogfmtFormatter::new().disable_timestamp(true),
+        "user logged in",
+        fields,
+    );
+    assert_eq!(
+        output,
+        "level=info msg=\"user logged in\" empty=\"\" eq=\"a=b\" multi=\"line1\\nline2\" \
+         path=\"C:\\\\temp\" quote=\"say \\\"hi\\\"\"\n"
+    );
+}
+
+#[test]
+fn test_logfmt_flattens_nested_values() {
+    let mut fields = Fields::new();
+    fields.insert(
+        "http".to_string(),
+        json!({"status": 200, "request": {"method": "GET"}, "tags": ["a", "b c"]}),
+    );
+    fields.insert("ok".to_string(), json!(true));
+    fields.insert("missing".to_string(), json!(null));
+
+    let output = log_line(
+        LogfmtFormatter::new().disable_timestamp(true),
+        "done",
+        fields,
+    );
+    assert_eq!(
+        output,
+        "level=info msg=done http.request.method=GET http.status=200 http.tags.0=a \
+         http.tags.1=\"b c\" missing=null ok=true\n"
+    );
+}
+
+#[test]
+fn test_logfmt_reserved_and_invalid_keys() {
+    let mut fields = Fields::new();
+    fields.insert("msg".to_string(), json!("shadowed"));
+    fields.insert("bad key".to_string(), json!(1));
+
+    let output = log_line(
+        LogfmtFormatter::new().disable_timestamp(true),
+        "real",
+        fields,
+    );
+    assert_eq!(
+        output,
+        "level=info msg=real bad_key=1 fields.msg=shadowed\n"
+    );
+}
@@ -0,0 +1,262 @@
+mod common;
+
+use common::MockServer;
+use flate2::read::GzDecoder;
+use loggix::{Backoff, Entry, Fields, Hook, JSONFormatter, Level, Logger, LokiEncoding, LokiHook};
+use prost::Message;
+use serde_json::{json, Value};
+use std::io::Read;
+use std::time::Duration;
+
+/// Loki's `logproto` messages, decoded independently of the hook
+#[derive(Clone, PartialEq, prost::Message)]
+struct PushRequest {
+    #[prost(message, repeated, tag = "1")]
+    streams: Vec<StreamAdapter>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct StreamAdapter {
+    #[prost(string, tag = "1")]
+    labels: String,
+    #[prost(message, repeated, tag = "2")]
+    entries: Vec<EntryAdapter>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct EntryAdapter {
+    #[prost(message, optional, tag = "1")]
+    timestamp: Option<Timestamp>,
+    #[prost(string, tag = "2")]
+    line: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct Timestamp {
+    #[prost(int64, tag = "1")]
+    seconds: i64,
+    #[prost(int32, tag = "2")]
+    nanos: i32,
+}
+
+fn entry<'a>(logger: &'a Logger, level: Level, msg: &str, fields: Fields) -> Entry<'a> {
+    Entry {
+        timestamp: chrono::Utc::now(),
+        level,
+        message: msg.to_string(),
+        fields,
+        logger,
+    }
+}
+
+fn tenant_fields(tenant: &str) -> Fields {
+    let mut fields = Fields::new();
+    fields.insert("tenant".to_string(), json!(tenant));
+    fields.insert("order_id".to_string(), json!(42));
+    fields
+}
+
+#[test]
+fn test_json_push_with_labels() {
+    let server = MockServer::start();
+    let hook = LokiHook::builder(&server.url(""))
+        .encoding(LokiEncoding::Json)
+        .label("app", "billing")
+        .label_field("tenant")
+        .tenant_id("team-a")
+        .batch_size(10)
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    hook.fire(&entry(&logger, Level::Info, "paid", tenant_fields("acme")))
+        .unwrap();
+    hook.fire(&entry(
+        &logger,
+        Level::Info,
+        "refunded",
+        tenant_fields("acme"),
+    ))
+    .unwrap();
+    hook.fire(&entry(
+        &logger,
+        Level::Error,
+        "failed",
+        tenant_fields("globex"),
+    ))
+    .unwrap();
+    hook.flush(Duration::from_secs(5)).unwrap();
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].path, "/loki/api/v1/push");
+    assert_eq!(requests[0].header("x-scope-orgid"), Some("team-a"));
+    assert_eq!(requests[0].header("content-type"), Some("application/json"));
+
+    let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+    let streams = body["streams"].as_array().unwrap();
+    assert_eq!(streams.len(), 2);
+    assert_eq!(
+        streams[0]["stream"],
+        json!({"app": "billing", "level": "info", "tenant": "acme"})
+    );
+    assert_eq!(streams[0]["values"].as_array().unwrap().len(), 2);
+    assert_eq!(
+        streams[0]["values"][0][1],
+        "level=info msg=paid order_id=42"
+    );
+    assert!(streams[0]["values"][0][0].as_str().unwrap().len() >= 19);
+    assert_eq!(streams[1]["stream"]["level"], "error");
+    assert_eq!(streams[1]["stream"]["tenant"], "globex");
+}
+
+#[test]
+fn test_snappy_protobuf_push() {
+    let server = MockServer::start();
+    let hook = LokiHook::builder(&server.url("/loki/api/v1/push"))
+        .label("app", "billing")
+        .formatter(JSONFormatter::new().disable_timestamp(true))
+        .batch_size(1)
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    hook.fire(&entry(&logger, Level::Warn, "slow", tenant_fields("acme")))
+        .unwrap();
+    hook.flush(Duration::from_secs(5)).unwrap();
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].path, "/loki/api/v1/push");
+    assert_eq!(
+        requests[0].header("content-type"),
+        Some("application/x-protobuf")
+    );
+
+    let raw = snap::raw::Decoder::new()
+        .decompress_vec(&requests[0].body)
+        .unwrap();
+    let push = PushRequest::decode(raw.as_slice()).unwrap();
+    assert_eq!(push.streams.len(), 1);
+    assert_eq!(push.streams[0].labels, "{app=\"billing\", level=\"warn\"}");
+    let line: Value = serde_json::from_str(&push.streams[0].entries[0].line).unwrap();
+    assert_eq!(line["message"], "slow");
+    assert_eq!(line["fields"]["tenant"], "acme");
+    assert!(
+        push.streams[0].entries[0]
+            .timestamp
+            .as_ref()
+            .unwrap()
+            .seconds
+            > 0
+    );
+}
+
+#[test]
+fn test_gzip_json_push() {
+    let server = MockServer::start();
+    let hook = LokiHook::builder(&server.url(""))
+        .encoding(LokiEncoding::Json)
+        .gzip(true)
+        .batch_size(1)
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    hook.fire(&entry(&logger, Level::Info, "compressed", Fields::new()))
+        .unwrap();
+    hook.flush(Duration::from_secs(5)).unwrap();
+
+    let requests = server.requests();
+    assert_eq!(requests[0].header("content-encoding"), Some("gzip"));
+    let mut body = String::new();
+    GzDecoder::new(requests[0].body.as_slice())
+        .read_to_string(&mut body)
+        .unwrap();
+    let body: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(
+        body["streams"][0]["values"][0][1],
+        "level=info msg=compressed"
+    );
+}
+
+#[test]
+fn test_label_cardinality_is_bounded() {
+    let server = MockServer::start();
+    let hook = LokiHook::builder(&server.url(""))
+        .encoding(LokiEncoding::Json)
+        .label_field("tenant")
+        .max_streams(1)
+        .batch_size(10)
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    hook.fire(&entry(&logger, Level::Info, "first", tenant_fields("acme")))
+        .unwrap();
+    hook.fire(&entry(
+        &logger,
+        Level::Info,
+        "second",
+        tenant_fields("globex"),
+    ))
+    .unwrap();
+    hook.flush(Duration::from_secs(5)).unwrap();
+
+    let body: Value = serde_json::from_slice(&server.requests()[0].body).unwrap();
+    let streams = body["streams"].as_array().unwrap();
+    assert_eq!(
+        streams[0]["stream"],
+        json!({"level": "info", "tenant": "acme"})
+    );
+    // The second tenant would exceed the stream limit, so it stays in the line
+    assert_eq!(streams[1]["stream"], json!({"level": "info"}));
+    assert_eq!(
+        streams[1]["values"][0][1],
+        "level=info msg=second order_id=42 tenant=globex"
+    );
+}
+
+#[test]
+fn test_retries_with_backoff() {
+    let server = MockServer::start();
+    server.respond_with(503, "busy");
+    server.respond_with(429, "slow down");
+    let hook = LokiHook::builder(&server.url(""))
+        .encoding(LokiEncoding::Json)
+        .retry(
+            Backoff::new()
+                .initial(Duration::from_millis(10))
+                .max_retries(3),
+        )
+        .batch_size(1)
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    hook.fire(&entry(&logger, Level::Info, "eventually", Fields::new()))
+        .unwrap();
+    hook.flush(Duration::from_secs(5)).unwrap();
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 3);
+    assert_eq!(requests[0].body, requests[2].body);
+}
+
+#[test]
+fn test_client_errors_are_not_retried() {
+    let server = MockServer::start();
+    server.respond_with(400, "bad labels");
+    let hook = LokiHook::builder(&server.url(""))
+        .retry(Backoff::new().initial(Duration::from_millis(10)))
+        .batch_size(1)
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    hook.fire(&entry(&logger, Level::Info, "rejected", Fields::new()))
+        .unwrap();
+    let err = hook.flush(Duration::from_secs(5)).unwrap_err();
+    assert!(err.to_string().contains("400"));
+    assert_eq!(server.requests().len(), 1);
+}
@@ -0,0 +1,114 @@
+use loggix::{debug, error, info, try_info, try_warn, warn, JSONFormatter, Level, Logger};
+use serde_json::{json, Value};
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Default)]
+struct Capture(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Capture {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Capture {
+    fn lines(&self) -> Vec<Value> {
+        String::from_utf8(self.0.lock().unwrap().clone())
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+}
+
+fn json_logger() -> (Arc<Logger>, Capture) {
+    let capture = Capture::default();
+    let logger = Logger::new()
+        .level(Level::Debug)
+        .formatter(JSONFormatter::new())
+        .output(capture.clone())
+        .build();
+    (logger, capture)
+}
+
+#[derive(Debug)]
+enum Role {
+    Admin,
+}
+
+#[test]
+fn test_format_arguments() {
+    let (logger, capture) = json_logger();
+    let name = "walrus";
+    info!(logger: logger, "user {} logged in", name);
+    info!(logger: logger, "user {name} logged in again");
+    warn!(logger: &logger, "{} of {} disks full", 2, 3,);
+
+    let lines = capture.lines();
+    assert_eq!(lines[0]["message"], "user walrus logged in");
+    assert_eq!(lines[1]["message"], "user walrus logged in again");
+    assert_eq!(lines[2]["message"], "2 of 3 disks full");
+    assert_eq!(lines[2]["level"], "warn");
+}
+
+#[test]
+fn test_inline_fields() {
+    let (logger, capture) = json_logger();
+    let name = String::from("walrus");
+    info!(logger: logger, user = %name, attempts = 3, role = ?Role::Admin; "login ok");
+    error!(logger: logger, http.status = 503, tags = vec!["db", "primary"]; "query failed after {}ms", 250);
+
+    let lines = capture.lines();
+    assert_eq!(lines[0]["message"], "login ok");
+    assert_eq!(
+        lines[0]["fields"],
+        json!({"user": "walrus", "attempts": 3, "role": "Admin"})
+    );
+    assert_eq!(lines[1]["message"], "query failed after 250ms");
+    assert_eq!(lines[1]["fields"]["http.status"], 503);
+    assert_eq!(lines[1]["fields"]["tags"], json!(["db", "primary"]));
+    // Fields are borrowed, not moved
+    assert_eq!(name, "walrus");
+}
+
+#[test]
+fn test_target_and_expression_messages() {
+    let (logger, capture) = json_logger();
+    let message = String::from("cache warmed");
+    debug!(logger: logger, target: "cache", entries = 128; message);
+    info!(logger: logger, &format!("{} entries", 128));
+
+    let lines = capture.lines();
+    assert_eq!(lines[0]["message"], "cache warmed");
+    assert_eq!(
+        lines[0]["fields"],
+        json!({"target": "cache", "entries": 128})
+    );
+    assert_eq!(lines[1]["message"], "128 entries");
+}
+
+#[test]
+fn test_try_macros_return_the_result() {
+    let (logger, capture) = json_logger();
+    try_info!(logger: logger, job = "backup"; "started {}", 1).unwrap();
+    logger.shutdown(std::time::Duration::from_secs(1)).unwrap();
+    assert!(try_warn!(logger: logger, "too late").is_err());
+    // The plain macros hand the error to the handler instead
+    warn!(logger: logger, "too late");
+
+    assert_eq!(capture.lines().len(), 1);
+}
+
+#[test]
+fn test_global_logger_syntax() {
+    info!("A walrus appears");
+    info!("{} walruses appear", 3);
+    info!(size = 10; "A group of walrus emerges");
+    try_info!(target: "zoo", "feeding time").unwrap();
+}
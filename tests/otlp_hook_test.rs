@@ -0,0 +1,182 @@
+mod common;
+
+use common::MockServer;
+use loggix::{
+    any_value, severity_number, Entry, ExportLogsServiceRequest, Fields, Hook, Level, Logger,
+    OtlpEncoding, OtlpHook,
+};
+use prost::Message;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+fn entry<'a>(logger: &'a Logger, level: Level, msg: &str, fields: Fields) -> Entry<'a> {
+    Entry {
+        timestamp: chrono::Utc::now(),
+        level,
+        message: msg.to_string(),
+        fields,
+        logger,
+    }
+}
+
+fn traced_fields() -> Fields {
+    let mut fields = Fields::new();
+    fields.insert(
+        "trace_id".to_string(),
+        json!("4bf92f3577b34da6a3ce929d0e0e4736"),
+    );
+    fields.insert("span_id".to_string(), json!("00f067aa0ba902b7"));
+    fields.insert("user".to_string(), json!("john"));
+    fields.insert("attempts".to_string(), json!(3));
+    fields
+}
+
+#[test]
+fn test_log_record_conversion() {
+    let hook = OtlpHook::builder("http://127.0.0.1:1/v1/logs")
+        .build()
+        .unwrap();
+    let logger = Logger::new();
+    let record = hook.to_log_record(&entry(&logger, Level::Warn, "disk full", traced_fields()));
+
+    assert_eq!(record.severity_number, severity_number(Level::Warn));
+    assert_eq!(record.severity_number, 13);
+    assert_eq!(record.severity_text, "WARN");
+    assert_eq!(
+        record.body.unwrap().value,
+        Some(any_value::Value::StringValue("disk full".to_string()))
+    );
+    assert_eq!(record.trace_id.len(), 16);
+    assert_eq!(record.trace_id[0], 0x4b);
+    assert_eq!(
+        record.span_id,
+        vec![0x00, 0xf0, 0x67, 0xaa, 0x0b, 0xa9, 0x02, 0xb7]
+    );
+
+    let keys: Vec<&str> = record.attributes.iter().map(|kv| kv.key.as_str()).collect();
+    assert_eq!(keys, vec!["attempts", "user"]);
+    assert_eq!(
+        record.attributes[0].value.as_ref().unwrap().value,
+        Some(any_value::Value::IntValue(3))
+    );
+}
+
+#[test]
+fn test_invalid_trace_id_stays_an_attribute() {
+    let hook = OtlpHook::builder("http://127.0.0.1:1/v1/logs")
+        .build()
+        .unwrap();
+    let logger = Logger::new();
+    let mut fields = Fields::new();
+    fields.insert("trace_id".to_string(), json!("not-a-trace"));
+    let record = hook.to_log_record(&entry(&logger, Level::Info, "hello", fields));
+
+    assert!(record.trace_id.is_empty());
+    assert_eq!(record.attributes[0].key, "trace_id");
+}
+
+#[test]
+fn test_protobuf_export() {
+    let server = MockServer::start();
+    let hook = OtlpHook::builder(&server.url("/v1/logs"))
+        .header("x-api-key", "secret")
+        .resource_attribute("service.name", "billing")
+        .batch_size(10)
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    hook.fire(&entry(&logger, Level::Info, "first", traced_fields()))
+        .unwrap();
+    hook.fire(&entry(&logger, Level::Error, "second", Fields::new()))
+        .unwrap();
+    hook.flush(Duration::from_secs(5)).unwrap();
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    let request = &requests[0];
+    assert_eq!(request.method, "POST");
+    assert_eq!(request.path, "/v1/logs");
+    assert_eq!(
+        request.header("content-type"),
+        Some("application/x-protobuf")
+    );
+    assert_eq!(request.header("x-api-key"), Some("secret"));
+
+    let decoded = ExportLogsServiceRequest::decode(request.body.as_slice()).unwrap();
+    let resource_logs = &decoded.resource_logs[0];
+    let resource = resource_logs.resource.as_ref().unwrap();
+    assert_eq!(resource.attributes[0].key, "service.name");
+    let scope_logs = &resource_logs.scope_logs[0];
+    assert_eq!(scope_logs.scope.as_ref().unwrap().name, "loggix");
+    assert_eq!(scope_logs.log_records.len(), 2);
+    assert_eq!(scope_logs.log_records[0].severity_number, 9);
+    assert_eq!(scope_logs.log_records[1].severity_number, 17);
+    assert_eq!(scope_logs.log_records[0].trace_id.len(), 16);
+}
+
+#[test]
+fn test_json_export_through_logger() {
+    let server = MockServer::start();
+    let hook = OtlpHook::builder(&server.url("/v1/logs"))
+        .encoding(OtlpEncoding::Json)
+        .batch_size(1)
+        .build()
+        .unwrap();
+    let logger = Logger::new().add_hook(hook).build();
+
+    logger
+        .log(Level::Info, "user logged in", traced_fields())
+        .unwrap();
+
+    let requests = server.wait_for_requests(1, Duration::from_secs(5));
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].header("content-type"), Some("application/json"));
+
+    let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+    let record = &body["resourceLogs"][0]["scopeLogs"][0]["logRecords"][0];
+    assert_eq!(record["severityNumber"], 9);
+    assert_eq!(record["severityText"], "INFO");
+    assert_eq!(record["body"]["stringValue"], "user logged in");
+    assert_eq!(record["traceId"], "4bf92f3577b34da6a3ce929d0e0e4736");
+    assert_eq!(record["spanId"], "00f067aa0ba902b7");
+    assert_eq!(record["attributes"][0]["key"], "attempts");
+    assert_eq!(record["attributes"][0]["value"]["intValue"], "3");
+    assert!(record["timeUnixNano"].is_string());
+}
+
+#[test]
+fn test_failed_export_does_not_block_logging() {
+    let server = MockServer::start();
+    server.respond_with(503, "unavailable");
+    let hook = OtlpHook::builder(&server.url("/v1/logs"))
+        .batch_size(1)
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    hook.fire(&entry(&logger, Level::Info, "dropped", Fields::new()))
+        .unwrap();
+    let err = hook.flush(Duration::from_secs(5)).unwrap_err();
+    assert!(err.to_string().contains("503"));
+    assert_eq!(server.requests().len(), 1);
+
+    // The failure is reported once
+    hook.flush(Duration::from_secs(5)).unwrap();
+}
+
+#[test]
+fn test_failed_export_fails_shutdown() {
+    let server = MockServer::start();
+    server.respond_with(500, "internal error");
+    let hook = OtlpHook::builder(&server.url("/v1/logs"))
+        .batch_size(10)
+        .build()
+        .unwrap();
+    let logger = Logger::new().output(std::io::sink()).add_hook(hook).build();
+
+    logger.log(Level::Info, "lost", Fields::new()).unwrap();
+    let err = logger.shutdown(Duration::from_secs(5)).unwrap_err();
+    assert!(err.to_string().contains("500"));
+    assert_eq!(server.requests().len(), 1);
+}
@@ -0,0 +1,123 @@
+use loggix::{Backoff, Entry, Fields, Hook, Level, Logger, LoggixError, PermanentError, RetryHook};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+/// A hook that fails its first `failures` calls
+struct FailingHook {
+    failures: u32,
+    permanent: bool,
+    calls: AtomicU32,
+}
+
+impl FailingHook {
+    fn new(failures: u32) -> Self {
+        Self {
+            failures,
+            permanent: false,
+            calls: AtomicU32::new(0),
+        }
+    }
+
+    fn permanent() -> Self {
+        Self {
+            permanent: true,
+            ..Self::new(u32::MAX)
+        }
+    }
+
+    fn calls(&self) -> u32 {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+impl Hook for FailingHook {
+    fn levels(&self) -> Vec<Level> {
+        vec![Level::Error]
+    }
+
+    fn fire(&self, _entry: &Entry) -> Result<(), LoggixError> {
+        let call = self.calls.fetch_add(1, Ordering::SeqCst);
+        if call >= self.failures {
+            Ok(())
+        } else if self.permanent {
+            Err(LoggixError::hook(
+                "FailingHook",
+                PermanentError::new("payload rejected"),
+            ))
+        } else {
+            Err(LoggixError::hook(
+                "FailingHook",
+                format!("connection refused (call {})", call + 1),
+            ))
+        }
+    }
+}
+
+fn entry(logger: &Logger) -> Entry<'_> {
+    Entry {
+        timestamp: chrono::Utc::now(),
+        level: Level::Error,
+        message: "disk full".to_string(),
+        fields: Fields::new(),
+        logger,
+    }
+}
+
+fn fast(retries: u32) -> Backoff {
+    Backoff::new()
+        .initial(Duration::from_millis(1))
+        .max_retries(retries)
+        .jitter(false)
+}
+
+#[test]
+fn test_retries_until_success() {
+    let hook = RetryHook::new(FailingHook::new(2)).backoff(fast(5));
+    assert_eq!(hook.levels(), vec![Level::Error]);
+    let logger = Logger::new();
+    hook.fire(&entry(&logger)).unwrap();
+    assert_eq!(hook.inner().calls(), 3);
+}
+
+#[test]
+fn test_gives_up_after_max_retries() {
+    let hook = RetryHook::new(FailingHook::new(10)).backoff(fast(3));
+    let logger = Logger::new();
+    let err = hook.fire(&entry(&logger)).unwrap_err();
+    assert_eq!(hook.inner().calls(), 4);
+    assert_eq!(
+        err.to_string(),
+        "FailingHook: connection refused (call 4) (gave up after 3 retries)"
+    );
+}
+
+#[test]
+fn test_permanent_errors_are_not_retried() {
+    let hook = RetryHook::new(FailingHook::permanent()).backoff(fast(5));
+    let logger = Logger::new();
+    let err = hook.fire(&entry(&logger)).unwrap_err();
+    assert_eq!(hook.inner().calls(), 1);
+    assert_eq!(err.to_string(), "FailingHook: payload rejected");
+    assert!(matches!(
+        err,
+        LoggixError::Hook { ref source, .. } if source.is::<PermanentError>()
+    ));
+}
+
+#[test]
+fn test_custom_classifier() {
+    let hook = RetryHook::new(FailingHook::new(10))
+        .backoff(fast(5))
+        .retry_if(|err| !err.to_string().contains("refused"));
+    let logger = Logger::new();
+    assert!(hook.fire(&entry(&logger)).is_err());
+    assert_eq!(hook.inner().calls(), 1);
+}
+
+#[tokio::test]
+async fn test_async_retries() {
+    let hook = RetryHook::new(FailingHook::new(3)).backoff(fast(5));
+    let logger = Logger::new();
+    hook.fire_async(&entry(&logger)).await.unwrap();
+    assert_eq!(hook.inner().calls(), 4);
+}
@@ -0,0 +1,262 @@
+use loggix::{BatchingHook, Entry, Fields, Hook, Level, Logger, LoggixError, SpoolHook};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A hook that records calls to it as events
+#[derive(Clone, Default)]
+struct EventHook {
+    events: Arc<Mutex<Vec<String>>>,
+    fire_delay: Duration,
+    flush_error: Option<&'static str>,
+}
+
+impl EventHook {
+    fn events(&self) -> Vec<String> {
+        self.events.lock().unwrap().clone()
+    }
+}
+
+impl Hook for EventHook {
+    fn levels(&self) -> Vec<Level> {
+        vec![Level::Info]
+    }
+
+    fn fire(&self, entry: &Entry) -> Result<(), LoggixError> {
+        std::thread::sleep(self.fire_delay);
+        self.events
+            .lock()
+            .unwrap()
+            .push(format!("fire {}", entry.message));
+        Ok(())
+    }
+
+    fn flush(&self, _timeout: Duration) -> Result<(), LoggixError> {
+        self.events.lock().unwrap().push("flush".to_string());
+        match self.flush_error {
+            Some(err) => Err(LoggixError::hook("EventHook", err)),
+            None => Ok(()),
+        }
+    }
+
+    fn flush_async(
+        &self,
+        _timeout: Duration,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), LoggixError>> + Send + '_>>
+    {
+        Box::pin(async move {
+            self.events.lock().unwrap().push("flush async".to_string());
+            Ok(())
+        })
+    }
+}
+
+/// A hook that delivers once its gate is opened, waiting for that up to a
+/// limit, so it can only succeed if something else gets to run
+#[derive(Clone, Default)]
+struct GatedHook {
+    down: Arc<AtomicBool>,
+    open: Arc<AtomicBool>,
+    delivered: Arc<Mutex<Vec<String>>>,
+}
+
+impl GatedHook {
+    fn deliver(&self, entry: &Entry) -> Result<(), LoggixError> {
+        if !self.open.load(Ordering::SeqCst) {
+            return Err(LoggixError::hook("GatedHook", "gate stayed closed"));
+        }
+        self.delivered.lock().unwrap().push(entry.message.clone());
+        Ok(())
+    }
+}
+
+impl Hook for GatedHook {
+    fn levels(&self) -> Vec<Level> {
+        vec![Level::Info]
+    }
+
+    fn fire(&self, entry: &Entry) -> Result<(), LoggixError> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !self.open.load(Ordering::SeqCst) && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        self.deliver(entry)
+    }
+
+    fn fire_async<'a>(
+        &'a self,
+        entry: &'a Entry,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), LoggixError>> + Send + 'a>>
+    {
+        Box::pin(async move {
+            if self.down.load(Ordering::SeqCst) {
+                return Err(LoggixError::hook("GatedHook", "destination unavailable"));
+            }
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while !self.open.load(Ordering::SeqCst) && Instant::now() < deadline {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+            self.deliver(entry)
+        })
+    }
+}
+
+fn logger(hooks: &[EventHook]) -> Logger {
+    hooks
+        .iter()
+        .fold(Logger::new().output(std::io::sink()), |logger, hook| {
+            logger.add_hook(hook.clone())
+        })
+}
+
+#[test]
+fn test_shutdown_flushes_hooks_and_stops_logging() {
+    let hook = EventHook::default();
+    let logger = logger(std::slice::from_ref(&hook));
+    logger.log(Level::Info, "before", Fields::new()).unwrap();
+
+    logger.shutdown(Duration::from_secs(1)).unwrap();
+    assert!(logger.is_shut_down());
+    assert_eq!(hook.events(), vec!["fire before", "flush"]);
+
+    let err = logger.log(Level::Info, "after", Fields::new()).unwrap_err();
+    assert_eq!(err.to_string(), "logger has been shut down");
+    drop(logger);
+    // Dropping a logger that was shut down does not flush again
+    assert_eq!(hook.events(), vec!["fire before", "flush"]);
+}
+
+#[test]
+fn test_shutdown_reports_every_failing_hook() {
+    let failing = EventHook {
+        flush_error: Some("broker unreachable"),
+        ..EventHook::default()
+    };
+    let healthy = EventHook::default();
+    let logger = logger(&[failing.clone(), healthy.clone(), failing.clone()]);
+
+    let err = logger.shutdown(Duration::from_secs(1)).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "logger did not flush completely: hook 0: EventHook: broker unreachable; hook 2: EventHook: broker unreachable"
+    );
+    let LoggixError::Shutdown(report) = err else {
+        panic!("expected a shutdown error");
+    };
+    assert_eq!(
+        report.hooks,
+        vec![
+            (0, "EventHook: broker unreachable".to_string()),
+            (2, "EventHook: broker unreachable".to_string())
+        ]
+    );
+    assert_eq!(report.in_flight, 0);
+    assert_eq!(healthy.events(), vec!["flush"]);
+}
+
+#[test]
+fn test_shutdown_waits_for_entries_being_logged() {
+    let hook = EventHook {
+        fire_delay: Duration::from_millis(200),
+        ..EventHook::default()
+    };
+    let logger = logger(std::slice::from_ref(&hook)).build();
+
+    let background = {
+        let logger = Arc::clone(&logger);
+        std::thread::spawn(move || logger.log(Level::Info, "slow", Fields::new()).is_ok())
+    };
+    std::thread::sleep(Duration::from_millis(50));
+    logger.shutdown(Duration::from_secs(5)).unwrap();
+    assert!(background.join().unwrap());
+    assert_eq!(hook.events(), vec!["fire slow", "flush"]);
+}
+
+#[test]
+fn test_guard_shuts_down_on_drop() {
+    let hook = EventHook::default();
+    let (logger, guard) =
+        logger(std::slice::from_ref(&hook)).build_with_guard(Duration::from_secs(1));
+    logger.log(Level::Info, "working", Fields::new()).unwrap();
+    assert!(!guard.logger().is_shut_down());
+
+    drop(guard);
+    assert!(logger.is_shut_down());
+    assert_eq!(hook.events(), vec!["fire working", "flush"]);
+}
+
+#[tokio::test]
+async fn test_shutdown_async() {
+    let hook = EventHook::default();
+    let logger = logger(std::slice::from_ref(&hook));
+    logger
+        .log_async(Level::Info, "async", Fields::new())
+        .await
+        .unwrap();
+
+    logger.shutdown_async(Duration::from_secs(1)).await.unwrap();
+    assert_eq!(hook.events(), vec!["fire async", "flush async"]);
+    assert!(logger
+        .log_async(Level::Info, "late", Fields::new())
+        .await
+        .is_err());
+}
+
+/// Opens `gate` from a task on the calling runtime, which only runs while
+/// that runtime is not blocked
+fn open_soon(gate: &GatedHook) -> tokio::task::JoinHandle<()> {
+    let open = Arc::clone(&gate.open);
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        open.store(true, Ordering::SeqCst);
+    })
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn test_shutdown_async_does_not_block_the_runtime() {
+    let gate = GatedHook::default();
+    let batching = BatchingHook::builder(gate.clone())
+        .batch_timeout(Duration::from_secs(60))
+        .build()
+        .unwrap();
+    let logger = Logger::new()
+        .output(std::io::sink())
+        .add_hook(batching)
+        .build();
+    logger
+        .log_async(Level::Info, "batched", Fields::new())
+        .await
+        .unwrap();
+
+    let opener = open_soon(&gate);
+    logger.shutdown_async(Duration::from_secs(2)).await.unwrap();
+    opener.await.unwrap();
+    assert_eq!(*gate.delivered.lock().unwrap(), vec!["batched"]);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn test_spool_shutdown_async_does_not_block_the_runtime() {
+    let dir = std::env::temp_dir().join(format!("loggix-shutdown-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let gate = GatedHook::default();
+    let spool = SpoolHook::builder(gate.clone(), &dir)
+        .sync(false)
+        .build()
+        .unwrap();
+    let logger = Logger::new()
+        .output(std::io::sink())
+        .add_hook(spool)
+        .build();
+    gate.down.store(true, Ordering::SeqCst);
+    logger
+        .log_async(Level::Info, "spooled", Fields::new())
+        .await
+        .unwrap();
+    gate.down.store(false, Ordering::SeqCst);
+
+    let opener = open_soon(&gate);
+    logger.shutdown_async(Duration::from_secs(2)).await.unwrap();
+    opener.await.unwrap();
+    assert_eq!(*gate.delivered.lock().unwrap(), vec!["spooled"]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
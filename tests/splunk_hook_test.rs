@@ -0,0 +1,189 @@
+mod common;
+
+use chrono::TimeZone;
+use common::MockServer;
+use loggix::{Backoff, Entry, Fields, Hook, Level, Logger, SplunkHook};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+const SUCCESS: &str = r#"{"text":"Success","code":0}"#;
+
+fn entry<'a>(logger: &'a Logger, level: Level, msg: &str, fields: Fields) -> Entry<'a> {
+    Entry {
+        timestamp: chrono::Utc.timestamp_millis_opt(1_743_935_400_250).unwrap(),
+        level,
+        message: msg.to_string(),
+        fields,
+        logger,
+    }
+}
+
+/// Split a batched HEC body into its event objects
+fn events(body: &[u8]) -> Vec<Value> {
+    serde_json::Deserializer::from_slice(body)
+        .into_iter::<Value>()
+        .map(|event| event.unwrap())
+        .collect()
+}
+
+#[test]
+fn test_event_envelope() {
+    let server = MockServer::start();
+    server.respond_with(200, SUCCESS);
+    let hook = SplunkHook::builder(&server.url(""), "abc-123")
+        .host("web-1")
+        .source("billing")
+        .sourcetype("_json")
+        .index("security")
+        .batch_size(2)
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    let mut fields = Fields::new();
+    fields.insert("user".to_string(), json!("john"));
+    hook.fire(&entry(&logger, Level::Warn, "denied", fields))
+        .unwrap();
+    hook.fire(&entry(&logger, Level::Info, "allowed", Fields::new()))
+        .unwrap();
+    hook.flush(Duration::from_secs(5)).unwrap();
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].path, "/services/collector/event");
+    assert_eq!(requests[0].header("authorization"), Some("Splunk abc-123"));
+
+    let events = events(&requests[0].body);
+    assert_eq!(events.len(), 2);
+    assert_eq!(
+        events[0],
+        json!({
+            "time": 1_743_935_400.25,
+            "host": "web-1",
+            "source": "billing",
+            "sourcetype": "_json",
+            "index": "security",
+            "event": {"message": "denied", "level": "warn", "fields": {"user": "john"}}
+        })
+    );
+    assert_eq!(events[1]["event"]["message"], "allowed");
+}
+
+#[test]
+fn test_default_host_is_hostname() {
+    let hook = SplunkHook::builder("http://127.0.0.1:1", "token")
+        .build()
+        .unwrap();
+    let logger = Logger::new();
+    let event = hook.to_event(&entry(&logger, Level::Info, "hello", Fields::new()));
+    assert!(!event["host"].as_str().unwrap().is_empty());
+    assert!(event.get("index").is_none());
+}
+
+#[test]
+fn test_indexer_acknowledgements() {
+    let server = MockServer::start();
+    server.respond_with(200, r#"{"text":"Success","code":0,"ackId":7}"#);
+    server.respond_with(200, r#"{"acks":{"7":false}}"#);
+    server.respond_with(200, r#"{"acks":{"7":true}}"#);
+    let hook = SplunkHook::builder(&server.url("/services/collector/event"), "token")
+        .acknowledgements(true)
+        .ack_poll_interval(Duration::from_millis(10))
+        .batch_size(1)
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    hook.fire(&entry(&logger, Level::Info, "indexed", Fields::new()))
+        .unwrap();
+    hook.flush(Duration::from_secs(5)).unwrap();
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 3);
+    let channel = requests[0].header("x-splunk-request-channel").unwrap();
+    assert_eq!(channel.len(), 36);
+    assert_eq!(requests[1].path, "/services/collector/ack");
+    assert_eq!(
+        requests[1].header("x-splunk-request-channel"),
+        Some(channel)
+    );
+    let body: Value = serde_json::from_slice(&requests[1].body).unwrap();
+    assert_eq!(body, json!({"acks": [7]}));
+}
+
+#[test]
+fn test_unconfirmed_batch_is_resent() {
+    let server = MockServer::start();
+    for ack_id in 1..=3 {
+        server.respond_with(
+            200,
+            &format!(r#"{{"text":"Success","code":0,"ackId":{}}}"#, ack_id),
+        );
+        server.respond_with(200, &format!(r#"{{"acks":{{"{}":false}}}}"#, ack_id));
+    }
+    // A poll interval longer than the ack timeout means one poll per send
+    let hook = SplunkHook::builder(&server.url("/services/collector/event"), "token")
+        .acknowledgements(true)
+        .ack_timeout(Duration::from_millis(10))
+        .ack_poll_interval(Duration::from_secs(1))
+        .retry(
+            Backoff::new()
+                .initial(Duration::from_millis(10))
+                .max_retries(2),
+        )
+        .batch_size(1)
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    hook.fire(&entry(&logger, Level::Info, "unconfirmed", Fields::new()))
+        .unwrap();
+    let err = hook.flush(Duration::from_secs(5)).unwrap_err();
+    assert!(err.to_string().contains("ack 3 not confirmed"));
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 6);
+    for (i, pair) in requests.chunks(2).enumerate() {
+        assert_eq!(pair[0].path, "/services/collector/event");
+        assert_eq!(events(&pair[0].body), events(&requests[0].body));
+        assert_eq!(pair[1].path, "/services/collector/ack");
+        let body: Value = serde_json::from_slice(&pair[1].body).unwrap();
+        assert_eq!(body, json!({"acks": [i + 1]}));
+    }
+}
+
+#[test]
+fn test_busy_collector_is_retried() {
+    let server = MockServer::start();
+    server.respond_with(503, r#"{"text":"Server is busy","code":9}"#);
+    server.respond_with(200, SUCCESS);
+    let hook = SplunkHook::builder(&server.url(""), "token")
+        .retry(Backoff::new().initial(Duration::from_millis(10)))
+        .batch_size(1)
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    hook.fire(&entry(&logger, Level::Info, "retried", Fields::new()))
+        .unwrap();
+    hook.flush(Duration::from_secs(5)).unwrap();
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[test]
+fn test_invalid_token_is_not_retried() {
+    let server = MockServer::start();
+    server.respond_with(403, r#"{"text":"Invalid token","code":4}"#);
+    let hook = SplunkHook::builder(&server.url(""), "wrong")
+        .retry(Backoff::new().initial(Duration::from_millis(10)))
+        .batch_size(1)
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    hook.fire(&entry(&logger, Level::Info, "rejected", Fields::new()))
+        .unwrap();
+    let err = hook.flush(Duration::from_secs(5)).unwrap_err();
+    assert!(err.to_string().contains("Invalid token"));
+    assert_eq!(server.requests().len(), 1);
+}
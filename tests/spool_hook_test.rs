@@ -0,0 +1,383 @@
+use loggix::{Backoff, Entry, Fields, Hook, Level, Logger, LoggixError, SpoolHook};
+use serde_json::json;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// A hook that accepts `capacity` more entries, records them, and fails
+/// after that
+#[derive(Clone, Default)]
+struct FlakyHook {
+    capacity: Arc<AtomicUsize>,
+    delivered: Arc<Mutex<Vec<String>>>,
+}
+
+impl FlakyHook {
+    fn set_up(&self, up: bool) {
+        self.allow(if up { usize::MAX } else { 0 });
+    }
+
+    fn allow(&self, entries: usize) {
+        self.capacity.store(entries, Ordering::SeqCst);
+    }
+
+    fn delivered(&self) -> Vec<String> {
+        self.delivered.lock().unwrap().clone()
+    }
+}
+
+impl Hook for FlakyHook {
+    fn levels(&self) -> Vec<Level> {
+        vec![Level::Info, Level::Error]
+    }
+
+    fn fire(&self, entry: &Entry) -> Result<(), LoggixError> {
+        self.capacity
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .map_err(|_| LoggixError::hook("FlakyHook", "destination unavailable"))?;
+        self.delivered.lock().unwrap().push(entry.message.clone());
+        Ok(())
+    }
+}
+
+/// A hook that can only deliver through `fire_async`
+struct AsyncOnlyHook(FlakyHook);
+
+impl Hook for AsyncOnlyHook {
+    fn levels(&self) -> Vec<Level> {
+        self.0.levels()
+    }
+
+    fn requires_async(&self) -> bool {
+        true
+    }
+
+    fn fire(&self, _entry: &Entry) -> Result<(), LoggixError> {
+        Err(LoggixError::hook("AsyncOnlyHook", "use fire_async"))
+    }
+
+    fn fire_async<'a>(
+        &'a self,
+        entry: &'a Entry,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), LoggixError>> + Send + 'a>>
+    {
+        Box::pin(async move { self.0.fire(entry) })
+    }
+}
+
+/// Tells a test that delivery has stalled, and waits for its release
+type Stall = (mpsc::Sender<()>, mpsc::Receiver<()>);
+
+/// A hook that fails while down, and once up holds its first delivery
+/// until released
+#[derive(Clone)]
+struct StallingHook {
+    up: Arc<AtomicBool>,
+    stall: Arc<Mutex<Option<Stall>>>,
+    delivered: Arc<Mutex<Vec<String>>>,
+}
+
+impl StallingHook {
+    /// The hook, a receiver told when the delivery stalls, and a sender
+    /// releasing it
+    fn new() -> (Self, mpsc::Receiver<()>, mpsc::Sender<()>) {
+        let (entered, stalled) = mpsc::channel();
+        let (release, released) = mpsc::channel();
+        let hook = StallingHook {
+            up: Arc::default(),
+            stall: Arc::new(Mutex::new(Some((entered, released)))),
+            delivered: Arc::default(),
+        };
+        (hook, stalled, release)
+    }
+}
+
+impl Hook for StallingHook {
+    fn levels(&self) -> Vec<Level> {
+        vec![Level::Info]
+    }
+
+    fn fire(&self, entry: &Entry) -> Result<(), LoggixError> {
+        if !self.up.load(Ordering::SeqCst) {
+            return Err(LoggixError::hook("StallingHook", "destination unavailable"));
+        }
+        let stall = self.stall.lock().unwrap().take();
+        if let Some((entered, released)) = stall {
+            entered.send(()).unwrap();
+            released.recv().unwrap();
+        }
+        self.delivered.lock().unwrap().push(entry.message.clone());
+        Ok(())
+    }
+}
+
+fn spool_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("loggix-spool-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+fn entry<'a>(logger: &'a Logger, message: &str) -> Entry<'a> {
+    let mut fields = Fields::new();
+    fields.insert("account".to_string(), json!("acme"));
+    Entry {
+        timestamp: chrono::Utc::now(),
+        level: Level::Info,
+        message: message.to_string(),
+        fields,
+        logger,
+    }
+}
+
+fn no_delay() -> Backoff {
+    Backoff::new().initial(Duration::ZERO).jitter(false)
+}
+
+#[test]
+fn test_spools_while_down_and_replays_in_order() {
+    let dir = spool_dir("order");
+    let flaky = FlakyHook::default();
+    let hook = SpoolHook::builder(flaky.clone(), &dir)
+        .retry(no_delay())
+        .build()
+        .unwrap();
+    assert_eq!(hook.levels(), vec![Level::Info, Level::Error]);
+
+    let logger = Logger::new();
+    hook.fire(&entry(&logger, "one")).unwrap();
+    hook.fire(&entry(&logger, "two")).unwrap();
+    assert_eq!(hook.pending(), 2);
+    assert!(flaky.delivered().is_empty());
+
+    // Back up: the new entry goes behind the spooled ones
+    flaky.set_up(true);
+    hook.fire(&entry(&logger, "three")).unwrap();
+    assert_eq!(flaky.delivered(), vec!["one", "two", "three"]);
+    assert_eq!(hook.pending(), 0);
+    assert_eq!(hook.disk_usage(), 0);
+
+    hook.fire(&entry(&logger, "four")).unwrap();
+    assert_eq!(flaky.delivered().len(), 4);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_spooled_entries_survive_restart() {
+    let dir = spool_dir("restart");
+    let logger = Logger::new();
+    {
+        let hook = SpoolHook::builder(FlakyHook::default(), &dir)
+            .segment_size(256)
+            .build()
+            .unwrap();
+        for message in ["a", "b", "c", "d", "e"] {
+            hook.fire(&entry(&logger, message)).unwrap();
+        }
+        assert_eq!(hook.pending(), 5);
+        assert!(hook.flush(Duration::from_millis(100)).is_err());
+    }
+
+    // Only part of the backlog gets through after a restart
+    let flaky = FlakyHook::default();
+    flaky.allow(3);
+    {
+        let hook = SpoolHook::builder(flaky.clone(), &dir)
+            .segment_size(256)
+            .build()
+            .unwrap();
+        assert_eq!(hook.pending(), 5);
+        assert!(hook.flush(Duration::from_secs(5)).is_err());
+        assert_eq!(hook.pending(), 2);
+    }
+
+    // The rest follows after the next one
+    flaky.set_up(true);
+    let hook = SpoolHook::builder(flaky.clone(), &dir).build().unwrap();
+    assert_eq!(hook.pending(), 2);
+    hook.flush(Duration::from_secs(5)).unwrap();
+    assert_eq!(flaky.delivered(), vec!["a", "b", "c", "d", "e"]);
+    drop(hook);
+
+    // Nothing is replayed twice
+    let hook = SpoolHook::builder(flaky.clone(), &dir).build().unwrap();
+    assert_eq!(hook.pending(), 0);
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_disk_budget_drops_oldest_segments() {
+    let dir = spool_dir("budget");
+    let flaky = FlakyHook::default();
+    let hook = SpoolHook::builder(flaky.clone(), &dir)
+        .segment_size(512)
+        .max_bytes(2048)
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    for i in 0..100 {
+        hook.fire(&entry(&logger, &format!("event {}", i))).unwrap();
+    }
+    assert!(hook.disk_usage() <= 2048);
+    assert!(hook.dropped() > 0);
+    let pending = hook.pending();
+    assert_eq!(pending as u64 + hook.dropped(), 100);
+
+    flaky.set_up(true);
+    hook.flush(Duration::from_secs(5)).unwrap();
+    let delivered = flaky.delivered();
+    assert_eq!(delivered.len(), pending);
+    // The newest entries are kept, in order
+    let expected: Vec<String> = (100 - delivered.len()..100)
+        .map(|i| format!("event {}", i))
+        .collect();
+    assert_eq!(delivered, expected);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_disk_budget_filled_during_replay() {
+    let dir = spool_dir("replay-budget");
+    let (stalling, stalled, release) = StallingHook::new();
+    let hook = Arc::new(
+        SpoolHook::builder(stalling.clone(), &dir)
+            .segment_size(512)
+            .max_bytes(1024)
+            .retry(no_delay())
+            .build()
+            .unwrap(),
+    );
+
+    let logger = Logger::new();
+    for i in 0..5 {
+        hook.fire(&entry(&logger, &format!("old {}", i))).unwrap();
+    }
+    assert_eq!(hook.pending(), 5);
+
+    // Replay "old 0" on another thread and hold it there
+    stalling.up.store(true, Ordering::SeqCst);
+    let replayer = {
+        let hook = Arc::clone(&hook);
+        std::thread::spawn(move || {
+            let logger = Logger::new();
+            hook.fire(&entry(&logger, "trigger")).unwrap();
+        })
+    };
+    stalled.recv_timeout(Duration::from_secs(5)).unwrap();
+
+    // Meanwhile the segment holding "old 0" is dropped for newer entries
+    for i in 0..20 {
+        hook.fire(&entry(&logger, &format!("new {}", i))).unwrap();
+    }
+    assert!(hook.dropped() > 0);
+    let waiting = hook.pending();
+    assert_eq!(waiting as u64 + hook.dropped(), 26);
+    release.send(()).unwrap();
+    replayer.join().unwrap();
+    hook.flush(Duration::from_secs(5)).unwrap();
+
+    assert_eq!(hook.pending(), 0);
+    assert_eq!(hook.disk_usage(), 0);
+    let delivered = stalling.delivered.lock().unwrap().clone();
+    // The in-flight entry, no longer counted as dropped, then every entry
+    // that was waiting, in order
+    assert_eq!(delivered.len(), waiting + 1);
+    assert_eq!(delivered.len() as u64 + hook.dropped(), 26);
+    assert_eq!(delivered[0], "old 0");
+    let expected: Vec<String> = (20 - waiting..20).map(|i| format!("new {}", i)).collect();
+    assert_eq!(delivered[1..], expected[..]);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_torn_record_is_discarded_on_open() {
+    let dir = spool_dir("torn");
+    let logger = Logger::new();
+    {
+        let hook = SpoolHook::builder(FlakyHook::default(), &dir)
+            .build()
+            .unwrap();
+        hook.fire(&entry(&logger, "kept")).unwrap();
+        hook.fire(&entry(&logger, "also kept")).unwrap();
+    }
+    // Simulate a crash in the middle of writing a third record
+    let segment = fs::read_dir(&dir)
+        .unwrap()
+        .map(|file| file.unwrap().path())
+        .find(|path| path.extension().is_some_and(|e| e == "wal"))
+        .unwrap();
+    let mut file = OpenOptions::new().append(true).open(&segment).unwrap();
+    file.write_all(&[200, 0, 0, 0, 1, 2, 3, 4, b'{']).unwrap();
+    drop(file);
+
+    let flaky = FlakyHook::default();
+    flaky.set_up(true);
+    let hook = SpoolHook::builder(flaky.clone(), &dir).build().unwrap();
+    assert_eq!(hook.pending(), 2);
+    hook.flush(Duration::from_secs(5)).unwrap();
+    assert_eq!(flaky.delivered(), vec!["kept", "also kept"]);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_async_fire_spools_and_replays() {
+    let dir = spool_dir("async");
+    let flaky = FlakyHook::default();
+    let hook = SpoolHook::builder(flaky.clone(), &dir)
+        .retry(no_delay())
+        .sync(false)
+        .build()
+        .unwrap();
+
+    let logger = Logger::new();
+    hook.fire_async(&entry(&logger, "queued")).await.unwrap();
+    assert_eq!(hook.pending(), 1);
+
+    flaky.set_up(true);
+    hook.fire_async(&entry(&logger, "live")).await.unwrap();
+    assert_eq!(flaky.delivered(), vec!["queued", "live"]);
+    assert_eq!(hook.pending(), 0);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_async_only_hook_is_replayed_through_fire_async() {
+    let dir = spool_dir("async-only");
+    let flaky = FlakyHook::default();
+    let hook = SpoolHook::builder(AsyncOnlyHook(flaky.clone()), &dir)
+        .retry(no_delay())
+        .sync(false)
+        .build()
+        .unwrap();
+    assert!(hook.requires_async());
+
+    let logger = Logger::new();
+    hook.fire(&entry(&logger, "queued")).unwrap();
+    assert_eq!(hook.pending(), 1);
+
+    flaky.set_up(true);
+    hook.fire(&entry(&logger, "live")).unwrap();
+    assert_eq!(flaky.delivered(), vec!["queued", "live"]);
+    assert_eq!(hook.pending(), 0);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_invalid_sizes_are_rejected() {
+    let dir = spool_dir("invalid");
+    assert!(SpoolHook::builder(FlakyHook::default(), &dir)
+        .segment_size(0)
+        .build()
+        .is_err());
+    assert!(SpoolHook::builder(FlakyHook::default(), &dir)
+        .segment_size(1024)
+        .max_bytes(512)
+        .build()
+        .is_err());
+    let _ = fs::remove_dir_all(&dir);
+}